@@ -14,7 +14,13 @@ pub async fn auth_middleware(
 ) -> Result<Response, ApiError> {
     // Skip auth for certain routes
     let path = request.uri().path();
-    if path == "/api/unlock" || path == "/api/status" || path == "/health" || path == "/ws" {
+    if path == "/api/unlock"
+        || path == "/api/unlock/pin"
+        || path == "/api/status"
+        || path == "/api/vaults"
+        || path == "/health"
+        || path == "/ws"
+    {
         return Ok(next.run(request).await);
     }
 
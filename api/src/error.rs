@@ -30,6 +30,12 @@ pub enum ApiError {
 
     #[error("Session expired")]
     SessionExpired,
+
+    #[error("Invalid PIN ({0} attempts left)")]
+    InvalidPin(u32),
+
+    #[error("PIN unlock unavailable: {0}")]
+    PinUnavailable(String),
 }
 
 impl IntoResponse for ApiError {
@@ -42,6 +48,8 @@ impl IntoResponse for ApiError {
             ApiError::VaultLocked => (StatusCode::FORBIDDEN, "Vault is locked"),
             ApiError::InvalidPassword => (StatusCode::UNAUTHORIZED, "Invalid password"),
             ApiError::SessionExpired => (StatusCode::UNAUTHORIZED, "Session expired"),
+            ApiError::InvalidPin(_) => (StatusCode::UNAUTHORIZED, "Invalid PIN"),
+            ApiError::PinUnavailable(_) => (StatusCode::FORBIDDEN, "PIN unlock unavailable"),
         };
 
         let body = Json(json!({
@@ -64,9 +72,22 @@ impl From<securefox_core::Error> for ApiError {
         use securefox_core::Error;
         match err {
             Error::InvalidPassword => ApiError::InvalidPassword,
+            Error::KeyMismatch => ApiError::SessionExpired,
             Error::VaultNotFound => ApiError::NotFound,
-            Error::ItemNotFound(_) => ApiError::NotFound,
+            Error::ItemNotFound(_) | Error::FolderNotFound(_) => ApiError::NotFound,
             Error::InvalidTotp => ApiError::BadRequest("Invalid TOTP secret".to_string()),
+            Error::InvalidPin(remaining) => ApiError::InvalidPin(remaining),
+            Error::PinUnavailable(reason) => ApiError::PinUnavailable(reason),
+            Error::KeyfileRequired
+            | Error::Keyfile(_)
+            | Error::Attachment(_)
+            | Error::Folder(_)
+            | Error::InvalidTag(_)
+            | Error::SshKey(_)
+            | Error::SshAgent(_)
+            | Error::Recovery(_)
+            // Carries the hint on how to open the vault from the CLI
+            | Error::LegacyArgon2Password(_) => ApiError::BadRequest(err.to_string()),
             _ => ApiError::Internal(err.to_string()),
         }
     }
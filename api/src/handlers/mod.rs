@@ -1,49 +1,103 @@
 // handlers/auth.rs
 pub mod auth_impl {
     use crate::{
-        models::{StatusResponse, UnlockRequest, UnlockResponse, VaultSummary},
+        models::{
+            PinUnlockRequest, Session, StatusResponse, UnlockRequest, UnlockResponse, VaultInfo,
+            VaultQuery, VaultSummary,
+        },
         ApiError, AppState, Result,
     };
-    use axum::{extract::State, Json};
+    use axum::{
+        extract::{Extension, Query, State},
+        Json,
+    };
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+    use securefox_core::keyfile::Keyfile;
 
     pub async fn unlock(
         State(state): State<AppState>,
         Json(req): Json<UnlockRequest>,
     ) -> Result<Json<UnlockResponse>> {
-        let session = state.unlock(req.password)?;
+        let keyfile = req
+            .keyfile
+            .map(|encoded| {
+                let contents = BASE64
+                    .decode(encoded.expose_secret())
+                    .map_err(|_| ApiError::BadRequest("Keyfile is not valid base64".to_string()))?;
+                Ok::<_, ApiError>(Keyfile::from_bytes(&contents)?)
+            })
+            .transpose()?;
+        let session = state.unlock(req.vault.as_deref(), req.password.expose_secret(), keyfile)?;
+
+        unlock_response(&state, session)
+    }
 
-        let vault = state.get_vault().ok_or(ApiError::VaultLocked)?;
+    pub async fn unlock_with_pin(
+        State(state): State<AppState>,
+        Json(req): Json<PinUnlockRequest>,
+    ) -> Result<Json<UnlockResponse>> {
+        let session = state.unlock_with_pin(req.vault.as_deref(), req.pin.expose_secret())?;
+
+        unlock_response(&state, session)
+    }
+
+    fn unlock_response(state: &AppState, session: Session) -> Result<Json<UnlockResponse>> {
+        let vault_summary = state.with_vault(&session, |vault| {
+            Ok(VaultSummary {
+                item_count: vault.len(),
+                folder_count: vault.folders()?.len(),
+                last_sync: Some(vault.sync_time()),
+            })
+        })?;
 
         Ok(Json(UnlockResponse {
             token: session.id,
+            vault: session.vault,
             expires_at: session.expires_at,
-            vault_summary: VaultSummary {
-                item_count: vault.items.len(),
-                folder_count: vault.folders.len(),
-                last_sync: Some(vault.sync_time),
-            },
+            vault_summary,
         }))
     }
 
-    pub async fn lock(State(state): State<AppState>) -> Result<Json<StatusResponse>> {
-        state.lock(None);
+    /// Lock the vault of the session
+    pub async fn lock(
+        State(state): State<AppState>,
+        Extension(session): Extension<Session>,
+    ) -> Result<Json<StatusResponse>> {
+        state.lock(Some(&session));
         Ok(Json(StatusResponse {
             locked: true,
             session_valid: false,
             vault_exists: true,
+            pin_available: false,
         }))
     }
 
-    pub async fn status(State(state): State<AppState>) -> Result<Json<StatusResponse>> {
-        let storage =
-            securefox_core::storage::VaultStorage::with_path(state.vault_path.join("vault.sf"));
+    pub async fn status(
+        State(state): State<AppState>,
+        Query(query): Query<VaultQuery>,
+    ) -> Result<Json<StatusResponse>> {
+        let (_, vault_dir) = state.resolve_vault(query.vault.as_deref())?;
+        let storage = securefox_core::storage::VaultStorage::with_path(vault_dir.join("vault.sf"));
+
+        let pin_available = storage
+            .pin_store()
+            .status()
+            .ok()
+            .flatten()
+            .is_some_and(|status| status.armed);
 
         Ok(Json(StatusResponse {
-            locked: state.is_locked(),
-            session_valid: !state.sessions.read().is_empty(),
+            locked: state.is_locked(&vault_dir),
+            session_valid: state.has_session(&vault_dir),
             vault_exists: storage.exists(),
+            pin_available,
         }))
     }
+
+    /// Vaults that can be unlocked
+    pub async fn list_vaults(State(state): State<AppState>) -> Result<Json<Vec<VaultInfo>>> {
+        Ok(Json(state.list_vaults()?))
+    }
 }
 
 // Simplified placeholder handlers for other modules
@@ -56,23 +110,61 @@ pub mod items_impl {
         Json,
     };
     use chrono::Utc;
-    use securefox_core::models::Item;
+    use securefox_core::memory::SealedVault;
+    use securefox_core::models::{Item, ItemType, SshKeyData, Vault};
     use uuid::Uuid;
 
+    /// Item `id`, unless it is in the trash, which `/api/trash` serves
+    pub fn active_item(vault: &SealedVault, id: &str) -> Result<Item> {
+        vault
+            .item(id)?
+            .filter(|item| !item.is_deleted())
+            .ok_or(ApiError::NotFound)
+    }
+
     pub async fn list_items(
         State(state): State<AppState>,
+        Extension(session): Extension<Session>,
         Query(query): Query<ListItemsQuery>,
     ) -> Result<Json<Vec<Item>>> {
-        let vault = state.get_vault().ok_or(ApiError::VaultLocked)?;
-
-        let mut items = vault.items;
+        let mut items = state.with_vault(&session, |vault| {
+            Ok(vault.items().collect::<securefox_core::Result<Vec<_>>>()?)
+        })?;
 
-        // Apply filters
+        // Trashed items are only listed through /trash
+        items.retain(|i| !i.is_deleted());
+
+        // Apply filters; a folder includes the folders nested under it
+        if let Some(folder_id) = query.folder_id {
+            let folders = state.with_vault(&session, |vault| Ok(vault.folders()?))?;
+            let folder = folders
+                .iter()
+                .find(|f| f.id == folder_id)
+                .ok_or(ApiError::NotFound)?;
+            let folder_ids: Vec<_> = folders
+                .iter()
+                .filter(|f| f.is_within(&folder.name))
+                .map(|f| f.id.as_str())
+                .collect();
+            items.retain(|i| {
+                i.folder_id
+                    .as_deref()
+                    .is_some_and(|id| folder_ids.contains(&id))
+            });
+        }
+        if let Some(tag) = query.tag {
+            items.retain(|i| i.has_tag(&tag));
+        }
         if let Some(search) = query.search {
             let search_lower = search.to_lowercase();
             items.retain(|i| i.name.to_lowercase().contains(&search_lower));
         }
 
+        // Private keys are only returned for a single item
+        for ssh_key in items.iter_mut().filter_map(|i| i.ssh_key.as_mut()) {
+            ssh_key.private_key = Default::default();
+        }
+
         Ok(Json(items))
     }
 
@@ -113,60 +205,72 @@ pub mod items_impl {
             }
         }
 
-        // Check for duplicate credentials (URI + username + password)
-        let vault = state.get_vault().ok_or(ApiError::VaultLocked)?;
+        let ssh_key = req.ssh_key.as_ref().map(ssh_key_data).transpose()?;
+        if req.item_type == ItemType::SSH_KEY && ssh_key.is_none() {
+            return Err(ApiError::BadRequest(
+                "SSH key data is required for SSH key items".to_string(),
+            ));
+        }
 
+        // Check for duplicate credentials (URI + username + password)
         if req.item_type == securefox_core::models::ItemType::LOGIN {
             if let Some(ref req_login) = req.login {
                 // Extract request username and password for comparison
                 let req_username = req_login.username.as_deref();
-                let req_password = req_login.password.as_deref();
+                let req_password = req_login.password.as_ref();
                 let req_uris = req_login.uris.as_ref();
 
-                // Check each existing item for duplicates
-                for existing_item in &vault.items {
-                    if existing_item.item_type != securefox_core::models::ItemType::LOGIN {
-                        continue;
-                    }
-
-                    if let Some(ref existing_login) = existing_item.login {
-                        // Check if username matches
-                        if existing_login.username.as_deref() != req_username {
+                // Check each existing item for duplicates, decrypting one at a time
+                let duplicate = state.with_vault(&session, |vault| {
+                    for existing_item in vault.items() {
+                        let existing_item = existing_item?;
+                        if existing_item.item_type != securefox_core::models::ItemType::LOGIN {
                             continue;
                         }
 
-                        // Check if any URI matches
-                        let has_matching_uri = match (req_uris, &existing_login.uris) {
-                            (Some(req_uris), Some(existing_uris)) => {
-                                req_uris.iter().any(|req_uri| {
-                                    existing_uris
-                                        .iter()
-                                        .any(|existing_uri| req_uri.uri == existing_uri.uri)
-                                })
+                        if let Some(ref existing_login) = existing_item.login {
+                            // Check if username matches
+                            if existing_login.username.as_deref() != req_username {
+                                continue;
                             }
-                            _ => false,
-                        };
 
-                        if !has_matching_uri {
-                            continue;
-                        }
+                            // Check if any URI matches
+                            let has_matching_uri = match (req_uris, &existing_login.uris) {
+                                (Some(req_uris), Some(existing_uris)) => {
+                                    req_uris.iter().any(|req_uri| {
+                                        existing_uris
+                                            .iter()
+                                            .any(|existing_uri| req_uri.uri == existing_uri.uri)
+                                    })
+                                }
+                                _ => false,
+                            };
+
+                            if !has_matching_uri {
+                                continue;
+                            }
 
-                        // Found matching URI + username
-                        // Check if password also matches
-                        if existing_login.password.as_deref() == req_password {
-                            // Complete duplicate - return existing item without saving
-                            return Ok(Json(existing_item.clone()));
+                            // Found matching URI + username
+                            // Check if password also matches
+                            if existing_login.password.as_ref() == req_password {
+                                // Complete duplicate - return existing item without saving
+                                return Ok(Some(existing_item));
+                            }
+                            // If password is different, we'll create a new item (not update)
+                            // This allows users to have multiple passwords for the same site
                         }
-                        // If password is different, we'll create a new item (not update)
-                        // This allows users to have multiple passwords for the same site
                     }
+                    Ok(None)
+                })?;
+                if let Some(existing_item) = duplicate {
+                    return Ok(Json(existing_item));
                 }
             }
         }
 
         // No duplicate found - create new item
         let now = Utc::now();
-        let item = Item {
+        let mut item = Item {
             id: Uuid::new_v4().to_string(),
             item_type: req.item_type,
             name: req.name,
@@ -177,16 +281,23 @@ pub mod items_impl {
             card: req.card,
             identity: req.identity,
             secure_note: req.secure_note,
+            ssh_key,
             fields: req.fields,
+            tags: Default::default(),
+            attachments: Vec::new(),
+            password_history: Vec::new(),
             reprompt: req.reprompt,
             creation_date: now,
             revision_date: now,
+            deleted_date: None,
         };
+        item.add_tags(&req.tags.unwrap_or_default())?;
 
         let item_clone = item.clone();
 
         // Save to vault with persistence
         state.update_vault(&session.id, |vault| {
+            check_folder(vault, item.folder_id.as_deref())?;
             vault.add_item(item);
             Ok(())
         })?;
@@ -196,14 +307,10 @@ pub mod items_impl {
 
     pub async fn get_item(
         State(state): State<AppState>,
+        Extension(session): Extension<Session>,
         Path(id): Path<String>,
     ) -> Result<Json<Item>> {
-        let vault = state.get_vault().ok_or(ApiError::VaultLocked)?;
-        let item = vault
-            .items
-            .into_iter()
-            .find(|i| i.id == id)
-            .ok_or(ApiError::NotFound)?;
+        let item = state.with_vault(&session, |vault| active_item(vault, &id))?;
         Ok(Json(item))
     }
 
@@ -213,17 +320,17 @@ pub mod items_impl {
         Path(id): Path<String>,
         Json(req): Json<UpdateItemRequest>,
     ) -> Result<Json<Item>> {
-        let updated_item = state
-            .get_vault()
-            .ok_or(ApiError::VaultLocked)?
-            .items
-            .iter()
-            .find(|i| i.id == id)
-            .ok_or(ApiError::NotFound)?
-            .clone();
+        let updated_item = state.with_vault(&session, |vault| active_item(vault, &id))?;
+
+        // A key without its private part, as listed, keeps the stored key
+        let ssh_key = match req.ssh_key {
+            Some(ssh_key) if ssh_key.private_key.is_empty() => updated_item.ssh_key.clone(),
+            Some(ssh_key) => Some(ssh_key_data(&ssh_key)?),
+            None => None,
+        };
 
         // Build updated item with new revision date
-        let item = Item {
+        let mut item = Item {
             id: updated_item.id,
             item_type: req.item_type,
             name: req.name,
@@ -234,37 +341,342 @@ pub mod items_impl {
             card: req.card,
             identity: req.identity,
             secure_note: req.secure_note,
+            ssh_key,
             fields: req.fields,
+            tags: updated_item.tags,
+            // Attachments are managed through their own routes
+            attachments: updated_item.attachments,
+            // Kept and extended by `Vault::update_item`
+            password_history: Vec::new(),
             reprompt: req.reprompt,
             creation_date: updated_item.creation_date,
             revision_date: Utc::now(),
+            // Kept by `Vault::update_item`; the trash has its own routes
+            deleted_date: updated_item.deleted_date,
         };
+        if let Some(tags) = req.tags {
+            item.tags.clear();
+            item.add_tags(&tags)?;
+        }
 
-        let item_clone = item.clone();
-
-        // Update in vault with persistence
+        // Update in vault with persistence, recording a changed password
+        let mut updated = None;
+        let folder_changed = item.folder_id != updated_item.folder_id;
         state.update_vault(&session.id, |vault| {
-            if let Some(existing) = vault.get_item_mut(&id) {
-                *existing = item;
-                Ok(())
-            } else {
-                Err(ApiError::NotFound)
+            if folder_changed {
+                check_folder(vault, item.folder_id.as_deref())?;
             }
+            vault.update_item(item).ok_or(ApiError::NotFound)?;
+            updated = vault.get_item(&id).cloned();
+            Ok(())
         })?;
 
-        Ok(Json(item_clone))
+        Ok(Json(updated.ok_or(ApiError::NotFound)?))
     }
 
+    /// Move an item to the trash; see `trash` for restoring and purging
     pub async fn delete_item(
         State(state): State<AppState>,
         Extension(session): Extension<Session>,
         Path(id): Path<String>,
     ) -> Result<()> {
         state.update_vault(&session.id, |vault| {
-            vault.remove_item(&id).ok_or(ApiError::NotFound)?;
+            vault.trash_item(&id).ok_or(ApiError::NotFound)?;
+            Ok(())
+        })
+    }
+
+    /// Read the private key of `ssh_key`, deriving its public key and fingerprint
+    fn ssh_key_data(ssh_key: &SshKeyData) -> Result<SshKeyData> {
+        Ok(SshKeyData::from_openssh(
+            ssh_key.private_key.expose_secret(),
+            None,
+        )?)
+    }
+
+    /// Make sure an item is not put into a folder that does not exist
+    fn check_folder(vault: &Vault, folder_id: Option<&str>) -> Result<()> {
+        match folder_id {
+            Some(id) if vault.get_folder(id).is_none() => {
+                Err(ApiError::BadRequest(format!("Unknown folder '{}'", id)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+pub mod tags_impl {
+    use crate::models::Session;
+    use crate::{AppState, Result};
+    use axum::{
+        extract::{Extension, State},
+        Json,
+    };
+    use std::collections::BTreeMap;
+
+    /// Tags of the items outside the trash, with the number of items having each
+    pub async fn list_tags(
+        State(state): State<AppState>,
+        Extension(session): Extension<Session>,
+    ) -> Result<Json<BTreeMap<String, usize>>> {
+        let mut tags = BTreeMap::new();
+        state.with_vault(&session, |vault| {
+            for item in vault.items() {
+                let item = item?;
+                if item.is_deleted() {
+                    continue;
+                }
+                for tag in item.tags {
+                    *tags.entry(tag).or_insert(0) += 1;
+                }
+            }
+            Ok(())
+        })?;
+        Ok(Json(tags))
+    }
+}
+
+pub mod folders_impl {
+    use crate::models::{DeleteFolderQuery, FolderRequest, Session};
+    use crate::{ApiError, AppState, Result};
+    use axum::{
+        extract::{Extension, Path, Query, State},
+        Json,
+    };
+    use securefox_core::{
+        folders::{folder_tree, FolderNode},
+        models::Folder,
+    };
+
+    /// Folders as a tree of nested folders
+    pub async fn list_folders(
+        State(state): State<AppState>,
+        Extension(session): Extension<Session>,
+    ) -> Result<Json<Vec<FolderNode>>> {
+        let folders = state.with_vault(&session, |vault| Ok(vault.folders()?))?;
+        Ok(Json(folder_tree(&folders)))
+    }
+
+    pub async fn create_folder(
+        State(state): State<AppState>,
+        Extension(session): Extension<Session>,
+        Json(req): Json<FolderRequest>,
+    ) -> Result<Json<Folder>> {
+        let mut created = None;
+        state.update_vault(&session.id, |vault| {
+            created = Some(vault.add_folder(&req.name)?.clone());
             Ok(())
         })?;
 
+        Ok(Json(created.ok_or(ApiError::NotFound)?))
+    }
+
+    /// Rename a folder along with the folders nested under it
+    pub async fn rename_folder(
+        State(state): State<AppState>,
+        Extension(session): Extension<Session>,
+        Path(id): Path<String>,
+        Json(req): Json<FolderRequest>,
+    ) -> Result<Json<Folder>> {
+        let mut renamed = None;
+        state.update_vault(&session.id, |vault| {
+            vault.rename_folder(&id, &req.name)?;
+            renamed = vault.get_folder(&id).cloned();
+            Ok(())
+        })?;
+
+        Ok(Json(renamed.ok_or(ApiError::NotFound)?))
+    }
+
+    /// Delete a folder, moving its items into `move_to` or out of any folder
+    pub async fn delete_folder(
+        State(state): State<AppState>,
+        Extension(session): Extension<Session>,
+        Path(id): Path<String>,
+        Query(query): Query<DeleteFolderQuery>,
+    ) -> Result<()> {
+        state.update_vault(&session.id, |vault| {
+            vault.remove_folder(&id, query.move_to.as_deref())?;
+            Ok(())
+        })
+    }
+}
+
+pub mod trash_impl {
+    use crate::models::Session;
+    use crate::{ApiError, AppState, Result};
+    use axum::{
+        extract::{Extension, Path, State},
+        Json,
+    };
+    use securefox_core::models::{Item, Vault};
+
+    pub async fn list_trash(
+        State(state): State<AppState>,
+        Extension(session): Extension<Session>,
+    ) -> Result<Json<Vec<Item>>> {
+        let mut items = state.with_vault(&session, |vault| {
+            Ok(vault.items().collect::<securefox_core::Result<Vec<_>>>()?)
+        })?;
+        items.retain(|i| i.is_deleted());
+        Ok(Json(items))
+    }
+
+    pub async fn restore_item(
+        State(state): State<AppState>,
+        Extension(session): Extension<Session>,
+        Path(id): Path<String>,
+    ) -> Result<Json<Item>> {
+        let mut restored = None;
+        state.update_vault(&session.id, |vault| {
+            trashed(vault, &id)?;
+            restored = vault.restore_item(&id).cloned();
+            Ok(())
+        })?;
+
+        Ok(Json(restored.ok_or(ApiError::NotFound)?))
+    }
+
+    /// Permanently delete one trashed item
+    pub async fn purge_item(
+        State(state): State<AppState>,
+        Extension(session): Extension<Session>,
+        Path(id): Path<String>,
+    ) -> Result<()> {
+        let mut removed = None;
+        state.update_vault(&session.id, |vault| {
+            trashed(vault, &id)?;
+            removed = vault.remove_item(&id);
+            Ok(())
+        })?;
+
+        // Delete the attachment blobs once the vault no longer refers to them
+        if let Some(item) = removed {
+            state.attachments(&session).remove_all(&item.attachments)?;
+        }
+        Ok(())
+    }
+
+    /// Permanently delete every trashed item
+    pub async fn empty_trash(
+        State(state): State<AppState>,
+        Extension(session): Extension<Session>,
+    ) -> Result<()> {
+        let mut removed = Vec::new();
+        state.update_vault(&session.id, |vault| {
+            removed = vault.empty_trash();
+            Ok(())
+        })?;
+
+        let store = state.attachments(&session);
+        for item in &removed {
+            store.remove_all(&item.attachments)?;
+        }
+        Ok(())
+    }
+
+    /// Make sure `id` names a trashed item
+    fn trashed(vault: &Vault, id: &str) -> Result<()> {
+        match vault.get_item(id) {
+            Some(item) if item.is_deleted() => Ok(()),
+            _ => Err(ApiError::NotFound),
+        }
+    }
+}
+
+pub mod attachments_impl {
+    use crate::models::{Session, UploadAttachmentQuery};
+    use crate::{ApiError, AppState, Result};
+    use axum::{
+        body::Bytes,
+        extract::{Extension, Path, Query, State},
+        http::header,
+        response::IntoResponse,
+        Json,
+    };
+    use chrono::Utc;
+    use securefox_core::models::Attachment;
+
+    pub async fn upload_attachment(
+        State(state): State<AppState>,
+        Extension(session): Extension<Session>,
+        Path(id): Path<String>,
+        Query(query): Query<UploadAttachmentQuery>,
+        body: Bytes,
+    ) -> Result<Json<Attachment>> {
+        if query.name.trim().is_empty() || query.name.contains(['/', '\\']) {
+            return Err(ApiError::BadRequest("Invalid file name".to_string()));
+        }
+
+        // Write the blob first, so the vault never points at a missing one
+        let store = state.attachments(&session);
+        let attachment = store.add(&query.name, &body)?;
+
+        let added = attachment.clone();
+        let result = state.update_vault(&session.id, |vault| {
+            let item = vault.get_item_mut(&id).ok_or(ApiError::NotFound)?;
+            item.attachments.push(added);
+            item.revision_date = Utc::now();
+            Ok(())
+        });
+        if let Err(e) = result {
+            let _ = store.remove(&attachment);
+            return Err(e);
+        }
+
+        Ok(Json(attachment))
+    }
+
+    pub async fn download_attachment(
+        State(state): State<AppState>,
+        Extension(session): Extension<Session>,
+        Path((id, attachment_id)): Path<(String, String)>,
+    ) -> Result<impl IntoResponse> {
+        let attachment = state.with_vault(&session, |vault| {
+            vault
+                .item(&id)?
+                .ok_or(ApiError::NotFound)?
+                .attachments
+                .into_iter()
+                .find(|a| a.id == attachment_id)
+                .ok_or(ApiError::NotFound)
+        })?;
+        let contents = state.attachments(&session).read(&attachment)?;
+
+        let disposition = format!(
+            "attachment; filename=\"{}\"",
+            attachment.file_name.replace(['"', '\\'], "_")
+        );
+        Ok((
+            [
+                (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            contents.to_vec(),
+        ))
+    }
+
+    pub async fn delete_attachment(
+        State(state): State<AppState>,
+        Extension(session): Extension<Session>,
+        Path((id, attachment_id)): Path<(String, String)>,
+    ) -> Result<()> {
+        let mut removed = None;
+        state.update_vault(&session.id, |vault| {
+            let item = vault.get_item_mut(&id).ok_or(ApiError::NotFound)?;
+            let position = item
+                .attachments
+                .iter()
+                .position(|a| a.id == attachment_id)
+                .ok_or(ApiError::NotFound)?;
+            removed = Some(item.attachments.remove(position));
+            item.revision_date = Utc::now();
+            Ok(())
+        })?;
+
+        if let Some(attachment) = removed {
+            state.attachments(&session).remove(&attachment)?;
+        }
         Ok(())
     }
 }
@@ -297,7 +709,7 @@ pub mod generate_impl {
         let score = if password.len() >= 16 { 5 } else { 3 };
 
         Ok(Json(GeneratePasswordResponse {
-            password,
+            password: password.into(),
             strength: PasswordStrength {
                 score,
                 label: match score {
@@ -340,10 +752,14 @@ pub mod health_impl {
 }
 
 // Re-export implementations
+pub use attachments_impl as attachments;
 pub use auth_impl as auth;
+pub use folders_impl as folders;
 pub use generate_impl as generate;
 pub use health_impl as health;
 pub use items_impl as items;
+pub use tags_impl as tags;
+pub use trash_impl as trash;
 
 // Placeholder exports for missing modules
 pub mod sync {
@@ -368,42 +784,39 @@ pub mod sync {
 }
 
 pub mod totp {
-    use crate::{models::TotpResponse, ApiError, AppState, Result};
+    use super::items_impl::active_item;
+    use crate::{
+        models::{Session, TotpResponse},
+        ApiError, AppState, Result,
+    };
     use axum::{
-        extract::{Path, State},
+        extract::{Extension, Path, State},
         Json,
     };
 
     pub async fn get_totp(
         State(state): State<AppState>,
+        Extension(session): Extension<Session>,
         Path(id): Path<String>,
     ) -> Result<Json<TotpResponse>> {
-        let vault = state.get_vault().ok_or(ApiError::VaultLocked)?;
-        let item = vault
-            .items
-            .into_iter()
-            .find(|i| i.id == id)
-            .ok_or(ApiError::NotFound)?;
+        let item = state.with_vault(&session, |vault| active_item(vault, &id))?;
 
-        tracing::info!("Found item for TOTP: name={}, id={}", item.name, item.id);
-        tracing::info!("Item login data: {:?}", item.login);
+        tracing::debug!(item_id = %item.id, "Generating TOTP code");
 
         let totp_secret = item
             .login
             .and_then(|l| l.totp)
             .ok_or(ApiError::BadRequest("Item has no TOTP".to_string()))?;
 
-        tracing::info!("TOTP secret from item: {}", totp_secret);
-
         // Parse and validate TOTP secret (handles formatting, whitespace, etc.)
         use securefox_core::totp::{parse_totp_secret, TotpConfig};
-        let cleaned_secret = parse_totp_secret(&totp_secret)
+        let cleaned_secret = parse_totp_secret(totp_secret.expose_secret())
             .map_err(|e| ApiError::BadRequest(format!("Invalid TOTP secret: {}", e)))?;
 
         let config = TotpConfig::new(cleaned_secret);
 
         Ok(Json(TotpResponse {
-            code: config.generate()?,
+            code: config.generate()?.into(),
             ttl: config.ttl(),
         }))
     }
@@ -426,3 +839,120 @@ pub mod websocket {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{items_impl, totp};
+    use crate::models::ListItemsQuery;
+    use crate::{models::Session, ApiError, AppState};
+    use axum::extract::{Extension, Path, Query, State};
+    use securefox_core::crypto::KdfParams;
+    use securefox_core::models::{Item, ItemType, Vault};
+    use securefox_core::storage::VaultStorage;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    /// Unlocked state over `vault`
+    fn unlocked(vault: &Vault) -> (TempDir, AppState, Session) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut kdf_params = KdfParams::pbkdf2();
+        kdf_params.iterations = 1_000;
+        VaultStorage::with_path(temp_dir.path().join("vault.sf"))
+            .save_with_kdf(vault, "password", kdf_params)
+            .unwrap();
+
+        let state = AppState::new(temp_dir.path().to_path_buf(), Duration::from_secs(60));
+        let session = state.unlock(None, "password", None).unwrap();
+        (temp_dir, state, session)
+    }
+
+    #[tokio::test]
+    async fn test_trashed_item_is_not_served() {
+        let mut vault = Vault::new();
+        let mut item = Item::new_login("GitHub");
+        let login = item.login.as_mut().unwrap();
+        login.password = Some("hunter2".into());
+        login.totp = Some("JBSWY3DPEHPK3PXP".into());
+        let id = item.id.clone();
+        vault.add_item(item);
+        vault.trash_item(&id).unwrap();
+        let (_temp_dir, state, session) = unlocked(&vault);
+
+        let item = items_impl::get_item(
+            State(state.clone()),
+            Extension(session.clone()),
+            Path(id.clone()),
+        )
+        .await;
+        assert!(matches!(item, Err(ApiError::NotFound)));
+
+        let code = totp::get_totp(
+            State(state.clone()),
+            Extension(session.clone()),
+            Path(id.clone()),
+        )
+        .await;
+        assert!(matches!(code, Err(ApiError::NotFound)));
+
+        let request = serde_json::from_value(serde_json::json!({
+            "name": "renamed",
+            "type": ItemType::LOGIN,
+        }))
+        .unwrap();
+        let updated = items_impl::update_item(
+            State(state.clone()),
+            Extension(session.clone()),
+            Path(id.clone()),
+            axum::Json(request),
+        )
+        .await;
+        assert!(matches!(updated, Err(ApiError::NotFound)));
+
+        // The item is left untouched in the trash
+        let trashed = state
+            .with_vault(&session, |vault| Ok(vault.item(&id)?.unwrap()))
+            .unwrap();
+        assert_eq!(trashed.name, "GitHub");
+        assert!(trashed.is_deleted());
+    }
+
+    #[tokio::test]
+    async fn test_folder_filter_includes_nested_folders() {
+        let mut vault = Vault::new();
+        let work = vault.add_folder("Work").unwrap().id.clone();
+        let aws = vault.add_folder("Work/Cloud/AWS").unwrap().id.clone();
+        let other = vault.add_folder("Workshop").unwrap().id.clone();
+        for (name, folder_id) in [("mail", &work), ("console", &aws), ("lathe", &other)] {
+            let mut item = Item::new_login(name);
+            item.folder_id = Some(folder_id.clone());
+            vault.add_item(item);
+        }
+        let (_temp_dir, state, session) = unlocked(&vault);
+
+        let list = |folder_id: &str| {
+            items_impl::list_items(
+                State(state.clone()),
+                Extension(session.clone()),
+                Query(ListItemsQuery {
+                    folder_id: Some(folder_id.to_string()),
+                    tag: None,
+                    search: None,
+                    domain: None,
+                }),
+            )
+        };
+
+        let mut names: Vec<_> = list(&work)
+            .await
+            .unwrap()
+            .0
+            .into_iter()
+            .map(|i| i.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["console", "mail"]);
+
+        assert_eq!(list(&aws).await.unwrap().0.len(), 1);
+        assert!(matches!(list("missing").await, Err(ApiError::NotFound)));
+    }
+}
@@ -5,11 +5,13 @@ mod models;
 mod state;
 
 use axum::{
+    extract::DefaultBodyLimit,
     http::Method,
     middleware,
     routing::{delete, get, post, put},
     Router,
 };
+use securefox_core::attachments::MAX_ATTACHMENT_SIZE;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::time::Duration;
@@ -41,7 +43,9 @@ pub fn create_app(vault_path: PathBuf, unlock_timeout: Duration) -> Router {
     // Public auth routes (no authentication required)
     let public_auth_routes = Router::new()
         .route("/unlock", post(handlers::auth::unlock))
+        .route("/unlock/pin", post(handlers::auth::unlock_with_pin))
         .route("/status", get(handlers::auth::status))
+        .route("/vaults", get(handlers::auth::list_vaults))
         .route("/version", get(handlers::health::version));
 
     // Protected routes (authentication required)
@@ -53,6 +57,32 @@ pub fn create_app(vault_path: PathBuf, unlock_timeout: Duration) -> Router {
         .route("/items/:id", get(handlers::items::get_item))
         .route("/items/:id", put(handlers::items::update_item))
         .route("/items/:id", delete(handlers::items::delete_item))
+        // Tag routes
+        .route("/tags", get(handlers::tags::list_tags))
+        // Folder routes
+        .route("/folders", get(handlers::folders::list_folders))
+        .route("/folders", post(handlers::folders::create_folder))
+        .route("/folders/:id", put(handlers::folders::rename_folder))
+        .route("/folders/:id", delete(handlers::folders::delete_folder))
+        // Trash routes
+        .route("/trash", get(handlers::trash::list_trash))
+        .route("/trash", delete(handlers::trash::empty_trash))
+        .route("/trash/:id/restore", post(handlers::trash::restore_item))
+        .route("/trash/:id", delete(handlers::trash::purge_item))
+        // Attachment routes
+        .route(
+            "/items/:id/attachments",
+            post(handlers::attachments::upload_attachment)
+                .layer(DefaultBodyLimit::max(MAX_ATTACHMENT_SIZE)),
+        )
+        .route(
+            "/items/:id/attachments/:attachment_id",
+            get(handlers::attachments::download_attachment),
+        )
+        .route(
+            "/items/:id/attachments/:attachment_id",
+            delete(handlers::attachments::delete_attachment),
+        )
         // TOTP routes
         .route("/items/:id/totp", get(handlers::totp::get_totp))
         // Generator routes
@@ -103,9 +133,11 @@ pub fn create_app(vault_path: PathBuf, unlock_timeout: Duration) -> Router {
 
 pub async fn run(vault_path: PathBuf, host: String, port: u16, unlock_timeout: u64) -> Result<()> {
     // Initialize tracing with better formatting (only if not already initialized)
+    use securefox_core::logging::RedactingWriter;
     use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
 
     let _ = tracing_subscriber::fmt()
+        .with_writer(RedactingWriter::new(std::io::stdout))
         .with_target(false)
         .with_thread_ids(false)
         .with_level(true)
@@ -1,15 +1,32 @@
 use chrono::{DateTime, Utc};
+use securefox_core::models::SecretString;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UnlockRequest {
-    pub password: String,
+    /// Registered vault to unlock; the served vault when absent
+    #[serde(default)]
+    pub vault: Option<String>,
+    pub password: SecretString,
+    /// Base64 encoded keyfile contents; the configured keyfile is used when absent
+    #[serde(default)]
+    pub keyfile: Option<SecretString>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PinUnlockRequest {
+    /// Registered vault to unlock; the served vault when absent
+    #[serde(default)]
+    pub vault: Option<String>,
+    pub pin: SecretString,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UnlockResponse {
     pub token: String,
+    /// Name of the unlocked vault
+    pub vault: String,
     pub expires_at: DateTime<Utc>,
     pub vault_summary: VaultSummary,
 }
@@ -26,6 +43,25 @@ pub struct StatusResponse {
     pub locked: bool,
     pub session_valid: bool,
     pub vault_exists: bool,
+    /// Whether `/api/unlock/pin` can currently unlock the vault
+    #[serde(default)]
+    pub pin_available: bool,
+}
+
+/// Query naming a registered vault; the served vault when absent
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultQuery {
+    pub vault: Option<String>,
+}
+
+/// A vault the service can unlock
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VaultInfo {
+    pub name: String,
+    /// Whether this is the vault unlocked when a request names none
+    pub default: bool,
+    pub exists: bool,
+    pub locked: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -40,7 +76,7 @@ pub struct GeneratePasswordRequest {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GeneratePasswordResponse {
-    pub password: String,
+    pub password: SecretString,
     pub strength: PasswordStrength,
 }
 
@@ -52,17 +88,39 @@ pub struct PasswordStrength {
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TotpResponse {
-    pub code: String,
+    pub code: SecretString,
     pub ttl: u64, // Time to live in seconds
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ListItemsQuery {
     pub folder_id: Option<String>,
+    pub tag: Option<String>,
     pub search: Option<String>,
     pub domain: Option<String>,
 }
 
+/// Request body for creating or renaming a folder
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FolderRequest {
+    /// Full name; `Work/Cloud` nests `Cloud` under `Work`
+    pub name: String,
+}
+
+/// Query of a folder deletion
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteFolderQuery {
+    /// Folder to move the items of the deleted folder into; none when absent
+    pub move_to: Option<String>,
+}
+
+/// Query of an attachment upload; the request body is the file contents
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UploadAttachmentQuery {
+    /// File name to store the attachment under
+    pub name: String,
+}
+
 /// Request body for creating a new item
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateItemRequest {
@@ -74,7 +132,7 @@ pub struct CreateItemRequest {
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub favorite: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub notes: Option<String>,
+    pub notes: Option<SecretString>,
 
     // Type-specific fields
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -85,11 +143,18 @@ pub struct CreateItemRequest {
     pub identity: Option<securefox_core::models::IdentityData>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub secure_note: Option<securefox_core::models::SecureNoteData>,
+    /// SSH key; the public key and fingerprint are derived from the private key
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub ssh_key: Option<securefox_core::models::SshKeyData>,
 
     // Custom fields
     #[serde(skip_serializing_if = "Option::is_none")]
     pub fields: Option<Vec<securefox_core::models::CustomField>>,
 
+    /// Tags of the item; left unchanged on update when absent
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tags: Option<Vec<String>>,
+
     // Metadata
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reprompt: Option<u8>,
@@ -106,7 +171,7 @@ pub struct UpdateItemRequest {
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub favorite: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub notes: Option<String>,
+    pub notes: Option<SecretString>,
 
     // Type-specific fields
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -117,11 +182,18 @@ pub struct UpdateItemRequest {
     pub identity: Option<securefox_core::models::IdentityData>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub secure_note: Option<securefox_core::models::SecureNoteData>,
+    /// SSH key; the public key and fingerprint are derived from the private key
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub ssh_key: Option<securefox_core::models::SshKeyData>,
 
     // Custom fields
     #[serde(skip_serializing_if = "Option::is_none")]
     pub fields: Option<Vec<securefox_core::models::CustomField>>,
 
+    /// Tags of the item; left unchanged on update when absent
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tags: Option<Vec<String>>,
+
     // Metadata
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reprompt: Option<u8>,
@@ -144,19 +216,22 @@ pub struct VersionResponse {
 #[derive(Debug, Clone)]
 pub struct Session {
     pub id: String,
+    /// Name of the vault the session was opened on
+    pub vault: String,
+    pub vault_dir: std::path::PathBuf,
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
-    pub master_password: String, // In production, store key derivation instead
 }
 
 impl Session {
-    pub fn new(master_password: String, timeout: std::time::Duration) -> Self {
+    pub fn new(vault: String, vault_dir: std::path::PathBuf, timeout: std::time::Duration) -> Self {
         let now = Utc::now();
         Self {
             id: Uuid::new_v4().to_string(),
+            vault,
+            vault_dir,
             created_at: now,
             expires_at: now + chrono::Duration::from_std(timeout).unwrap(),
-            master_password,
         }
     }
 
@@ -1,13 +1,38 @@
 use parking_lot::RwLock;
-use securefox_core::{models::Vault, storage::VaultStorage};
-use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
+use securefox_core::{
+    attachments::AttachmentStore,
+    config::{default_vault_dir, ConfigManager},
+    crypto::EncryptionKey,
+    integrity::{self, Severity},
+    keyfile::Keyfile,
+    keyslot::Credentials,
+    memory::{LockedKey, SealedVault},
+    models::{SyncConfigFile, Vault, DEFAULT_PROFILE, DEFAULT_TRASH_RETENTION_DAYS},
+    storage::VaultStorage,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
-use crate::models::Session;
+use crate::models::{Session, VaultInfo};
+
+/// A vault unlocked by the service
+pub struct UnlockedVault {
+    /// Unlocked vault, encrypted in memory under an ephemeral key
+    pub vault: SealedVault,
+    /// Data key of the vault, locked in memory and zeroized when dropped
+    pub key: LockedKey,
+}
 
 #[derive(Clone)]
 pub struct AppState {
+    /// Directory of the vault unlocked when a request names none
     pub vault_path: PathBuf,
-    pub vault: Arc<RwLock<Option<Vault>>>,
+    /// Unlocked vaults by directory
+    pub vaults: Arc<RwLock<HashMap<PathBuf, UnlockedVault>>>,
     pub sessions: Arc<RwLock<HashMap<String, Session>>>,
     pub unlock_timeout: Duration,
 }
@@ -16,23 +41,151 @@ impl AppState {
     pub fn new(vault_path: PathBuf, unlock_timeout: Duration) -> Self {
         Self {
             vault_path,
-            vault: Arc::new(RwLock::new(None)),
+            vaults: Arc::new(RwLock::new(HashMap::new())),
             sessions: Arc::new(RwLock::new(HashMap::new())),
             unlock_timeout,
         }
     }
 
-    pub fn unlock(&self, password: String) -> crate::Result<Session> {
-        let storage = VaultStorage::with_path(self.vault_path.join("vault.sf"));
+    /// Name and directory of the vault registered as `name`, or of the served vault
+    ///
+    /// Profiles are read from the config file on every call, so vaults
+    /// added while the service runs can be unlocked without a restart.
+    pub fn resolve_vault(&self, name: Option<&str>) -> crate::Result<(String, PathBuf)> {
+        let config = load_config();
+        match name {
+            None => {
+                let name = config
+                    .profile_for(&self.vault_path)
+                    .map_or(DEFAULT_PROFILE, |(name, _)| name);
+                Ok((name.to_string(), self.vault_path.clone()))
+            }
+            Some(DEFAULT_PROFILE) => Ok((DEFAULT_PROFILE.to_string(), default_vault_dir()?)),
+            Some(name) => config
+                .vaults
+                .get(name)
+                .map(|profile| (name.to_string(), profile.path.clone()))
+                .ok_or_else(|| crate::ApiError::BadRequest(format!("Unknown vault '{}'", name))),
+        }
+    }
+
+    /// The served vault and every registered one
+    pub fn list_vaults(&self) -> crate::Result<Vec<VaultInfo>> {
+        let config = load_config();
+        let mut dirs = vec![(DEFAULT_PROFILE.to_string(), default_vault_dir()?)];
+        dirs.extend(
+            config
+                .vaults
+                .iter()
+                .map(|(name, profile)| (name.clone(), profile.path.clone())),
+        );
+        if !dirs.iter().any(|(_, dir)| *dir == self.vault_path) {
+            dirs.push(self.resolve_vault(None)?);
+        }
+
+        Ok(dirs
+            .into_iter()
+            .map(|(name, dir)| VaultInfo {
+                default: dir == self.vault_path,
+                exists: VaultStorage::with_path(dir.join("vault.sf")).exists(),
+                locked: self.is_locked(&dir),
+                name,
+            })
+            .collect())
+    }
+
+    pub fn unlock(
+        &self,
+        vault: Option<&str>,
+        password: &str,
+        keyfile: Option<Keyfile>,
+    ) -> crate::Result<Session> {
+        let (name, vault_dir) = self.resolve_vault(vault)?;
+        let storage = VaultStorage::with_path(vault_dir.join("vault.sf"));
+        let config = load_config();
 
-        // Load and decrypt vault
-        let vault = storage.load(&password)?;
+        // Fall back to the keyfile configured for the vault
+        let keyfile = match (keyfile, config.keyfile_for(&vault_dir)) {
+            (Some(keyfile), _) => Some(keyfile),
+            (None, Some(path)) => Some(Keyfile::load(path)?),
+            (None, None) => None,
+        };
+        let credentials = Credentials::new(password, keyfile.as_ref());
 
-        // Store vault in memory
-        *self.vault.write() = Some(vault);
+        // Load and decrypt vault, keeping the data key for later saves
+        let (vault, key) = storage.unlock(credentials)?;
+
+        // Re-wrap weak KDF settings if the policy asks for it
+        if config.auto_upgrade_kdf {
+            match storage.upgrade_weak_kdf(credentials) {
+                Ok(true) => tracing::info!("Upgraded vault key derivation to Argon2id"),
+                Ok(false) => {}
+                Err(e) => tracing::warn!("KDF upgrade failed: {}", e),
+            }
+        }
+
+        // A full unlock re-arms an expired PIN, but not a locked out one
+        let pin_policy = config.pin_policy.unwrap_or_default();
+        if let Err(e) = storage.rearm_pin(&key, &pin_policy) {
+            tracing::warn!("Could not re-arm PIN unlock: {}", e);
+        }
+
+        self.start_session(name, vault_dir, vault, key)
+    }
+
+    /// Unlock with the quick unlock PIN set up from the CLI
+    pub fn unlock_with_pin(&self, vault: Option<&str>, pin: &str) -> crate::Result<Session> {
+        let (name, vault_dir) = self.resolve_vault(vault)?;
+        let storage = VaultStorage::with_path(vault_dir.join("vault.sf"));
+        let pin_policy = load_config().pin_policy.unwrap_or_default();
+
+        let (vault, key) = storage.unlock_with_pin(pin, &pin_policy)?;
+        self.start_session(name, vault_dir, vault, key)
+    }
+
+    /// Keep the unlocked vault and key in memory and open a session on it
+    fn start_session(
+        &self,
+        name: String,
+        vault_dir: PathBuf,
+        mut vault: Vault,
+        key: EncryptionKey,
+    ) -> crate::Result<Session> {
+        // Drop items that outlived the trash retention period
+        let storage = VaultStorage::with_path(vault_dir.join("vault.sf"));
+        let retention_days = ConfigManager::new()
+            .and_then(|m| m.load())
+            .map(|c| c.trash_retention_days())
+            .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS);
+        match storage.purge_expired_trash(&mut vault, &key, retention_days) {
+            Ok(purged) if !purged.is_empty() => {
+                tracing::info!("Purged {} expired items from the trash", purged.len())
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Could not purge the trash: {}", e),
+        }
+
+        // Report broken items; repairs are left to `securefox doctor --fix`
+        for issue in integrity::check(&vault) {
+            match issue.severity {
+                Severity::Error => {
+                    tracing::warn!("Vault {}: '{}': {}", name, issue.name, issue.message)
+                }
+                _ => tracing::info!("Vault {}: '{}': {}", name, issue.name, issue.message),
+            }
+        }
+
+        // Store vault and key in memory
+        self.vaults.write().insert(
+            vault_dir.clone(),
+            UnlockedVault {
+                vault: SealedVault::seal(vault)?,
+                key: LockedKey::new(key),
+            },
+        );
 
         // Create session
-        let session = Session::new(password, self.unlock_timeout);
+        let session = Session::new(name, vault_dir, self.unlock_timeout);
         let token = session.id.clone();
 
         // Store session
@@ -41,13 +194,22 @@ impl AppState {
         Ok(session)
     }
 
-    pub fn lock(&self, token: Option<&str>) {
-        if let Some(token) = token {
-            self.sessions.write().remove(token);
+    /// Lock the vault of `session`, or every vault
+    ///
+    /// Other sessions on a locked vault end with it.
+    pub fn lock(&self, session: Option<&Session>) {
+        match session {
+            Some(session) => {
+                self.sessions
+                    .write()
+                    .retain(|_, s| s.vault_dir != session.vault_dir);
+                self.vaults.write().remove(&session.vault_dir);
+            }
+            None => {
+                self.sessions.write().clear();
+                self.vaults.write().clear();
+            }
         }
-
-        // Clear vault from memory
-        *self.vault.write() = None;
     }
 
     pub fn get_session(&self, token: &str) -> Option<Session> {
@@ -67,12 +229,29 @@ impl AppState {
         }
     }
 
-    pub fn is_locked(&self) -> bool {
-        self.vault.read().is_none()
+    /// Whether the vault in `vault_dir` is locked
+    pub fn is_locked(&self, vault_dir: &Path) -> bool {
+        !self.vaults.read().contains_key(vault_dir)
     }
 
-    pub fn get_vault(&self) -> Option<Vault> {
-        self.vault.read().clone()
+    /// Whether a session is open on the vault in `vault_dir`
+    pub fn has_session(&self, vault_dir: &Path) -> bool {
+        self.sessions
+            .read()
+            .values()
+            .any(|s| s.vault_dir == vault_dir && !s.is_expired())
+    }
+
+    /// Run `f` on the vault of `session`; items are decrypted as `f` reads them
+    pub fn with_vault<T, F>(&self, session: &Session, f: F) -> crate::Result<T>
+    where
+        F: FnOnce(&SealedVault) -> crate::Result<T>,
+    {
+        let vaults = self.vaults.read();
+        let unlocked = vaults
+            .get(&session.vault_dir)
+            .ok_or(crate::ApiError::VaultLocked)?;
+        f(&unlocked.vault)
     }
 
     pub fn update_vault<F>(&self, token: &str, f: F) -> crate::Result<()>
@@ -83,21 +262,22 @@ impl AppState {
             .get_session(token)
             .ok_or(crate::ApiError::SessionExpired)?;
 
-        let mut vault_guard = self.vault.write();
-        let vault = vault_guard.as_mut().ok_or(crate::ApiError::VaultLocked)?;
-
-        // Apply the update
-        f(vault)?;
+        let mut vaults = self.vaults.write();
+        let unlocked = vaults
+            .get_mut(&session.vault_dir)
+            .ok_or(crate::ApiError::VaultLocked)?;
 
-        // Save to disk
-        let storage = VaultStorage::with_path(self.vault_path.join("vault.sf"));
-        storage.save(vault, &session.master_password)?;
+        // Apply the update to the vault on disk under the vault lock, so
+        // changes written meanwhile by the CLI or sync are not overwritten
+        let storage = VaultStorage::with_path(session.vault_dir.join("vault.sf"));
+        let (vault, ()) = storage.update_with_key(unlocked.key.key(), f)?;
+        unlocked.vault = SealedVault::seal(vault)?;
 
         // Git sync if configured
         #[cfg(feature = "git")]
         {
             use securefox_core::git_sync::GitSync;
-            if let Ok(sync) = GitSync::init(&self.vault_path) {
+            if let Ok(sync) = GitSync::init(&session.vault_dir) {
                 let _ = sync.auto_commit_push("API vault update");
             }
         }
@@ -105,8 +285,19 @@ impl AppState {
         Ok(())
     }
 
+    /// Attachment blobs of the vault of `session`
+    pub fn attachments(&self, session: &Session) -> AttachmentStore {
+        VaultStorage::with_path(session.vault_dir.join("vault.sf")).attachments()
+    }
+
     pub fn cleanup_expired_sessions(&self) {
         let mut sessions = self.sessions.write();
         sessions.retain(|_, session| !session.is_expired());
     }
 }
+
+fn load_config() -> SyncConfigFile {
+    ConfigManager::new()
+        .and_then(|m| m.load())
+        .unwrap_or_default()
+}
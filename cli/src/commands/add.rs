@@ -12,13 +12,15 @@ pub async fn execute(
     username: Option<String>,
     generate: bool,
     totp: Option<String>,
+    tags: Vec<String>,
 ) -> Result<()> {
     let vault_path = vault_path
         .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
         .join("vault.sf");
 
     // Load vault
-    let (mut vault, master_password) = crate::utils::load_vault(&vault_path)?;
+    let storage = VaultStorage::with_path(&vault_path);
+    let (mut vault, key) = crate::utils::load_vault(&storage)?;
 
     // Create item based on type
     let mut item = match item_type.as_str() {
@@ -26,6 +28,7 @@ pub async fn execute(
         "note" => Item::new_secure_note(&name),
         _ => return Err(anyhow::anyhow!("Unsupported item type: {}", item_type)),
     };
+    item.add_tags(&tags)?;
 
     // Handle login-specific fields
     if item_type == "login" {
@@ -52,14 +55,14 @@ pub async fn execute(
                 };
                 let password = pg.generate_one().unwrap();
                 println!("Generated password: {}", password.green().bold());
-                login.password = Some(password);
+                login.password = Some(password.into());
             } else {
-                login.password = Some(Password::new().with_prompt("Password").interact()?);
+                login.password = Some(Password::new().with_prompt("Password").interact()?.into());
             }
 
             // Set TOTP if provided
             if let Some(totp_secret) = totp {
-                login.totp = Some(totp_secret);
+                login.totp = Some(totp_secret.into());
             }
 
             // Add URL
@@ -83,15 +86,19 @@ pub async fn execute(
         .default(false)
         .interact()?
     {
-        item.notes = Some(Input::<String>::new().with_prompt("Notes").interact()?);
+        item.notes = Some(
+            Input::<String>::new()
+                .with_prompt("Notes")
+                .interact()?
+                .into(),
+        );
     }
 
     // Add item to vault
     vault.add_item(item);
 
     // Save vault
-    let storage = VaultStorage::with_path(&vault_path);
-    storage.save(&vault, &master_password)?;
+    storage.save_with_key(&vault, &key)?;
 
     // Git sync
     #[cfg(feature = "git")]
@@ -0,0 +1,68 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::storage::VaultStorage;
+use std::fs;
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    item_name: String,
+    file: PathBuf,
+    name: Option<String>,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    let file_name = match name {
+        Some(name) => name,
+        None => file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .ok_or_else(|| anyhow::anyhow!("Invalid file path: {}", file.display()))?,
+    };
+    let contents = fs::read(&file)?;
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (mut vault, key) = crate::utils::load_vault(&storage)?;
+    let item = crate::utils::find_item_mut(&mut vault, &item_name)?;
+    if item.attachments.iter().any(|a| a.file_name == file_name) {
+        return Err(anyhow::anyhow!(
+            "'{}' already has an attachment named {}",
+            item.name,
+            file_name
+        ));
+    }
+
+    // Write the blob first, so the vault never points at a missing one
+    let attachments = storage.attachments();
+    let attachment = attachments.add(&file_name, &contents)?;
+    item.attachments.push(attachment.clone());
+    item.revision_date = chrono::Utc::now();
+    let item_name = item.name.clone();
+
+    if let Err(e) = storage.save_with_key(&vault, &key) {
+        let _ = attachments.remove(&attachment);
+        return Err(e.into());
+    }
+
+    // Git sync
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        if let Some(parent) = vault_path.parent() {
+            let sync = GitSync::init(parent)?;
+            sync.auto_commit_push(&format!("Attached file to item: {}", item_name))?;
+        }
+    }
+
+    println!(
+        "{} Attached {} ({} bytes) to '{}'",
+        "✓".green().bold(),
+        file_name,
+        attachment.size,
+        item_name
+    );
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::storage::VaultStorage;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    item_name: String,
+    attachment_name: String,
+    output: Option<PathBuf>,
+    force: bool,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (vault, _) = crate::utils::load_vault(&storage)?;
+    let item = vault
+        .active_items()
+        .find(|i| i.name == item_name || i.id == item_name)
+        .ok_or_else(|| anyhow::anyhow!("Item not found: {}", item_name))?;
+    let attachment = crate::utils::find_attachment(item, &attachment_name)?;
+
+    let contents = storage.attachments().read(attachment)?;
+
+    let output = output.unwrap_or_else(|| PathBuf::from(&attachment.file_name));
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&output).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => anyhow::anyhow!(
+            "{} already exists; use --force to overwrite it",
+            output.display()
+        ),
+        _ => e.into(),
+    })?;
+    file.write_all(&contents)?;
+
+    println!(
+        "{} Saved {} to {}",
+        "✓".green().bold(),
+        attachment.file_name,
+        output.display()
+    );
+    Ok(())
+}
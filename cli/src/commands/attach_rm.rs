@@ -0,0 +1,61 @@
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Confirm;
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    item_name: String,
+    attachment_name: String,
+    force: bool,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (mut vault, key) = crate::utils::load_vault(&storage)?;
+    let item = crate::utils::find_item_mut(&mut vault, &item_name)?;
+    let attachment = crate::utils::find_attachment(item, &attachment_name)?.clone();
+
+    if !force
+        && !Confirm::new()
+            .with_prompt(format!(
+                "Remove {} from '{}'?",
+                attachment.file_name, item.name
+            ))
+            .default(false)
+            .interact()?
+    {
+        println!("Cancelled");
+        return Ok(());
+    }
+
+    item.attachments.retain(|a| a.id != attachment.id);
+    item.revision_date = chrono::Utc::now();
+    let item_name = item.name.clone();
+
+    // Save the vault before deleting the blob, so it never points at a missing one
+    storage.save_with_key(&vault, &key)?;
+    storage.attachments().remove(&attachment)?;
+
+    // Git sync
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        if let Some(parent) = vault_path.parent() {
+            let sync = GitSync::init(parent)?;
+            sync.auto_commit_push(&format!("Removed attachment from item: {}", item_name))?;
+        }
+    }
+
+    println!(
+        "{} Removed {} from '{}'",
+        "✓".green().bold(),
+        attachment.file_name,
+        item_name
+    );
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    let storage = VaultStorage::with_path(&vault_path);
+    let backup = storage.backup_before("manual")?;
+
+    println!(
+        "{} Backed up the vault to {}",
+        "✓".green().bold(),
+        backup.display()
+    );
+    Ok(())
+}
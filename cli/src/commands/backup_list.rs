@@ -0,0 +1,48 @@
+use anyhow::Result;
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    let storage = VaultStorage::with_path(&vault_path);
+    let backups = storage.backups().list()?;
+    if backups.is_empty() {
+        println!("No backups found");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![
+            Cell::new("#").fg(Color::Blue),
+            Cell::new("Created").fg(Color::Blue),
+            Cell::new("Before").fg(Color::Blue),
+            Cell::new("Size").fg(Color::Blue),
+            Cell::new("File").fg(Color::Blue),
+        ]);
+
+    for (i, backup) in backups.iter().enumerate() {
+        table.add_row(vec![
+            Cell::new(i + 1),
+            Cell::new(
+                backup
+                    .created
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+            ),
+            Cell::new(backup.reason.as_deref().unwrap_or("-")),
+            Cell::new(format!("{:.1} KB", backup.size as f64 / 1024.0)),
+            Cell::new(backup.file_name()),
+        ]);
+    }
+
+    println!("{table}");
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::config::ConfigManager;
+
+pub async fn execute(
+    keep_last: Option<usize>,
+    keep_daily: Option<usize>,
+    keep_weekly: Option<usize>,
+) -> Result<()> {
+    let mut policy = crate::utils::backup_policy();
+    let changed = keep_last.is_some() || keep_daily.is_some() || keep_weekly.is_some();
+
+    if changed {
+        policy.keep_last = keep_last.unwrap_or(policy.keep_last);
+        policy.keep_daily = keep_daily.unwrap_or(policy.keep_daily);
+        policy.keep_weekly = keep_weekly.unwrap_or(policy.keep_weekly);
+        ConfigManager::new()?.update_backup_policy(Some(policy.clone()))?;
+        println!("{} Backup retention updated", "✓".green().bold());
+    }
+
+    println!("Keep the last {} backups", policy.keep_last);
+    println!("Keep one backup per day for {} days", policy.keep_daily);
+    println!("Keep one backup per week for {} weeks", policy.keep_weekly);
+    Ok(())
+}
@@ -0,0 +1,11 @@
+use anyhow::Result;
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>, backup: String, force: bool) -> Result<()> {
+    let vault_dir = vault_path.ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?;
+    let storage = VaultStorage::with_path(vault_dir.join("vault.sf"));
+    let backup = crate::utils::find_backup(&storage, &backup)?;
+
+    super::restore::execute(Some(vault_dir), backup.path.display().to_string(), force).await
+}
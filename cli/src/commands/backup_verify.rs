@@ -0,0 +1,64 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::{backup::BackupStatus, storage::VaultStorage};
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>, backup: Option<String>) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault for its data key
+    let storage = VaultStorage::with_path(&vault_path);
+    let (_, key) = crate::utils::load_vault(&storage)?;
+
+    let store = storage.backups();
+    let backups = match backup {
+        Some(name) => vec![crate::utils::find_backup(&storage, &name)?],
+        None => store.list()?,
+    };
+    if backups.is_empty() {
+        println!("No backups found");
+        return Ok(());
+    }
+
+    let mut corrupt = 0;
+    let mut other_key = 0;
+    for backup in &backups {
+        match store.verify(backup, &key) {
+            BackupStatus::Valid { items } => {
+                println!(
+                    "{} {} ({} items)",
+                    "✓".green().bold(),
+                    backup.file_name(),
+                    items
+                )
+            }
+            BackupStatus::OtherKey => {
+                other_key += 1;
+                println!(
+                    "{} {} (encrypted under a previous key)",
+                    "?".yellow().bold(),
+                    backup.file_name()
+                );
+            }
+            BackupStatus::Corrupt(reason) => {
+                corrupt += 1;
+                println!("{} {}: {}", "✗".red().bold(), backup.file_name(), reason);
+            }
+        }
+    }
+
+    if other_key > 0 {
+        println!();
+        println!(
+            "{} {} backups need the password the vault had when they were taken",
+            "Note:".yellow(),
+            other_key
+        );
+    }
+    if corrupt > 0 {
+        anyhow::bail!("{} of {} backups are corrupt", corrupt, backups.len());
+    }
+    Ok(())
+}
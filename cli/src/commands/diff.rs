@@ -0,0 +1,93 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::{
+    diff::{vault_diff, FieldChange, VaultDiff},
+    snapshot::VaultSnapshot,
+    storage::VaultStorage,
+};
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    old: String,
+    new: String,
+    reveal: bool,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (_, key) = crate::utils::load_vault(&storage)?;
+
+    let (old, new) = (VaultSnapshot::parse(&old), VaultSnapshot::parse(&new));
+    let diff = vault_diff(
+        &crate::utils::open_snapshot(&storage, &key, &old)?,
+        &crate::utils::open_snapshot(&storage, &key, &new)?,
+    )?;
+
+    println!("{}", format!("Changes from {} to {}", old, new).bold());
+    print_diff(&diff, reveal);
+    Ok(())
+}
+
+/// Print added, removed and modified items and folders
+pub fn print_diff(diff: &VaultDiff, reveal: bool) {
+    if diff.is_empty() {
+        println!("No differences");
+        return;
+    }
+
+    if !diff.added_items.is_empty()
+        || !diff.removed_items.is_empty()
+        || !diff.modified_items.is_empty()
+    {
+        println!();
+        println!("{}", "Items".cyan().bold());
+    }
+    for item in &diff.added_items {
+        println!("  {} {}", "+".green().bold(), item.name);
+    }
+    for item in &diff.removed_items {
+        println!("  {} {}", "-".red().bold(), item.name);
+    }
+    for item in &diff.modified_items {
+        println!("  {} {}", "~".yellow().bold(), item.name);
+        for field in &item.fields {
+            println!("      {}", describe_field(field, reveal));
+        }
+    }
+
+    if !diff.added_folders.is_empty()
+        || !diff.removed_folders.is_empty()
+        || !diff.renamed_folders.is_empty()
+    {
+        println!();
+        println!("{}", "Folders".cyan().bold());
+    }
+    for folder in &diff.added_folders {
+        println!("  {} {}", "+".green().bold(), folder.name);
+    }
+    for folder in &diff.removed_folders {
+        println!("  {} {}", "-".red().bold(), folder.name);
+    }
+    for (old, new) in &diff.renamed_folders {
+        println!("  {} {} → {}", "~".yellow().bold(), old.name, new.name);
+    }
+}
+
+/// `field: old → new`, with secrets masked unless `reveal`
+pub fn describe_field(field: &FieldChange, reveal: bool) -> String {
+    let show = |value: &Option<String>| match value {
+        None => "(none)".dimmed().to_string(),
+        Some(_) if field.secret && !reveal => "********".to_string(),
+        Some(value) => value.clone(),
+    };
+    format!(
+        "{}: {} → {}",
+        field.field,
+        show(&field.old),
+        show(&field.new)
+    )
+}
@@ -0,0 +1,105 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::{
+    integrity::{self, Issue, Severity, Subject},
+    storage::VaultStorage,
+};
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>, fix: bool) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (vault, key) = crate::utils::load_vault(&storage)?;
+
+    let mut issues = integrity::check(&vault);
+    if issues.is_empty() {
+        println!("{} No problems found", "✓".green().bold());
+        return Ok(());
+    }
+
+    let mut repaired = Vec::new();
+    if fix && issues.iter().any(|i| i.fix.is_some()) {
+        storage.backup_before("doctor")?;
+        let (_, fixed) = storage.update_with_key(&key, |vault| {
+            Ok::<_, securefox_core::Error>(integrity::repair(vault))
+        })?;
+        repaired = fixed;
+
+        // Git sync
+        #[cfg(feature = "git")]
+        {
+            use securefox_core::git_sync::GitSync;
+            if let Some(parent) = vault_path.parent() {
+                let sync = GitSync::init(parent)?;
+                sync.auto_commit_push(&format!("Repaired {} vault problems", repaired.len()))?;
+            }
+        }
+
+        // Report what is left after the repairs
+        let (vault, _) = crate::utils::load_vault(&storage)?;
+        issues = integrity::check(&vault);
+    }
+
+    for issue in &repaired {
+        print_issue(issue, true);
+    }
+    for issue in &issues {
+        print_issue(issue, false);
+    }
+
+    println!();
+    if !repaired.is_empty() {
+        println!(
+            "{} Repaired {} problems",
+            "✓".green().bold(),
+            repaired.len()
+        );
+    }
+    let fixable = issues.iter().filter(|i| i.fix.is_some()).count();
+    if fixable > 0 {
+        println!(
+            "{} {} problems can be repaired with {}",
+            "Note:".yellow(),
+            fixable,
+            "securefox doctor --fix".cyan()
+        );
+    }
+
+    let errors = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .count();
+    if errors > 0 {
+        anyhow::bail!("{} problems need to be fixed by hand", errors);
+    }
+    Ok(())
+}
+
+fn print_issue(issue: &Issue, repaired: bool) {
+    let severity = match issue.severity {
+        Severity::Info => issue.severity.to_string().blue(),
+        Severity::Warning => issue.severity.to_string().yellow(),
+        Severity::Error => issue.severity.to_string().red(),
+    };
+    let subject = match issue.subject {
+        Subject::Item(_) => "item",
+        Subject::Folder(_) => "folder",
+    };
+
+    println!(
+        "{} {} '{}': {}",
+        format!("[{}]", severity).bold(),
+        subject,
+        issue.name,
+        issue.message
+    );
+    match &issue.fix {
+        Some(fix) if repaired => println!("  {} {}", "fixed:".green(), fix),
+        Some(fix) => println!("  {} {}", "fix:".dimmed(), fix),
+        None => {}
+    }
+}
@@ -1 +1,107 @@
-pub use super::placeholder::edit::execute;
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::{Confirm, Input, Password};
+use passwords::PasswordGenerator;
+use securefox_core::{models::Item, storage::VaultStorage};
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    item_name: String,
+    tags: Vec<String>,
+    untags: Vec<String>,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (mut vault, key) = crate::utils::load_vault(&storage)?;
+    let mut item = crate::utils::find_item_mut(&mut vault, &item_name)?.clone();
+
+    // Changing tags skips the prompts
+    if !tags.is_empty() || !untags.is_empty() {
+        item.add_tags(&tags)?;
+        for tag in &untags {
+            item.tags.remove(tag.trim());
+        }
+    } else {
+        edit_fields(&mut item)?;
+    }
+
+    // The vault keeps the replaced password in the item's history
+    item.revision_date = chrono::Utc::now();
+    let name = item.name.clone();
+    vault.update_item(item);
+
+    // Save vault
+    storage.save_with_key(&vault, &key)?;
+
+    // Git sync
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        if let Some(parent) = vault_path.parent() {
+            let sync = GitSync::init(parent)?;
+            sync.auto_commit_push(&format!("Updated item: {}", name))?;
+        }
+    }
+
+    println!(
+        "{} Item '{}' updated successfully",
+        "✓".green().bold(),
+        name
+    );
+    Ok(())
+}
+
+/// Prompt for the name and login fields of `item`
+fn edit_fields(item: &mut Item) -> Result<()> {
+    item.name = Input::<String>::new()
+        .with_prompt("Name")
+        .with_initial_text(&item.name)
+        .interact_text()?;
+
+    if let Some(ref mut login) = item.login {
+        let username = Input::<String>::new()
+            .with_prompt("Username")
+            .with_initial_text(login.username.clone().unwrap_or_default())
+            .allow_empty(true)
+            .interact_text()?;
+        login.username = (!username.is_empty()).then_some(username);
+
+        if Confirm::new()
+            .with_prompt("Change password?")
+            .default(false)
+            .interact()?
+        {
+            let password = if Confirm::new()
+                .with_prompt("Generate a new password?")
+                .default(true)
+                .interact()?
+            {
+                let pg = PasswordGenerator {
+                    length: 20,
+                    numbers: true,
+                    lowercase_letters: true,
+                    uppercase_letters: true,
+                    symbols: true,
+                    spaces: false,
+                    exclude_similar_characters: true,
+                    strict: true,
+                };
+                let password = pg.generate_one().unwrap();
+                println!("Generated password: {}", password.green().bold());
+                password
+            } else {
+                Password::new()
+                    .with_prompt("New password")
+                    .with_confirmation("Confirm password", "Passwords do not match")
+                    .interact()?
+            };
+            login.password = Some(password.into());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>, name: String) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (mut vault, key) = crate::utils::load_vault(&storage)?;
+
+    let folder = vault.add_folder(&name)?.clone();
+    storage.save_with_key(&vault, &key)?;
+
+    // Git sync
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        if let Some(parent) = vault_path.parent() {
+            let sync = GitSync::init(parent)?;
+            sync.auto_commit_push(&format!("Added folder: {}", folder.name))?;
+        }
+    }
+
+    println!("{} Added folder '{}'", "✓".green().bold(), folder.name);
+    Ok(())
+}
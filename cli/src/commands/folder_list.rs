@@ -0,0 +1,49 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::{folders::FolderNode, models::Vault, storage::VaultStorage};
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (vault, _) = crate::utils::load_vault(&storage)?;
+
+    let tree = vault.folder_tree();
+    if tree.is_empty() {
+        println!("No folders");
+        return Ok(());
+    }
+
+    for node in &tree {
+        print_node(&vault, node, None, 0);
+    }
+
+    let unfiled = vault
+        .active_items()
+        .filter(|i| i.folder_id.is_none())
+        .count();
+    if unfiled > 0 {
+        println!("{} ({})", "No folder".dimmed(), unfiled);
+    }
+    Ok(())
+}
+
+/// Print a folder named relative to its parent, then its subfolders indented
+fn print_node(vault: &Vault, node: &FolderNode, parent: Option<&str>, depth: usize) {
+    let name = parent
+        .and_then(|p| node.folder.name.strip_prefix(p))
+        .map_or(node.folder.name.as_str(), |rest| &rest[1..]);
+    let items = vault
+        .active_items()
+        .filter(|i| i.folder_id.as_deref() == Some(node.folder.id.as_str()))
+        .count();
+
+    println!("{}{} ({})", "  ".repeat(depth), name.bold(), items);
+    for child in &node.children {
+        print_node(vault, child, Some(&node.folder.name), depth + 1);
+    }
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    item_name: String,
+    folder: Option<String>,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (mut vault, key) = crate::utils::load_vault(&storage)?;
+    let item_id = crate::utils::find_item_mut(&mut vault, &item_name)?
+        .id
+        .clone();
+    let folder = folder
+        .map(|name| crate::utils::find_folder(&vault, &name).cloned())
+        .transpose()?;
+
+    let item = vault
+        .move_item(&item_id, folder.as_ref().map(|f| f.id.as_str()))?
+        .name
+        .clone();
+    storage.save_with_key(&vault, &key)?;
+
+    let destination = folder
+        .as_ref()
+        .map_or("out of its folder".to_string(), |f| {
+            format!("to '{}'", f.name)
+        });
+
+    // Git sync
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        if let Some(parent) = vault_path.parent() {
+            let sync = GitSync::init(parent)?;
+            sync.auto_commit_push(&format!("Moved item {}: {}", destination, item))?;
+        }
+    }
+
+    println!("{} Moved '{}' {}", "✓".green().bold(), item, destination);
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Confirm;
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    folder: String,
+    move_to: Option<String>,
+    force: bool,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (mut vault, key) = crate::utils::load_vault(&storage)?;
+    let folder = crate::utils::find_folder(&vault, &folder)?.clone();
+    let target = move_to
+        .map(|name| crate::utils::find_folder(&vault, &name).cloned())
+        .transpose()?;
+
+    let items = vault
+        .items
+        .iter()
+        .filter(|i| i.folder_id.as_deref() == Some(folder.id.as_str()))
+        .count();
+    if !force && items > 0 {
+        let destination = target
+            .as_ref()
+            .map_or("out of any folder".to_string(), |t| {
+                format!("to '{}'", t.name)
+            });
+        let confirmed = Confirm::new()
+            .with_prompt(format!(
+                "Delete folder '{}' and move its {} items {}?",
+                folder.name, items, destination
+            ))
+            .default(false)
+            .interact()?;
+        if !confirmed {
+            println!("Cancelled");
+            return Ok(());
+        }
+    }
+
+    let (_, moved) = vault.remove_folder(&folder.id, target.as_ref().map(|t| t.id.as_str()))?;
+    storage.save_with_key(&vault, &key)?;
+
+    // Git sync
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        if let Some(parent) = vault_path.parent() {
+            let sync = GitSync::init(parent)?;
+            sync.auto_commit_push(&format!("Removed folder: {}", folder.name))?;
+        }
+    }
+
+    println!("{} Removed folder '{}'", "✓".green().bold(), folder.name);
+    if moved > 0 {
+        match &target {
+            Some(target) => println!("  moved {} items to '{}'", moved, target.name),
+            None => println!("  moved {} items out of the folder", moved),
+        }
+    }
+    Ok(())
+}
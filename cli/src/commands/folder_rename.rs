@@ -0,0 +1,43 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>, folder: String, new_name: String) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (mut vault, key) = crate::utils::load_vault(&storage)?;
+    let folder = crate::utils::find_folder(&vault, &folder)?.clone();
+
+    // Folders nested under it are renamed along with it
+    let renamed = vault.rename_folder(&folder.id, &new_name)?;
+    let new_name = vault
+        .get_folder(&folder.id)
+        .map_or(new_name, |f| f.name.clone());
+    storage.save_with_key(&vault, &key)?;
+
+    // Git sync
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        if let Some(parent) = vault_path.parent() {
+            let sync = GitSync::init(parent)?;
+            sync.auto_commit_push(&format!("Renamed folder: {} -> {}", folder.name, new_name))?;
+        }
+    }
+
+    println!(
+        "{} Renamed '{}' to '{}'",
+        "✓".green().bold(),
+        folder.name,
+        new_name
+    );
+    if renamed > 1 {
+        println!("  along with {} subfolders", renamed - 1);
+    }
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    item_name: String,
+    reveal: bool,
+    restore: Option<String>,
+) -> Result<()> {
+    #[cfg(feature = "git")]
+    {
+        use colored::Colorize;
+        use securefox_core::git_sync::GitSync;
+        use securefox_core::history::{self, ItemChangeKind};
+        use securefox_core::storage::VaultStorage;
+
+        let vault_dir = vault_path.ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?;
+        let vault_path = vault_dir.join("vault.sf");
+
+        // Load vault
+        let storage = VaultStorage::with_path(&vault_path);
+        let (mut vault, key) = crate::utils::load_vault(&storage)?;
+        let sync = GitSync::init(&vault_dir)?;
+
+        // Trashed and purged items have a history too; the latter by ID only
+        let (item_id, name) = match vault
+            .items
+            .iter()
+            .find(|i| i.name == item_name || i.id == item_name)
+        {
+            Some(item) => (item.id.clone(), item.name.clone()),
+            None => (item_name.clone(), item_name.clone()),
+        };
+
+        if let Some(revision) = restore {
+            let past = history::item_at(&sync, &key, &revision, &item_id)?.ok_or_else(|| {
+                anyhow::anyhow!("'{}' did not exist at revision {}", name, revision)
+            })?;
+            let past_name = past.name.clone();
+            history::restore_item(&mut vault, past);
+            storage.backup_before("restore")?;
+            storage.save_with_key(&vault, &key)?;
+            sync.auto_commit_push(&format!(
+                "Restored item {} from revision {}",
+                past_name, revision
+            ))?;
+
+            println!(
+                "{} Restored '{}' as of revision {}",
+                "✓".green().bold(),
+                past_name,
+                revision
+            );
+            return Ok(());
+        }
+
+        let history = history::item_history(&sync, &key, &item_id)?;
+        if history.changes.is_empty() {
+            println!("No history found for '{}'", name);
+        } else {
+            println!(
+                "{} ({} changes)",
+                format!("History of '{}'", name).bold(),
+                history.changes.len()
+            );
+        }
+
+        for change in &history.changes {
+            let kind = match change.kind {
+                ItemChangeKind::Created => "Created".green(),
+                ItemChangeKind::Modified => "Modified".cyan(),
+                ItemChangeKind::Trashed => "Trashed".yellow(),
+                ItemChangeKind::Restored => "Restored".green(),
+                ItemChangeKind::Deleted => "Deleted".red(),
+            };
+            println!();
+            println!(
+                "{}  {}  {}  {}",
+                change.revision.short_commit().yellow(),
+                change.revision.time.format("%Y-%m-%d %H:%M"),
+                kind,
+                change.revision.message.dimmed()
+            );
+
+            for field in &change.fields {
+                println!("    {}", super::diff::describe_field(field, reveal));
+            }
+        }
+
+        if history.skipped > 0 {
+            println!();
+            println!(
+                "{} {} older revisions were encrypted under a previous key and are not shown",
+                "Note:".yellow(),
+                history.skipped
+            );
+        }
+        if !history.changes.is_empty() {
+            println!();
+            println!(
+                "Restore a version with 'securefox history {} --restore <commit>'",
+                item_name
+            );
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "git"))]
+    {
+        let _ = (vault_path, item_name, reveal, restore);
+        anyhow::bail!("Git feature not enabled");
+    }
+}
@@ -37,9 +37,10 @@ pub async fn execute(vault_path: Option<PathBuf>, file: PathBuf, format: String)
 
     // Load existing vault or create new one
     let storage = VaultStorage::with_path(&vault_path);
-    let (mut vault, password) = if storage.exists() {
+    let merging = storage.exists();
+    let (mut vault, key) = if merging {
         println!("Merging with existing vault...");
-        crate::utils::load_vault(&vault_path)?
+        crate::utils::load_vault(&storage)?
     } else {
         println!("Creating new vault...");
         use dialoguer::Password;
@@ -47,7 +48,8 @@ pub async fn execute(vault_path: Option<PathBuf>, file: PathBuf, format: String)
             .with_prompt("Enter master password for new vault")
             .with_confirmation("Confirm master password", "Passwords do not match")
             .interact()?;
-        (securefox_core::models::Vault::new(), password)
+        storage.save(&securefox_core::models::Vault::new(), &password)?;
+        storage.unlock(&password)?
     };
 
     // Import with progress bar
@@ -79,8 +81,11 @@ pub async fn execute(vault_path: Option<PathBuf>, file: PathBuf, format: String)
 
     pb.finish_with_message("Import complete");
 
-    // Save vault
-    storage.save(&vault, &password)?;
+    // Save vault, backing up the one being merged into
+    if merging {
+        storage.backup_before("import")?;
+    }
+    storage.save_with_key(&vault, &key)?;
 
     // Git sync
     #[cfg(feature = "git")]
@@ -1,13 +1,17 @@
 use anyhow::Result;
 use colored::Colorize;
 use dialoguer::Password;
-use securefox_core::{crypto::KdfParams, prelude::*, storage::VaultStorage};
+use securefox_core::{
+    config::ConfigManager, crypto::KdfParams, keyfile::Keyfile, keyslot::Credentials, prelude::*,
+    storage::VaultStorage,
+};
 use std::path::PathBuf;
 
 pub async fn execute(
     vault_path: Option<PathBuf>,
     remote: Option<String>,
     kdf: String,
+    keyfile: Option<PathBuf>,
 ) -> Result<()> {
     println!("{}", "Initializing new SecureFox vault...".cyan().bold());
 
@@ -59,13 +63,40 @@ pub async fn execute(
         }
     };
 
+    // Reuse an existing keyfile (e.g. one made by KeePass), otherwise create one
+    let keyfile_path = keyfile;
+    let keyfile = match &keyfile_path {
+        Some(path) if path.exists() => Some(Keyfile::load(path)?),
+        Some(path) => {
+            let generated = Keyfile::generate();
+            generated.write(path)?;
+            println!("Generated keyfile at {}", path.display());
+            println!(
+                "{}",
+                "⚠ Keep a copy of the keyfile somewhere safe: the vault cannot be opened without it"
+                    .yellow()
+            );
+            Some(generated)
+        }
+        None => None,
+    };
+
     println!("Encrypting vault...");
 
     // Create new vault
     let vault = Vault::new();
 
     // Save vault with specified KDF
-    storage.save_with_kdf(&vault, &password, kdf_params)?;
+    storage.save_with_kdf(
+        &vault,
+        Credentials::new(&password, keyfile.as_ref()),
+        kdf_params,
+    )?;
+
+    // Remember the keyfile so later unlocks pick it up
+    if let Some(path) = &keyfile_path {
+        ConfigManager::new()?.update_keyfile_for(&vault_path, Some(path.canonicalize()?))?;
+    }
 
     println!("{}", "✓ Vault initialized successfully".green());
 
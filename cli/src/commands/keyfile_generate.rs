@@ -0,0 +1,22 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::keyfile::Keyfile;
+use std::path::PathBuf;
+
+pub async fn execute(path: PathBuf) -> Result<()> {
+    let keyfile = Keyfile::generate();
+    keyfile.write(&path)?;
+
+    println!(
+        "{} Keyfile {} written to {}",
+        "✓".green().bold(),
+        keyfile.fingerprint().cyan(),
+        path.display()
+    );
+    println!(
+        "{}",
+        "⚠ Keep a copy of the keyfile somewhere safe: the vault cannot be opened without it"
+            .yellow()
+    );
+    Ok(())
+}
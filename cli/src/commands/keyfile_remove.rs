@@ -0,0 +1,51 @@
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Confirm;
+use securefox_core::{config::ConfigManager, keyslot::Credentials, storage::VaultStorage};
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>, force: bool) -> Result<()> {
+    let vault_dir = vault_path.ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?;
+
+    let storage = VaultStorage::with_path(vault_dir.join("vault.sf"));
+    if !storage.exists() {
+        return Err(anyhow::anyhow!(
+            "Vault not found. Run 'securefox init' to create one."
+        ));
+    }
+
+    let keyfile = crate::utils::load_keyfile(&storage, None)?
+        .ok_or_else(|| anyhow::anyhow!("No keyfile is configured for this vault"))?;
+
+    if !force
+        && !Confirm::new()
+            .with_prompt("Remove the keyfile requirement and unlock with the password only?")
+            .default(false)
+            .interact()?
+    {
+        return Ok(());
+    }
+
+    let password = crate::utils::get_password("Enter master password")?;
+
+    println!("Updating vault...");
+    let backup_path = storage.change_password(
+        Credentials::new(&password, Some(&keyfile)),
+        Credentials::new(&password, None),
+        None,
+        false,
+    )?;
+    println!("Backup written to {}", backup_path.display());
+
+    ConfigManager::new()?.update_keyfile_for(&vault_dir, None)?;
+
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        let sync = GitSync::init(&vault_dir)?;
+        sync.auto_commit_push("Removed keyfile")?;
+    }
+
+    println!("{} Keyfile requirement removed", "✓".green().bold());
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::{
+    config::ConfigManager, keyfile::Keyfile, keyslot::Credentials, storage::VaultStorage,
+};
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>, path: PathBuf) -> Result<()> {
+    let vault_dir = vault_path.ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?;
+
+    let storage = VaultStorage::with_path(vault_dir.join("vault.sf"));
+    if !storage.exists() {
+        return Err(anyhow::anyhow!(
+            "Vault not found. Run 'securefox init' to create one."
+        ));
+    }
+
+    // Reuse an existing keyfile (e.g. one made by KeePass), otherwise create one
+    let new_keyfile = if path.exists() {
+        Keyfile::load(&path)?
+    } else {
+        let keyfile = Keyfile::generate();
+        keyfile.write(&path)?;
+        println!("Generated keyfile at {}", path.display());
+        keyfile
+    };
+
+    let current_keyfile = crate::utils::load_keyfile(&storage, None)?;
+    let password = crate::utils::get_password("Enter master password")?;
+
+    println!("Updating vault...");
+    let backup_path = storage.change_password(
+        Credentials::new(&password, current_keyfile.as_ref()),
+        Credentials::new(&password, Some(&new_keyfile)),
+        None,
+        false,
+    )?;
+    println!("Backup written to {}", backup_path.display());
+
+    ConfigManager::new()?.update_keyfile_for(&vault_dir, Some(path.canonicalize()?))?;
+
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        let sync = GitSync::init(&vault_dir)?;
+        sync.auto_commit_push("Rotated keyfile")?;
+    }
+
+    println!(
+        "{} Vault now requires keyfile {}",
+        "✓".green().bold(),
+        new_keyfile.fingerprint().cyan()
+    );
+    Ok(())
+}
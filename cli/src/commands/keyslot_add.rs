@@ -0,0 +1,82 @@
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Password;
+use securefox_core::{
+    keyslot::{Credentials, Keyslot},
+    ssh_agent::SshAgent,
+    storage::VaultStorage,
+};
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    label: Option<String>,
+    kdf: String,
+    ssh_key: Option<String>,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    let storage = VaultStorage::with_path(&vault_path);
+    if !storage.exists() {
+        return Err(anyhow::anyhow!(
+            "Vault not found. Run 'securefox init' to create one."
+        ));
+    }
+
+    if let Some(fingerprint) = ssh_key {
+        return add_ssh_keyslot(&storage, &fingerprint, label);
+    }
+
+    let kdf_params = crate::utils::parse_kdf(&kdf)?;
+    let keyfile = crate::utils::load_keyfile(&storage, None)?;
+    let master_password = crate::utils::get_password("Enter current master password")?;
+
+    let new_password = Password::new()
+        .with_prompt("Enter password for the new keyslot")
+        .with_confirmation("Confirm password", "Passwords do not match")
+        .interact()?;
+
+    let slot = storage.add_keyslot(
+        Credentials::new(&master_password, keyfile.as_ref()),
+        |key, vault_id| {
+            let slot = Keyslot::password(&new_password, kdf_params, key, vault_id)?;
+            Ok(match label {
+                Some(label) => slot.with_label(label),
+                None => slot,
+            })
+        },
+    )?;
+
+    println!("{} Keyslot {} added", "✓".green().bold(), slot.id.cyan());
+    Ok(())
+}
+
+/// Add a keyslot opened by an Ed25519 key held in ssh-agent
+fn add_ssh_keyslot(storage: &VaultStorage, fingerprint: &str, label: Option<String>) -> Result<()> {
+    let mut agent = SshAgent::connect_env()?;
+    let identity = agent.identity(fingerprint)?;
+
+    let keyfile = crate::utils::load_keyfile(storage, None)?;
+    let master_password = crate::utils::get_password("Enter current master password")?;
+
+    let slot = storage.add_keyslot(
+        Credentials::new(&master_password, keyfile.as_ref()),
+        |key, vault_id| {
+            let slot = Keyslot::ssh_agent(&mut agent, &identity, key, vault_id)?;
+            Ok(match label {
+                Some(label) => slot.with_label(label),
+                None => slot,
+            })
+        },
+    )?;
+
+    println!(
+        "{} SSH keyslot {} added for {}",
+        "✓".green().bold(),
+        slot.id.cyan(),
+        identity.fingerprint()
+    );
+    Ok(())
+}
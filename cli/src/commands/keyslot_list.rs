@@ -0,0 +1,49 @@
+use anyhow::Result;
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    let container = VaultStorage::with_path(&vault_path).load_container()?;
+
+    if container.is_legacy() {
+        println!("Vault uses the legacy format and is encrypted directly with the master password");
+        println!("It will be upgraded to keyslots on the next save");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS);
+    table.set_header(vec![
+        Cell::new("ID").fg(Color::Blue),
+        Cell::new("Kind").fg(Color::Blue),
+        Cell::new("Label").fg(Color::Blue),
+        Cell::new("KDF").fg(Color::Blue),
+        Cell::new("Created").fg(Color::Blue),
+    ]);
+
+    for slot in &container.keyslots {
+        let kdf = slot
+            .kdf_params()
+            .map(|p| format!("{:?}", p.algorithm).to_lowercase())
+            .unwrap_or_else(|| "-".to_string());
+
+        table.add_row(vec![
+            Cell::new(&slot.id),
+            Cell::new(slot.kind.to_string()),
+            Cell::new(slot.label.as_deref().unwrap_or("-")),
+            Cell::new(kdf),
+            Cell::new(slot.created_at.format("%Y-%m-%d").to_string()),
+        ]);
+    }
+
+    println!("{table}");
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Confirm;
+use securefox_core::{keyslot::Credentials, storage::VaultStorage};
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>, id: String, force: bool) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    let storage = VaultStorage::with_path(&vault_path);
+    if !storage.exists() {
+        return Err(anyhow::anyhow!(
+            "Vault not found. Run 'securefox init' to create one."
+        ));
+    }
+
+    if !force
+        && !Confirm::new()
+            .with_prompt(format!(
+                "Remove keyslot {}? It will no longer unlock the vault",
+                id
+            ))
+            .default(false)
+            .interact()?
+    {
+        println!("Aborted");
+        return Ok(());
+    }
+
+    let keyfile = crate::utils::load_keyfile(&storage, None)?;
+    let password = crate::utils::get_password("Enter master password")?;
+    let removed = storage.remove_keyslot(Credentials::new(&password, keyfile.as_ref()), &id)?;
+
+    println!(
+        "{} Keyslot {} ({}) removed",
+        "✓".green().bold(),
+        removed.id.cyan(),
+        removed.kind
+    );
+    Ok(())
+}
@@ -1,12 +1,17 @@
 use anyhow::Result;
 use colored::Colorize;
 use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
-use securefox_core::models::ItemType;
+use securefox_core::{
+    models::{Item, ItemType},
+    storage::VaultStorage,
+};
 use std::path::PathBuf;
 
 pub async fn execute(
     vault_path: Option<PathBuf>,
+    item_type: Option<String>,
     folder: Option<String>,
+    tags: Vec<String>,
     search: Option<String>,
     detailed: bool,
 ) -> Result<()> {
@@ -15,18 +20,44 @@ pub async fn execute(
         .join("vault.sf");
 
     // Load vault
-    let (vault, _) = crate::utils::load_vault(&vault_path)?;
-
-    // Filter items
-    let mut items = vault.items.clone();
+    let storage = VaultStorage::with_path(&vault_path);
+    let (vault, _) = crate::utils::load_vault(&storage)?;
+
+    // Filter items; trashed ones are listed by `securefox trash list`
+    let mut items: Vec<_> = vault.active_items().cloned().collect();
+
+    // Filter by type
+    if let Some(item_type) = &item_type {
+        let item_type = match item_type.as_str() {
+            "login" => ItemType::LOGIN,
+            "note" => ItemType::SECURE_NOTE,
+            "card" => ItemType::CARD,
+            "identity" => ItemType::IDENTITY,
+            "ssh-key" => ItemType::SSH_KEY,
+            _ => anyhow::bail!("Unknown item type: {}", item_type),
+        };
+        items.retain(|i| i.item_type == item_type);
+    }
 
-    // Filter by folder
-    if let Some(folder_name) = &folder {
-        if let Some(folder) = vault.folders.iter().find(|f| f.name == *folder_name) {
-            items.retain(|i| i.folder_id == Some(folder.id.clone()));
-        }
+    // Filter by folder, including the folders nested under it
+    if let Some(folder) = &folder {
+        let folder = crate::utils::find_folder(&vault, folder)?;
+        let folder_ids: Vec<_> = vault
+            .folders
+            .iter()
+            .filter(|f| f.is_within(&folder.name))
+            .map(|f| f.id.as_str())
+            .collect();
+        items.retain(|i| {
+            i.folder_id
+                .as_deref()
+                .is_some_and(|id| folder_ids.contains(&id))
+        });
     }
 
+    // Filter by tags; items must have all of them
+    items.retain(|i| tags.iter().all(|t| i.has_tag(t)));
+
     // Filter by search
     if let Some(query) = &search {
         let query_lower = query.to_lowercase();
@@ -34,7 +65,7 @@ pub async fn execute(
             i.name.to_lowercase().contains(&query_lower)
                 || i.notes
                     .as_ref()
-                    .map(|n| n.to_lowercase().contains(&query_lower))
+                    .map(|n| n.expose_secret().to_lowercase().contains(&query_lower))
                     .unwrap_or(false)
                 || (i
                     .login
@@ -63,6 +94,7 @@ pub async fn execute(
             Cell::new("Username").fg(Color::Blue),
             Cell::new("URL").fg(Color::Blue),
             Cell::new("TOTP").fg(Color::Blue),
+            Cell::new("Tags").fg(Color::Blue),
             Cell::new("Modified").fg(Color::Blue),
         ]);
 
@@ -72,15 +104,11 @@ pub async fn execute(
                 ItemType::SECURE_NOTE => "Note".yellow(),
                 ItemType::CARD => "Card".cyan(),
                 ItemType::IDENTITY => "Identity".magenta(),
+                ItemType::SSH_KEY => "SSH key".blue(),
                 _ => "Unknown".red(),
             };
 
-            let username = item
-                .login
-                .as_ref()
-                .and_then(|l| l.username.as_ref())
-                .map(|u| u.as_str())
-                .unwrap_or("-");
+            let username = subtitle(&item);
 
             let url = item
                 .login
@@ -97,6 +125,7 @@ pub async fn execute(
                 .map(|_| "✓".green())
                 .unwrap_or_else(|| "-".normal());
 
+            let tags: Vec<_> = item.tags.iter().map(String::as_str).collect();
             let modified = item.revision_date.format("%Y-%m-%d").to_string();
 
             table.add_row(vec![
@@ -105,6 +134,7 @@ pub async fn execute(
                 Cell::new(username),
                 Cell::new(url),
                 Cell::new(has_totp),
+                Cell::new(tags.join(", ")),
                 Cell::new(modified),
             ]);
         }
@@ -121,15 +151,11 @@ pub async fn execute(
                 ItemType::SECURE_NOTE => "Note",
                 ItemType::CARD => "Card",
                 ItemType::IDENTITY => "Identity",
+                ItemType::SSH_KEY => "SSH key",
                 _ => "Unknown",
             };
 
-            let username = item
-                .login
-                .as_ref()
-                .and_then(|l| l.username.as_ref())
-                .map(|u| u.as_str())
-                .unwrap_or("-");
+            let username = subtitle(&item);
 
             table.add_row(vec![
                 Cell::new(&item.name),
@@ -143,3 +169,14 @@ pub async fn execute(
 
     Ok(())
 }
+
+/// Username of a login, or fingerprint of an SSH key, as Bitwarden shows them
+///
+/// Private keys are never listed.
+fn subtitle(item: &Item) -> &str {
+    item.login
+        .as_ref()
+        .and_then(|l| l.username.as_deref())
+        .or_else(|| item.ssh_key.as_ref().map(|k| k.key_fingerprint.as_str()))
+        .unwrap_or("-")
+}
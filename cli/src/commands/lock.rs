@@ -1 +1,23 @@
-pub use super::placeholder::lock::execute;
+use anyhow::Result;
+use colored::Colorize;
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>) -> Result<()> {
+    let vault_dir = vault_path.ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?;
+
+    #[cfg(feature = "keychain")]
+    {
+        use securefox_core::keychain::Keychain;
+        let keychain = Keychain::for_vault(&vault_dir);
+        if keychain.has_master_key() {
+            keychain.delete_master_key()?;
+            println!("Removed remembered vault key from system keychain");
+        }
+    }
+
+    #[cfg(not(feature = "keychain"))]
+    let _ = vault_dir;
+
+    println!("{} Vault locked", "✓".green().bold());
+    Ok(())
+}
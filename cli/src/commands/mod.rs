@@ -1,25 +1,90 @@
 pub mod placeholder;
 
 pub mod add;
+pub mod diff;
+pub mod doctor;
 pub mod edit;
 pub mod export;
 pub mod generate;
+pub mod history;
 pub mod import;
 pub mod init;
 pub mod list;
 pub mod lock;
+pub mod passwd;
 pub mod remove;
+pub mod restore;
 pub mod show;
 pub mod sync;
 pub mod totp;
 pub mod unlock;
 
+// Attachment subcommands
+pub mod attach_add;
+pub mod attach_get;
+pub mod attach_rm;
+
+// Backup subcommands
+pub mod backup_create;
+pub mod backup_list;
+pub mod backup_policy;
+pub mod backup_restore;
+pub mod backup_verify;
+
+// Folder subcommands
+pub mod folder_add;
+pub mod folder_list;
+pub mod folder_move;
+pub mod folder_remove;
+pub mod folder_rename;
+
+// Tag subcommands
+pub mod tag_list;
+pub mod tag_merge;
+pub mod tag_remove;
+pub mod tag_rename;
+
+// SSH key subcommands
+pub mod ssh_key_add;
+pub mod ssh_key_export;
+
+// Trash subcommands
+pub mod trash_list;
+pub mod trash_purge;
+pub mod trash_restore;
+pub mod trash_retention;
+
+// Vault subcommands
+pub mod vault_add;
+pub mod vault_list;
+pub mod vault_remove;
+pub mod vault_use;
+
 // Sync subcommands
 pub mod sync_config;
 pub mod sync_disable;
 pub mod sync_enable;
 pub mod sync_status;
 
+// Keyslot subcommands
+pub mod keyslot_add;
+pub mod keyslot_list;
+pub mod keyslot_remove;
+
+// Keyfile subcommands
+pub mod keyfile_generate;
+pub mod keyfile_remove;
+pub mod keyfile_rotate;
+
+// PIN subcommands
+pub mod pin_remove;
+pub mod pin_set;
+pub mod pin_status;
+
+// Recovery subcommands
+pub mod recovery_restore;
+pub mod recovery_split;
+
 // Service commands
 pub mod service_disable;
 pub mod service_enable;
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Password;
+use securefox_core::{config::ConfigManager, keyslot::Credentials, storage::VaultStorage};
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    kdf: Option<String>,
+    rekey: bool,
+    auto_upgrade: Option<String>,
+) -> Result<()> {
+    let vault_dir = vault_path.ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?;
+    let vault_path = vault_dir.join("vault.sf");
+
+    // Only update the policy when requested
+    if let Some(policy) = auto_upgrade {
+        let enabled = match policy.to_lowercase().as_str() {
+            "on" | "true" => true,
+            "off" | "false" => false,
+            _ => return Err(anyhow::anyhow!("Invalid policy. Use 'on' or 'off'")),
+        };
+        ConfigManager::new()?.update_auto_upgrade_kdf(enabled)?;
+        println!(
+            "{} Automatic KDF upgrade {}",
+            "✓".green().bold(),
+            if enabled { "enabled" } else { "disabled" }
+        );
+        return Ok(());
+    }
+
+    let storage = VaultStorage::with_path(&vault_path);
+    if !storage.exists() {
+        return Err(anyhow::anyhow!(
+            "Vault not found. Run 'securefox init' to create one."
+        ));
+    }
+
+    let kdf_params = kdf.as_deref().map(crate::utils::parse_kdf).transpose()?;
+
+    let keyfile = crate::utils::load_keyfile(&storage, None)?;
+    let current = Password::new()
+        .with_prompt("Enter current master password")
+        .interact()?;
+    let new = Password::new()
+        .with_prompt("Enter new master password (empty to keep current)")
+        .with_confirmation("Confirm new master password", "Passwords do not match")
+        .allow_empty_password(true)
+        .interact()?;
+    let new = if new.is_empty() { current.clone() } else { new };
+
+    if rekey {
+        println!(
+            "{}",
+            "⚠ Re-encrypting with a new data key removes all other keyslots".yellow()
+        );
+    }
+
+    println!("Updating vault...");
+    let backup_path = storage.change_password(
+        Credentials::new(&current, keyfile.as_ref()),
+        Credentials::new(&new, keyfile.as_ref()),
+        kdf_params,
+        rekey,
+    )?;
+    println!("Backup written to {}", backup_path.display());
+
+    // A remembered data key no longer matches after re-keying
+    #[cfg(feature = "keychain")]
+    if rekey {
+        use securefox_core::keychain::Keychain;
+        let keychain = Keychain::for_vault(crate::utils::vault_dir(&storage));
+        if keychain.has_master_key() {
+            let _ = keychain.delete_master_key();
+            println!("Removed remembered key from keychain");
+        }
+    }
+
+    // Git sync
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        let sync = GitSync::init(&vault_dir)?;
+        sync.auto_commit_push("Changed master password")?;
+    }
+
+    println!("{} Master password updated", "✓".green().bold());
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Password;
+use securefox_core::{
+    config::ConfigManager, keyslot::Credentials, models::PinPolicy, pin::MIN_PIN_LENGTH,
+    storage::VaultStorage,
+};
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    max_attempts: Option<u32>,
+    valid_hours: Option<u64>,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    let storage = VaultStorage::with_path(&vault_path);
+    if !storage.exists() {
+        return Err(anyhow::anyhow!(
+            "Vault not found. Run 'securefox init' to create one."
+        ));
+    }
+
+    // Only touch the config when a limit is given
+    let mut policy = crate::utils::pin_policy();
+    if max_attempts.is_some() || valid_hours.is_some() {
+        policy = PinPolicy {
+            max_attempts: max_attempts.unwrap_or(policy.max_attempts),
+            valid_hours: valid_hours.unwrap_or(policy.valid_hours),
+        };
+        if policy.max_attempts == 0 {
+            return Err(anyhow::anyhow!("--max-attempts must be at least 1"));
+        }
+        ConfigManager::new()?.update_pin_policy(Some(policy.clone()))?;
+    }
+
+    // Setting a PIN always requires the master password
+    let keyfile = crate::utils::load_keyfile(&storage, None)?;
+    let password = crate::utils::get_password("Enter master password")?;
+    let (_, key) = storage.unlock(Credentials::new(&password, keyfile.as_ref()))?;
+
+    let pin = Password::new()
+        .with_prompt(format!(
+            "Enter PIN (at least {} characters)",
+            MIN_PIN_LENGTH
+        ))
+        .with_confirmation("Confirm PIN", "PINs do not match")
+        .interact()?;
+    storage.enable_pin(&pin, &key, &policy)?;
+
+    println!(
+        "{} PIN unlock enabled on this machine ({} attempts, master password required every {} hours)",
+        "✓".green().bold(),
+        policy.max_attempts,
+        policy.valid_hours
+    );
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    let policy = crate::utils::pin_policy();
+    let Some(status) = VaultStorage::with_path(&vault_path).pin_store().status()? else {
+        println!("PIN unlock: {}", "disabled".yellow());
+        return Ok(());
+    };
+
+    if status.armed {
+        println!("PIN unlock: {}", "ready".green());
+        if let Some(expires_at) = status.expires_at {
+            println!(
+                "Master password required after: {}",
+                expires_at.format("%Y-%m-%d %H:%M UTC")
+            );
+        }
+        println!(
+            "Attempts left: {}",
+            policy.max_attempts.saturating_sub(status.failed_attempts)
+        );
+    } else if status.locked_out {
+        println!(
+            "PIN unlock: {} after too many wrong attempts (run {} to enable it again)",
+            "locked".red(),
+            "securefox pin set".cyan()
+        );
+    } else {
+        println!(
+            "PIN unlock: {} (unlock with the master password to re-enable)",
+            "paused".yellow()
+        );
+    }
+    Ok(())
+}
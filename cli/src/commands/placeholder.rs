@@ -25,6 +25,7 @@ pub mod add {
 pub mod edit {
     use super::*;
 
+    #[allow(dead_code)]
     pub async fn execute(_vault_path: Option<PathBuf>, _item: String) -> Result<()> {
         Ok(())
     }
@@ -119,19 +120,11 @@ pub mod list {
     }
 }
 
-// lock.rs
-pub mod lock {
-    use super::*;
-
-    pub async fn execute(_vault_path: Option<PathBuf>) -> Result<()> {
-        Ok(())
-    }
-}
-
 // remove.rs
 pub mod remove {
     use super::*;
 
+    #[allow(dead_code)]
     pub async fn execute(_vault_path: Option<PathBuf>, _item: String, _force: bool) -> Result<()> {
         Ok(())
     }
@@ -205,15 +198,6 @@ pub mod totp {
     }
 }
 
-// unlock.rs
-pub mod unlock {
-    use super::*;
-
-    pub async fn execute(_vault_path: Option<PathBuf>, _remember: bool) -> Result<()> {
-        Ok(())
-    }
-}
-
 // serve.rs
 #[cfg(feature = "serve")]
 pub mod serve {
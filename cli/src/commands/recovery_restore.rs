@@ -0,0 +1,102 @@
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::{Input, Password};
+use securefox_core::{
+    crypto::EncryptionKey,
+    keyslot::Credentials,
+    recovery::{self, RecoveryShare},
+    storage::VaultStorage,
+};
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>, reset: bool, remember: bool) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    let storage = VaultStorage::with_path(&vault_path);
+    if !storage.exists() {
+        return Err(anyhow::anyhow!(
+            "Vault not found. Run 'securefox init' to create one."
+        ));
+    }
+
+    let recovery_key = read_recovery_key()?;
+    let (vault, key) = storage.unlock_with_recovery_key(&recovery_key)?;
+    println!(
+        "{} Recovery key accepted ({} items)",
+        "✓".green().bold(),
+        vault.items.len()
+    );
+
+    if reset {
+        let password = Password::new()
+            .with_prompt("Enter new master password")
+            .with_confirmation("Confirm new master password", "Passwords do not match")
+            .interact()?;
+        let keyfile = crate::utils::load_keyfile(&storage, None)?;
+
+        println!("Updating vault...");
+        let backup_path = storage.reset_password(
+            &recovery_key,
+            Credentials::new(&password, keyfile.as_ref()),
+            None,
+        )?;
+        println!("Backup written to {}", backup_path.display());
+
+        #[cfg(feature = "git")]
+        {
+            use securefox_core::git_sync::GitSync;
+            if let Some(vault_dir) = vault_path.parent() {
+                let sync = GitSync::init(vault_dir)?;
+                sync.auto_commit_push("Reset master password from recovery shares")?;
+            }
+        }
+
+        println!("{} Master password reset", "✓".green().bold());
+    }
+
+    if remember {
+        #[cfg(feature = "keychain")]
+        {
+            use securefox_core::keychain::Keychain;
+            Keychain::for_vault(crate::utils::vault_dir(&storage))
+                .store_master_key(key.as_bytes())?;
+            println!("Vault key remembered in system keychain");
+        }
+
+        #[cfg(not(feature = "keychain"))]
+        {
+            let _ = key;
+            anyhow::bail!("Keychain feature not enabled");
+        }
+    }
+
+    Ok(())
+}
+
+/// Prompt for shares until the threshold recorded in them is reached
+fn read_recovery_key() -> Result<EncryptionKey> {
+    let mut shares: Vec<RecoveryShare> = Vec::new();
+    loop {
+        let prompt = match shares.first() {
+            Some(first) => format!("Enter share {} of {}", shares.len() + 1, first.threshold()),
+            None => "Enter a recovery share".to_string(),
+        };
+        let input: String = Input::new().with_prompt(prompt).interact_text()?;
+
+        match input.parse::<RecoveryShare>() {
+            Ok(share) if shares.iter().any(|s| s.index() == share.index()) => {
+                println!("{}", "Share already entered".yellow());
+            }
+            Ok(share) => shares.push(share),
+            Err(e) => println!("{} {}", "✗".red(), e),
+        }
+
+        if let Some(first) = shares.first() {
+            if shares.len() >= first.threshold() as usize {
+                return Ok(recovery::combine(&shares)?);
+            }
+        }
+    }
+}
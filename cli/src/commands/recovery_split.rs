@@ -0,0 +1,94 @@
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Confirm;
+use securefox_core::{
+    crypto::generate_key,
+    keyslot::{Credentials, KeyslotKind},
+    recovery,
+    storage::VaultStorage,
+};
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    shares: u8,
+    threshold: u8,
+    format: String,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    let qr = match format.to_lowercase().as_str() {
+        "text" => false,
+        "qr" => true,
+        _ => return Err(anyhow::anyhow!("Invalid format. Use 'text' or 'qr'")),
+    };
+
+    let storage = VaultStorage::with_path(&vault_path);
+    if !storage.exists() {
+        return Err(anyhow::anyhow!(
+            "Vault not found. Run 'securefox init' to create one."
+        ));
+    }
+
+    let has_recovery = storage
+        .load_container()?
+        .keyslots
+        .iter()
+        .any(|s| s.kind == KeyslotKind::Recovery);
+    if has_recovery
+        && !Confirm::new()
+            .with_prompt("Replace the existing recovery key? Its shares will stop working")
+            .default(false)
+            .interact()?
+    {
+        return Ok(());
+    }
+
+    // Validate the parameters before touching the vault
+    let recovery_key = generate_key();
+    let split = recovery::split(&recovery_key, shares, threshold)?;
+
+    let keyfile = crate::utils::load_keyfile(&storage, None)?;
+    let password = crate::utils::get_password("Enter master password")?;
+    storage.set_recovery_key(
+        Credentials::new(&password, keyfile.as_ref()),
+        &recovery_key,
+        Some(format!("{} of {} shares", threshold, shares)),
+    )?;
+
+    if qr {
+        // One compact string per line, ready to feed to a QR code generator
+        for share in &split {
+            println!("{}", share.to_compact_string());
+        }
+    } else {
+        println!(
+            "{}",
+            format!(
+                "Recovery key split into {} shares; any {} of them unlock the vault",
+                shares, threshold
+            )
+            .cyan()
+            .bold()
+        );
+        for share in &split {
+            println!();
+            println!(
+                "Share {} of {} (set {})",
+                share.index(),
+                shares,
+                share.set_id()
+            );
+            println!("{}", share);
+        }
+        println!();
+        println!(
+            "{}",
+            "⚠ Give each share to a different person or place; they are shown only once".yellow()
+        );
+    }
+
+    Ok(())
+}
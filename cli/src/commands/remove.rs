@@ -1 +1,64 @@
-pub use super::placeholder::remove::execute;
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Confirm;
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    item_name: String,
+    permanent: bool,
+    force: bool,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (mut vault, key) = crate::utils::load_vault(&storage)?;
+    let item = crate::utils::find_item_mut(&mut vault, &item_name)?.clone();
+
+    if permanent
+        && !force
+        && !Confirm::new()
+            .with_prompt(format!("Permanently delete '{}'?", item.name))
+            .default(false)
+            .interact()?
+    {
+        println!("Cancelled");
+        return Ok(());
+    }
+
+    if permanent {
+        // Save the vault before deleting the blobs, so it never points at a missing one
+        vault.remove_item(&item.id);
+        storage.save_with_key(&vault, &key)?;
+        storage.attachments().remove_all(&item.attachments)?;
+    } else {
+        vault.trash_item(&item.id);
+        storage.save_with_key(&vault, &key)?;
+    }
+
+    // Git sync
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        if let Some(parent) = vault_path.parent() {
+            let sync = GitSync::init(parent)?;
+            let action = if permanent { "Deleted" } else { "Trashed" };
+            sync.auto_commit_push(&format!("{} item: {}", action, item.name))?;
+        }
+    }
+
+    if permanent {
+        println!("{} Deleted '{}'", "✓".green().bold(), item.name);
+    } else {
+        println!(
+            "{} Moved '{}' to the trash (undo with 'securefox trash restore')",
+            "✓".green().bold(),
+            item.name
+        );
+    }
+    Ok(())
+}
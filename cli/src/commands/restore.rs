@@ -0,0 +1,72 @@
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Confirm;
+use securefox_core::{
+    diff::vault_diff,
+    snapshot::{self, VaultSnapshot},
+    storage::VaultStorage,
+};
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>, at: String, force: bool) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (vault, key) = crate::utils::load_vault(&storage)?;
+
+    let at = VaultSnapshot::parse(&at);
+    let past = crate::utils::open_snapshot(&storage, &key, &at)?;
+    let diff = vault_diff(&vault, &past)?;
+
+    println!("{}", format!("Changes to restore from {}:", at).bold());
+    super::diff::print_diff(&diff, false);
+    if diff.is_empty() {
+        return Ok(());
+    }
+
+    println!();
+    if !force
+        && !Confirm::new()
+            .with_prompt("Replace all items and folders with this state?")
+            .default(false)
+            .interact()?
+    {
+        println!("Cancelled");
+        return Ok(());
+    }
+
+    let (backup, restored) = snapshot::restore(&storage, &key, past)?;
+
+    // Blobs deleted since the snapshot cannot be brought back from here
+    let attachments = storage.attachments();
+    let missing = restored
+        .items
+        .iter()
+        .flat_map(|i| &i.attachments)
+        .filter(|a| !attachments.contains(a))
+        .count();
+
+    // Git sync
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        if let Some(parent) = vault_path.parent() {
+            let sync = GitSync::init(parent)?;
+            sync.auto_commit_push(&format!("Restored vault from {}", at))?;
+        }
+    }
+
+    println!("{} Restored the vault from {}", "✓".green().bold(), at);
+    println!("Previous state saved to {}", backup.display());
+    if missing > 0 {
+        println!(
+            "{} {} attachments no longer have their contents",
+            "Warning:".yellow(),
+            missing
+        );
+    }
+    Ok(())
+}
@@ -2,7 +2,7 @@ use anyhow::Result;
 use colored::Colorize;
 use copypasta_ext::prelude::*;
 use copypasta_ext::x11_fork::ClipboardContext;
-use securefox_core::totp::TotpConfig;
+use securefox_core::{models::SecretString, storage::VaultStorage, totp::TotpConfig};
 use std::path::PathBuf;
 
 pub async fn execute(
@@ -10,18 +10,19 @@ pub async fn execute(
     item_name: String,
     copy: bool,
     totp: bool,
+    history: bool,
 ) -> Result<()> {
     let vault_path = vault_path
         .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
         .join("vault.sf");
 
     // Load vault
-    let (vault, _) = crate::utils::load_vault(&vault_path)?;
+    let storage = VaultStorage::with_path(&vault_path);
+    let (vault, _) = crate::utils::load_vault(&storage)?;
 
     // Find item by name or ID
     let item = vault
-        .items
-        .iter()
+        .active_items()
         .find(|i| i.name == item_name || i.id == item_name)
         .ok_or_else(|| anyhow::anyhow!("Item not found: {}", item_name))?;
 
@@ -33,17 +34,22 @@ pub async fn execute(
         println!("{}: {}", "Favorite".cyan().bold(), "★".yellow());
     }
 
+    if !item.tags.is_empty() {
+        let tags: Vec<_> = item.tags.iter().map(String::as_str).collect();
+        println!("{}: {}", "Tags".cyan().bold(), tags.join(", "));
+    }
+
     // Display type-specific fields
     if let Some(ref login) = item.login {
         if let Some(ref username) = login.username {
             println!("{}: {}", "Username".cyan().bold(), username);
         }
 
-        if let Some(ref password) = login.password {
+        if let Some(password) = login.password.as_ref().map(SecretString::expose_secret) {
             if copy && !totp {
                 // Copy password to clipboard
                 if let Ok(mut ctx) = ClipboardContext::new() {
-                    let _ = ctx.set_contents(password.clone());
+                    let _ = ctx.set_contents(password.to_string());
                 }
                 println!(
                     "{}: {} (copied to clipboard)",
@@ -55,10 +61,10 @@ pub async fn execute(
             }
         }
 
-        if let Some(ref totp_secret) = login.totp {
+        if let Some(totp_secret) = login.totp.as_ref().map(SecretString::expose_secret) {
             if totp {
                 let config = TotpConfig::from_uri(totp_secret).or_else(|_| {
-                    Ok::<TotpConfig, anyhow::Error>(TotpConfig::new(totp_secret.clone()))
+                    Ok::<TotpConfig, anyhow::Error>(TotpConfig::new(totp_secret.to_string()))
                 })?;
                 let code = config.generate()?;
                 let ttl = config.ttl();
@@ -93,8 +99,55 @@ pub async fn execute(
         }
     }
 
+    if let Some(ref ssh_key) = item.ssh_key {
+        if let Some(key_type) = ssh_key.key_type() {
+            println!("{}: {}", "Key Type".cyan().bold(), key_type);
+        }
+        println!("{}: {}", "Public Key".cyan().bold(), ssh_key.public_key);
+        println!(
+            "{}: {}",
+            "Fingerprint".cyan().bold(),
+            ssh_key.key_fingerprint
+        );
+        println!(
+            "{}: {} (use {} to save it)",
+            "Private Key".cyan().bold(),
+            "hidden".dimmed(),
+            "securefox ssh-key export".cyan()
+        );
+    }
+
     if let Some(ref notes) = item.notes {
-        println!("{}: {}", "Notes".cyan().bold(), notes);
+        println!("{}: {}", "Notes".cyan().bold(), notes.expose_secret());
+    }
+
+    if history {
+        if item.password_history.is_empty() {
+            println!("{}: {}", "Password History".cyan().bold(), "none".dimmed());
+        }
+        for entry in &item.password_history {
+            println!(
+                "{}: {} (replaced {})",
+                "Previous Password".cyan().bold(),
+                entry.password.expose_secret(),
+                entry.last_used_date.format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+    } else if !item.password_history.is_empty() {
+        println!(
+            "{}: {} previous passwords (use --history to show)",
+            "Password History".cyan().bold(),
+            item.password_history.len()
+        );
+    }
+
+    for attachment in &item.attachments {
+        println!(
+            "{}: {} ({} bytes)",
+            "Attachment".cyan().bold(),
+            attachment.file_name,
+            attachment.size
+        );
     }
 
     println!(
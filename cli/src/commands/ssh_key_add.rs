@@ -0,0 +1,77 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::{
+    models::{Item, SshKeyData},
+    ssh_keys::KeyAlgorithm,
+    storage::VaultStorage,
+};
+use std::fs;
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    name: String,
+    file: Option<PathBuf>,
+    key_type: String,
+    comment: Option<String>,
+    tags: Vec<String>,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Read or generate the key before unlocking, so mistakes fail early
+    let ssh_key = match &file {
+        Some(file) => {
+            let contents = fs::read_to_string(file)?;
+            let passphrase = if SshKeyData::is_encrypted(&contents)? {
+                Some(crate::utils::get_password("Key passphrase")?)
+            } else {
+                None
+            };
+            SshKeyData::from_openssh(&contents, passphrase.as_deref())?
+        }
+        None => {
+            let algorithm: KeyAlgorithm = key_type.parse()?;
+            if matches!(algorithm, KeyAlgorithm::Rsa(_)) {
+                println!("Generating {} key...", algorithm);
+            }
+            SshKeyData::generate(algorithm, comment.as_deref().unwrap_or(&name))?
+        }
+    };
+
+    let mut item = Item::new_ssh_key(&name, ssh_key);
+    item.add_tags(&tags)?;
+    let public_key = item.ssh_key.as_ref().map(|k| k.public_key.clone());
+    let fingerprint = item.ssh_key.as_ref().map(|k| k.key_fingerprint.clone());
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (mut vault, key) = crate::utils::load_vault(&storage)?;
+    vault.add_item(item);
+    storage.save_with_key(&vault, &key)?;
+
+    // Git sync
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        if let Some(parent) = vault_path.parent() {
+            let sync = GitSync::init(parent)?;
+            sync.auto_commit_push(&format!("Added SSH key: {}", name))?;
+        }
+    }
+
+    let action = if file.is_some() {
+        "imported"
+    } else {
+        "generated"
+    };
+    println!("{} SSH key '{}' {}", "✓".green().bold(), name, action);
+    println!(
+        "{}: {}",
+        "Fingerprint".cyan().bold(),
+        fingerprint.unwrap_or_default()
+    );
+    println!("{}", public_key.unwrap_or_default());
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Password;
+use securefox_core::storage::VaultStorage;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    item_name: String,
+    public: bool,
+    output: Option<PathBuf>,
+    passphrase: bool,
+    force: bool,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (vault, _) = crate::utils::load_vault(&storage)?;
+    let item = vault
+        .active_items()
+        .find(|i| i.name == item_name || i.id == item_name)
+        .ok_or_else(|| anyhow::anyhow!("Item not found: {}", item_name))?;
+    let ssh_key = item
+        .ssh_key
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("'{}' is not an SSH key", item.name))?;
+
+    let public_key = format!("{}\n", ssh_key.public_key);
+    let private_key;
+    let contents = if public {
+        &public_key
+    } else {
+        let passphrase = if passphrase {
+            Some(
+                Password::new()
+                    .with_prompt("Key passphrase")
+                    .with_confirmation("Confirm passphrase", "Passphrases do not match")
+                    .interact()?,
+            )
+        } else {
+            None
+        };
+        private_key = ssh_key.export_private_key(passphrase.as_deref())?;
+        &*private_key
+    };
+
+    let Some(output) = output else {
+        print!("{}", contents);
+        return Ok(());
+    };
+
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(if public { 0o644 } else { 0o600 });
+    }
+    let mut file = options.open(&output).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => anyhow::anyhow!(
+            "{} already exists; use --force to overwrite it",
+            output.display()
+        ),
+        _ => e.into(),
+    })?;
+    file.write_all(contents.as_bytes())?;
+
+    let which = if public { "public" } else { "private" };
+    println!(
+        "{} Saved the {} key of '{}' to {}",
+        "✓".green().bold(),
+        which,
+        item.name,
+        output.display()
+    );
+    Ok(())
+}
@@ -20,7 +20,7 @@ pub async fn execute(vault_path: Option<PathBuf>, remote_url: String) -> Result<
         // Update standalone config file
         let config_manager = ConfigManager::new()?;
         config_manager
-            .update_remote_url(Some(remote_url.clone()))
+            .update_remote_url_for(&vault_path, Some(remote_url.clone()))
             .context("Failed to update config file")?;
 
         println!("✓ Git remote configured: {}", remote_url);
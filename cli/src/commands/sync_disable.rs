@@ -19,7 +19,7 @@ pub async fn execute(vault_path: Option<PathBuf>) -> Result<()> {
     // Update standalone config file
     let config_manager = ConfigManager::new()?;
     config_manager
-        .update_sync_config(Some(disabled_config))
+        .update_sync_config_for(&vault_path, Some(disabled_config))
         .context("Failed to update config file")?;
 
     println!("✓ Auto-sync disabled");
@@ -23,7 +23,7 @@ pub async fn execute(vault_path: Option<PathBuf>, mode: String, interval: u64) -
     // Update standalone config file
     let config_manager = ConfigManager::new()?;
     config_manager
-        .update_sync_config(Some(sync_config.clone()))
+        .update_sync_config_for(&vault_path, Some(sync_config.clone()))
         .context("Failed to update config file")?;
 
     // Print configuration
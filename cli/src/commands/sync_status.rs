@@ -4,17 +4,21 @@ use securefox_core::config::ConfigManager;
 use securefox_core::models::SyncMode;
 use std::path::PathBuf;
 
-pub async fn execute(_vault_path: Option<PathBuf>) -> Result<()> {
+pub async fn execute(vault_path: Option<PathBuf>) -> Result<()> {
+    let vault_path = vault_path.ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?;
     let config_manager = ConfigManager::new()?;
     let config = config_manager.load().context("Failed to load config")?;
 
     println!("{}", "Sync Status".bold());
     println!("───────────────────────────────────");
+    if let Some((name, _)) = config.profile_for(&vault_path) {
+        println!("Vault:       {}", name.cyan());
+    }
     println!();
 
     // Git Configuration Section
     println!("{}", "Git Configuration".bold());
-    if let Some(url) = &config.remote_url {
+    if let Some(url) = config.remote_url_for(&vault_path) {
         println!("Remote URL:  {}", url.green());
         println!("Git Status:  {}", "Configured".green());
     } else {
@@ -26,7 +30,7 @@ pub async fn execute(_vault_path: Option<PathBuf>) -> Result<()> {
     // Auto-Sync Configuration Section
     println!("{}", "Auto-Sync Configuration".bold());
 
-    if let Some(sync_config) = &config.sync_config {
+    if let Some(sync_config) = config.sync_config_for(&vault_path) {
         println!(
             "Status:      {}",
             if sync_config.enabled {
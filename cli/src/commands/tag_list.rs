@@ -0,0 +1,35 @@
+use anyhow::Result;
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (vault, _) = crate::utils::load_vault(&storage)?;
+
+    let tags = vault.tags();
+    if tags.is_empty() {
+        println!("No tags");
+        return Ok(());
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![
+            Cell::new("Tag").fg(Color::Blue),
+            Cell::new("Items").fg(Color::Blue),
+        ]);
+    for (tag, count) in tags {
+        table.add_row(vec![Cell::new(tag), Cell::new(count)]);
+    }
+
+    println!("{table}");
+    Ok(())
+}
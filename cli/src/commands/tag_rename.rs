@@ -0,0 +1,39 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>, tag: String, new_tag: String) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (mut vault, key) = crate::utils::load_vault(&storage)?;
+
+    let changed = vault.rename_tag(&tag, &new_tag)?;
+    if changed == 0 {
+        anyhow::bail!("No item is tagged '{}'", tag);
+    }
+    storage.save_with_key(&vault, &key)?;
+
+    // Git sync
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        if let Some(parent) = vault_path.parent() {
+            let sync = GitSync::init(parent)?;
+            sync.auto_commit_push(&format!("Renamed tag: {} -> {}", tag, new_tag))?;
+        }
+    }
+
+    println!(
+        "{} Renamed tag '{}' to '{}' on {} items",
+        "✓".green().bold(),
+        tag,
+        new_tag,
+        changed
+    );
+    Ok(())
+}
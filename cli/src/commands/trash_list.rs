@@ -0,0 +1,53 @@
+use anyhow::Result;
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(vault_path: Option<PathBuf>) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (vault, _) = crate::utils::load_vault(&storage)?;
+
+    let mut items: Vec<_> = vault.trashed_items().collect();
+    if items.is_empty() {
+        println!("The trash is empty");
+        return Ok(());
+    }
+    items.sort_by_key(|i| std::cmp::Reverse(i.deleted_date));
+
+    let retention_days = crate::utils::trash_retention_days();
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![
+            Cell::new("Name").fg(Color::Blue),
+            Cell::new("ID").fg(Color::Blue),
+            Cell::new("Deleted").fg(Color::Blue),
+            Cell::new("Purged after").fg(Color::Blue),
+        ]);
+
+    for item in items {
+        let deleted = item.deleted_date.unwrap_or(item.revision_date);
+        let purge = if retention_days == 0 {
+            "never".to_string()
+        } else {
+            (deleted + chrono::Duration::days(i64::from(retention_days)))
+                .format("%Y-%m-%d")
+                .to_string()
+        };
+        table.add_row(vec![
+            Cell::new(&item.name),
+            Cell::new(&item.id),
+            Cell::new(deleted.format("%Y-%m-%d %H:%M").to_string()),
+            Cell::new(purge),
+        ]);
+    }
+
+    println!("{table}");
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::Confirm;
+use securefox_core::storage::VaultStorage;
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    item_name: Option<String>,
+    force: bool,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    // Load vault
+    let storage = VaultStorage::with_path(&vault_path);
+    let (mut vault, key) = crate::utils::load_vault(&storage)?;
+
+    let prompt = match &item_name {
+        Some(name) => {
+            let item = crate::utils::find_trashed_item(&vault, name)?;
+            format!("Permanently delete '{}'?", item.name)
+        }
+        None => match vault.trashed_items().count() {
+            0 => {
+                println!("The trash is empty");
+                return Ok(());
+            }
+            count => format!("Permanently delete {} items in the trash?", count),
+        },
+    };
+
+    if !force
+        && !Confirm::new()
+            .with_prompt(prompt)
+            .default(false)
+            .interact()?
+    {
+        println!("Cancelled");
+        return Ok(());
+    }
+
+    let purged = match &item_name {
+        Some(name) => {
+            let id = crate::utils::find_trashed_item(&vault, name)?.id.clone();
+            vault.remove_item(&id).into_iter().collect()
+        }
+        None => vault.empty_trash(),
+    };
+
+    // Save the vault before deleting the blobs, so it never points at a missing one
+    storage.save_with_key(&vault, &key)?;
+    for item in &purged {
+        storage.attachments().remove_all(&item.attachments)?;
+    }
+
+    // Git sync
+    #[cfg(feature = "git")]
+    {
+        use securefox_core::git_sync::GitSync;
+        if let Some(parent) = vault_path.parent() {
+            let sync = GitSync::init(parent)?;
+            sync.auto_commit_push(&format!("Purged {} items from trash", purged.len()))?;
+        }
+    }
+
+    println!(
+        "{} Permanently deleted {} items",
+        "✓".green().bold(),
+        purged.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::config::ConfigManager;
+
+pub async fn execute(days: Option<u32>) -> Result<()> {
+    let Some(days) = days else {
+        match crate::utils::trash_retention_days() {
+            0 => println!("Trashed items are kept until purged"),
+            days => println!("Trashed items are purged after {} days", days),
+        }
+        return Ok(());
+    };
+
+    ConfigManager::new()?.update_trash_retention_days(Some(days))?;
+    if days == 0 {
+        println!(
+            "{} Trashed items are now kept until purged",
+            "✓".green().bold()
+        );
+    } else {
+        println!(
+            "{} Trashed items are now purged after {} days",
+            "✓".green().bold(),
+            days
+        );
+    }
+    Ok(())
+}
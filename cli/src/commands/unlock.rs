@@ -1 +1,63 @@
-pub use super::placeholder::unlock::execute;
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::{keyslot::Credentials, ssh_agent::SshAgent, storage::VaultStorage};
+use std::path::PathBuf;
+
+pub async fn execute(
+    vault_path: Option<PathBuf>,
+    remember: bool,
+    keyfile: Option<PathBuf>,
+    pin: bool,
+    ssh_key: Option<String>,
+    legacy_argon2: bool,
+) -> Result<()> {
+    let vault_path = vault_path
+        .ok_or_else(|| anyhow::anyhow!("Vault path not specified"))?
+        .join("vault.sf");
+
+    let storage = VaultStorage::with_path(&vault_path);
+    if !storage.exists() {
+        return Err(anyhow::anyhow!(
+            "Vault not found. Run 'securefox init' to create one."
+        ));
+    }
+
+    let (vault, key) = if let Some(fingerprint) = ssh_key {
+        let mut agent = SshAgent::connect_env()?;
+        storage.unlock_with_ssh_agent(&mut agent, Some(&fingerprint))?
+    } else if pin {
+        let pin = crate::utils::get_password("Enter PIN")?;
+        storage.unlock_with_pin(&pin, &crate::utils::pin_policy())?
+    } else {
+        let keyfile = crate::utils::load_keyfile(&storage, keyfile)?;
+        let password = crate::utils::get_password("Enter master password")?;
+        let unlocked = storage.unlock(
+            Credentials::new(&password, keyfile.as_ref()).with_legacy_argon2(legacy_argon2),
+        )?;
+        crate::utils::rearm_pin(&storage, &unlocked.1);
+        unlocked
+    };
+
+    if remember {
+        #[cfg(feature = "keychain")]
+        {
+            use securefox_core::keychain::Keychain;
+            Keychain::for_vault(crate::utils::vault_dir(&storage))
+                .store_master_key(key.as_bytes())?;
+            println!("Vault key remembered in system keychain");
+        }
+
+        #[cfg(not(feature = "keychain"))]
+        {
+            let _ = key;
+            anyhow::bail!("Keychain feature not enabled");
+        }
+    }
+
+    println!(
+        "{} Vault unlocked ({} items)",
+        "✓".green().bold(),
+        vault.items.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::{config::ConfigManager, models::VaultProfile, storage::VaultStorage};
+use std::path::PathBuf;
+
+pub async fn execute(name: String, path: PathBuf, remote: Option<String>) -> Result<()> {
+    // Profiles are matched by path, so store it absolute
+    let path = match path.canonicalize() {
+        Ok(path) => path,
+        Err(_) => std::path::absolute(&path)?,
+    };
+    let exists = VaultStorage::with_path(path.join("vault.sf")).exists();
+
+    #[cfg(feature = "git")]
+    if let (Some(url), true) = (&remote, exists) {
+        securefox_core::git_sync::GitSync::init(&path)?.set_remote(url)?;
+    }
+
+    let mut profile = VaultProfile::new(&path);
+    profile.remote_url = remote;
+    ConfigManager::new()?.add_vault(&name, profile)?;
+
+    println!(
+        "{} Added vault '{}' at {}",
+        "✓".green().bold(),
+        name,
+        path.display()
+    );
+    if !exists {
+        println!("Create it with 'securefox --profile {} init'", name);
+    }
+    println!("Select it with 'securefox vault use {}'", name);
+    Ok(())
+}
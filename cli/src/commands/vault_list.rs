@@ -0,0 +1,55 @@
+use anyhow::Result;
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
+use securefox_core::{
+    config::{default_vault_dir, ConfigManager},
+    models::DEFAULT_PROFILE,
+    storage::VaultStorage,
+};
+
+pub async fn execute() -> Result<()> {
+    let config = ConfigManager::new()?.load()?;
+    let current = config.current_vault.as_deref().unwrap_or(DEFAULT_PROFILE);
+
+    let mut vaults = vec![(
+        DEFAULT_PROFILE,
+        default_vault_dir()?,
+        config.remote_url.clone(),
+    )];
+    vaults.extend(config.vaults.iter().map(|(name, profile)| {
+        (
+            name.as_str(),
+            profile.path.clone(),
+            profile.remote_url.clone(),
+        )
+    }));
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec![
+            Cell::new("").fg(Color::Blue),
+            Cell::new("Name").fg(Color::Blue),
+            Cell::new("Path").fg(Color::Blue),
+            Cell::new("Remote").fg(Color::Blue),
+            Cell::new("Status").fg(Color::Blue),
+        ]);
+
+    for (name, path, remote) in vaults {
+        let exists = VaultStorage::with_path(path.join("vault.sf")).exists();
+        table.add_row(vec![
+            Cell::new(if name == current { "*" } else { "" }).fg(Color::Green),
+            Cell::new(name),
+            Cell::new(path.display()),
+            Cell::new(remote.as_deref().unwrap_or("-")),
+            if exists {
+                Cell::new("ready")
+            } else {
+                Cell::new("not initialized").fg(Color::Yellow)
+            },
+        ]);
+    }
+
+    println!("{table}");
+    Ok(())
+}
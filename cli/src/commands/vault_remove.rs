@@ -0,0 +1,11 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::config::ConfigManager;
+
+pub async fn execute(name: String) -> Result<()> {
+    let profile = ConfigManager::new()?.remove_vault(&name)?;
+
+    println!("{} Removed vault '{}'", "✓".green().bold(), name);
+    println!("Its files are still in {}", profile.path.display());
+    Ok(())
+}
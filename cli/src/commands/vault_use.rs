@@ -0,0 +1,16 @@
+use anyhow::Result;
+use colored::Colorize;
+use securefox_core::config::ConfigManager;
+
+pub async fn execute(name: String) -> Result<()> {
+    let config_manager = ConfigManager::new()?;
+    config_manager.use_vault(&name)?;
+
+    println!(
+        "{} Using vault '{}' at {}",
+        "✓".green().bold(),
+        name,
+        config_manager.vault_dir(None)?.display()
+    );
+    Ok(())
+}
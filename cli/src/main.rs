@@ -3,8 +3,10 @@ mod sync_daemon;
 mod utils;
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
 use colored::Colorize;
+use securefox_core::config::ConfigManager;
+use securefox_core::logging::RedactingWriter;
 use std::path::PathBuf;
 const GIT_HASH: &str = env!("GIT_HASH");
 const GIT_BRANCH: &str = env!("GIT_BRANCH");
@@ -33,9 +35,13 @@ fn print_version_info() {
 #[command(propagate_version = false)]
 struct Cli {
     /// Path to vault file
-    #[arg(short, long, env = "SECUREFOX_VAULT")]
+    #[arg(short, long, env = "SECUREFOX_VAULT", conflicts_with = "profile")]
     vault: Option<PathBuf>,
 
+    /// Registered vault to use instead of the current one
+    #[arg(long, global = true, env = "SECUREFOX_PROFILE")]
+    profile: Option<String>,
+
     /// Verbose output
     #[arg(short, long, env = "SECUREFOX_VERBOSE")]
     verbose: bool,
@@ -57,6 +63,9 @@ enum Commands {
         /// KDF algorithm to use (pbkdf2 or argon2)
         #[arg(long, default_value = "pbkdf2")]
         kdf: String,
+        /// Require a keyfile to unlock; generated if the file does not exist
+        #[arg(long)]
+        keyfile: Option<PathBuf>,
     },
 
     /// Unlock the vault
@@ -64,11 +73,38 @@ enum Commands {
         /// Remember in keychain
         #[arg(short, long)]
         remember: bool,
+        /// Keyfile to unlock with (defaults to the configured one)
+        #[arg(long)]
+        keyfile: Option<PathBuf>,
+        /// Unlock with the quick unlock PIN
+        #[arg(long, conflicts_with = "keyfile")]
+        pin: bool,
+        /// Unlock with this SSH key (SHA256 fingerprint) held in ssh-agent
+        #[arg(long, value_name = "FINGERPRINT", conflicts_with_all = ["keyfile", "pin"])]
+        ssh_key: Option<String>,
+        /// Open an Argon2 vault made by an older version (needs about 19 GiB of memory)
+        #[arg(long, conflicts_with_all = ["keyfile", "pin", "ssh_key"])]
+        legacy_argon2: bool,
     },
 
     /// Lock the vault
     Lock,
 
+    /// Change the master password and/or migrate the KDF
+    Passwd {
+        /// New KDF algorithm (pbkdf2 or argon2); keeps the current one if omitted
+        #[arg(long)]
+        kdf: Option<String>,
+
+        /// Re-encrypt the vault under a new data key (drops other keyslots)
+        #[arg(long)]
+        rekey: bool,
+
+        /// Set the automatic upgrade of weak KDF settings on unlock (on or off)
+        #[arg(long, value_name = "on|off")]
+        auto_upgrade: Option<String>,
+    },
+
     /// Add a new item
     Add {
         /// Item name
@@ -89,14 +125,26 @@ enum Commands {
         /// TOTP secret
         #[arg(long)]
         totp: Option<String>,
+
+        /// Tag the item; repeat or separate tags with commas
+        #[arg(long = "tag", value_delimiter = ',')]
+        tags: Vec<String>,
     },
 
     /// List items
     List {
-        /// Filter by type
+        /// Filter by type (login, note, card, identity, ssh-key)
         #[arg(short = 't', long)]
         item_type: Option<String>,
 
+        /// Filter by folder, including its subfolders
+        #[arg(short, long)]
+        folder: Option<String>,
+
+        /// Only list items with this tag; repeat to require several
+        #[arg(long = "tag", value_delimiter = ',')]
+        tags: Vec<String>,
+
         /// Search query
         #[arg(short, long)]
         search: Option<String>,
@@ -118,19 +166,81 @@ enum Commands {
         /// Show TOTP code
         #[arg(short = 't', long)]
         totp: bool,
+
+        /// Show previous passwords
+        #[arg(long)]
+        history: bool,
     },
 
     /// Edit an existing item
     Edit {
         /// Item ID or name
         name: String,
+
+        /// Add a tag instead of prompting for fields; repeat or separate with commas
+        #[arg(long = "tag", value_delimiter = ',')]
+        tags: Vec<String>,
+
+        /// Remove a tag instead of prompting for fields
+        #[arg(long = "untag", value_delimiter = ',')]
+        untags: Vec<String>,
     },
 
-    /// Remove an item
+    /// Show how an item changed over the git history of the vault
+    History {
+        /// Item ID or name
+        name: String,
+
+        /// Show secret values instead of masking them
+        #[arg(long)]
+        reveal: bool,
+
+        /// Put the item back as it was at this commit, leaving other items alone
+        #[arg(long, value_name = "COMMIT")]
+        restore: Option<String>,
+    },
+
+    /// Compare two states of the vault
+    Diff {
+        /// Older state: a commit, a timestamp, a backup or .sf file, or "current"
+        old: String,
+
+        /// Newer state, in the same forms
+        #[arg(default_value = "current")]
+        new: String,
+
+        /// Show secret values instead of masking them
+        #[arg(long)]
+        reveal: bool,
+    },
+
+    /// Roll the whole vault back to an earlier state
+    Restore {
+        /// State to restore: a commit, a timestamp, or a backup or .sf file
+        #[arg(long, value_name = "COMMIT|TIMESTAMP|FILE")]
+        at: String,
+
+        /// Restore without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Check the vault for broken items and folders
+    Doctor {
+        /// Apply the repairs that lose no data, after taking a backup
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Move an item to the trash
     Remove {
         /// Item ID or name
         name: String,
 
+        /// Delete immediately instead of moving to the trash
+        #[arg(long)]
+        permanent: bool,
+
         /// Force removal without confirmation
         #[arg(short, long)]
         force: bool,
@@ -185,12 +295,78 @@ enum Commands {
         format: String,
     },
 
+    /// Manage files attached to an item
+    Attach {
+        #[command(subcommand)]
+        command: AttachCommands,
+    },
+
+    /// Create, rename and remove folders, and move items between them
+    Folder {
+        #[command(subcommand)]
+        command: FolderCommands,
+    },
+
+    /// List, rename, merge and remove item tags
+    Tag {
+        #[command(subcommand)]
+        command: TagCommands,
+    },
+
+    /// Generate, import and export SSH keys
+    SshKey {
+        #[command(subcommand)]
+        command: SshKeyCommands,
+    },
+
+    /// List, restore and purge trashed items
+    Trash {
+        #[command(subcommand)]
+        command: TrashCommands,
+    },
+
+    /// List, verify and restore vault backups
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommands,
+    },
+
+    /// Manage named vaults, e.g. separate work and personal ones
+    Vault {
+        #[command(subcommand)]
+        command: VaultCommands,
+    },
+
     /// Git synchronization commands
     Sync {
         #[command(subcommand)]
         command: Option<SyncCommands>,
     },
 
+    /// Manage the keyslots that can unlock the vault
+    Keyslot {
+        #[command(subcommand)]
+        command: KeyslotCommands,
+    },
+
+    /// Manage the keyfile required to unlock the vault
+    Keyfile {
+        #[command(subcommand)]
+        command: KeyfileCommands,
+    },
+
+    /// Manage PIN quick unlock on this machine
+    Pin {
+        #[command(subcommand)]
+        command: PinCommands,
+    },
+
+    /// Split a recovery key into shares or unlock with them
+    Recovery {
+        #[command(subcommand)]
+        command: RecoveryCommands,
+    },
+
     /// Background service management
     Service {
         #[command(subcommand)]
@@ -198,6 +374,279 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand, Debug)]
+enum AttachCommands {
+    /// Encrypt a file and attach it to an item
+    Add {
+        /// Item ID or name
+        item: String,
+
+        /// File to attach
+        file: PathBuf,
+
+        /// Name to store the file under (defaults to its file name)
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+
+    /// Decrypt an attachment into a file
+    Get {
+        /// Item ID or name
+        item: String,
+
+        /// Attachment ID or file name
+        attachment: String,
+
+        /// Output path (defaults to the attachment's file name)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Overwrite an existing file
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Remove an attachment from an item
+    Rm {
+        /// Item ID or name
+        item: String,
+
+        /// Attachment ID or file name
+        attachment: String,
+
+        /// Force removal without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum FolderCommands {
+    /// Show folders as a tree with their item counts
+    List,
+
+    /// Create a folder; nest it with a full name such as Work/Cloud/AWS
+    Add {
+        /// Folder name
+        name: String,
+    },
+
+    /// Rename a folder along with the folders nested under it
+    Rename {
+        /// Folder ID or full name
+        folder: String,
+
+        /// New full name
+        new_name: String,
+    },
+
+    /// Delete a folder; its items move out of it and subfolders are kept
+    Remove {
+        /// Folder ID or full name
+        folder: String,
+
+        /// Folder to move the items into instead
+        #[arg(long, value_name = "FOLDER")]
+        move_to: Option<String>,
+
+        /// Remove without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Move an item into a folder, or out of its folder
+    Move {
+        /// Item ID or name
+        item: String,
+
+        /// Folder ID or full name (omit to move the item out of its folder)
+        folder: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TagCommands {
+    /// List tags with the number of items having each
+    List,
+
+    /// Rename a tag on every item
+    Rename {
+        /// Current tag
+        tag: String,
+
+        /// New tag
+        new_tag: String,
+    },
+
+    /// Replace several tags with one on every item
+    Merge {
+        /// Tags to merge
+        #[arg(required = true)]
+        tags: Vec<String>,
+
+        /// Tag to merge them into
+        #[arg(long)]
+        into: String,
+    },
+
+    /// Remove a tag from every item
+    Remove {
+        /// Tag to remove
+        tag: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SshKeyCommands {
+    /// Generate a key pair, or import one from an OpenSSH private key file
+    Add {
+        /// Item name
+        name: String,
+
+        /// Private key file to import instead of generating a key
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+
+        /// Type of key to generate (ed25519, rsa or rsa-<bits>)
+        #[arg(
+            short = 't',
+            long = "type",
+            default_value = "ed25519",
+            conflicts_with = "file"
+        )]
+        key_type: String,
+
+        /// Comment of a generated key (defaults to the item name)
+        #[arg(short, long, conflicts_with = "file")]
+        comment: Option<String>,
+
+        /// Tags, comma separated or repeated
+        #[arg(long = "tag", value_delimiter = ',')]
+        tags: Vec<String>,
+    },
+
+    /// Write the private or public key of an SSH key item
+    Export {
+        /// Item ID or name
+        item: String,
+
+        /// Export the public key instead of the private key
+        #[arg(long)]
+        public: bool,
+
+        /// Output path (defaults to standard output)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Encrypt the private key with a passphrase, prompted for
+        #[arg(short, long, conflicts_with = "public")]
+        passphrase: bool,
+
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TrashCommands {
+    /// List trashed items
+    List,
+
+    /// Move an item out of the trash
+    Restore {
+        /// Item ID or name
+        item: String,
+    },
+
+    /// Permanently delete a trashed item, or all of them
+    Purge {
+        /// Item ID or name (defaults to every trashed item)
+        item: Option<String>,
+
+        /// Force deletion without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Show or set how many days trashed items are kept (0 keeps them)
+    Retention {
+        /// Days to keep trashed items
+        days: Option<u32>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum BackupCommands {
+    /// List backups, newest first
+    List,
+
+    /// Back up the vault now
+    Create,
+
+    /// Decrypt backups to check they are intact
+    Verify {
+        /// Backup number, file name or path (defaults to every backup)
+        backup: Option<String>,
+    },
+
+    /// Replace the items and folders of the vault with those of a backup
+    Restore {
+        /// Backup number, file name or path
+        backup: String,
+
+        /// Restore without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Show or set how many backups are kept
+    Policy {
+        /// Number of most recent backups to keep
+        #[arg(long)]
+        keep_last: Option<usize>,
+
+        /// Days to keep the newest backup of each day
+        #[arg(long)]
+        keep_daily: Option<usize>,
+
+        /// Weeks to keep the newest backup of each week
+        #[arg(long)]
+        keep_weekly: Option<usize>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum VaultCommands {
+    /// List registered vaults
+    List,
+
+    /// Register a vault directory under a name
+    Add {
+        /// Name to select the vault by
+        name: String,
+
+        /// Directory holding (or to hold) the vault
+        path: PathBuf,
+
+        /// Git remote URL of the vault
+        #[arg(short, long)]
+        remote: Option<String>,
+    },
+
+    /// Select the vault used when no --profile is given
+    Use {
+        /// Vault name ("default" for ~/.securefox)
+        name: String,
+    },
+
+    /// Unregister a vault, leaving its files in place
+    Remove {
+        /// Vault name
+        name: String,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 enum SyncCommands {
     /// Push changes to remote
@@ -230,6 +679,108 @@ enum SyncCommands {
     Disable,
 }
 
+#[derive(Subcommand, Debug)]
+enum KeyslotCommands {
+    /// List keyslots
+    List,
+
+    /// Add a password or SSH key keyslot
+    Add {
+        /// Label to identify the keyslot
+        #[arg(short, long)]
+        label: Option<String>,
+
+        /// KDF algorithm to use (pbkdf2 or argon2)
+        #[arg(long, default_value = "pbkdf2")]
+        kdf: String,
+
+        /// Unlock with this Ed25519 key (SHA256 fingerprint) held in ssh-agent instead of a password
+        #[arg(long, value_name = "FINGERPRINT")]
+        ssh_key: Option<String>,
+    },
+
+    /// Remove a keyslot
+    Remove {
+        /// Keyslot ID
+        id: String,
+
+        /// Force removal without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum KeyfileCommands {
+    /// Write a new random keyfile in KeePass XML format
+    Generate {
+        /// Keyfile path
+        path: PathBuf,
+    },
+
+    /// Require a new keyfile, replacing the current one if any
+    Rotate {
+        /// Keyfile path; generated if the file does not exist
+        path: PathBuf,
+    },
+
+    /// Stop requiring a keyfile
+    Remove {
+        /// Force removal without confirmation
+        #[arg(short, long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum PinCommands {
+    /// Set the PIN (requires the master password)
+    Set {
+        /// Wrong attempts before the master password is required
+        #[arg(long)]
+        max_attempts: Option<u32>,
+
+        /// Hours before the master password is required again
+        #[arg(long)]
+        valid_hours: Option<u64>,
+    },
+
+    /// Disable PIN unlock
+    Remove,
+
+    /// Show PIN unlock status
+    Status,
+}
+
+#[derive(Subcommand, Debug)]
+enum RecoveryCommands {
+    /// Create a recovery key and split it into Shamir shares
+    Split {
+        /// Number of shares to create
+        #[arg(long, default_value = "5")]
+        shares: u8,
+
+        /// Number of shares needed to recover
+        #[arg(long, default_value = "3")]
+        threshold: u8,
+
+        /// Output format (text or qr)
+        #[arg(short = 'f', long, default_value = "text")]
+        format: String,
+    },
+
+    /// Rebuild the recovery key from shares and unlock the vault
+    Restore {
+        /// Set a new master password
+        #[arg(long)]
+        reset: bool,
+
+        /// Remember in keychain
+        #[arg(short, long)]
+        remember: bool,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 enum ServiceCommands {
     /// Start background service
@@ -295,25 +846,85 @@ enum ServiceCommands {
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    // Setup logging
-    let log_level = if cli.verbose { "debug" } else { "info" };
-    tracing_subscriber::fmt().with_env_filter(log_level).init();
+    // clap skips the conflict when --profile follows the subcommand
+    if cli.vault.is_some() && cli.profile.is_some() {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "the argument '--vault <VAULT>' cannot be used with '--profile <PROFILE>'",
+            )
+            .exit();
+    }
 
-    // Get vault path
-    let vault_path = cli
-        .vault
-        .or_else(|| dirs::home_dir().map(|h| h.join(".securefox")));
+    // Setup logging; output is redacted as it also feeds service.log
+    let log_level = if cli.verbose { "debug" } else { "info" };
+    tracing_subscriber::fmt()
+        .with_env_filter(log_level)
+        .with_writer(RedactingWriter::new(std::io::stdout))
+        .init();
+
+    if let Commands::Version = cli.command {
+        print_version_info();
+        return Ok(());
+    }
 
     // Execute command
-    let result = match cli.command {
-        Commands::Version => {
-            print_version_info();
-            return Ok(());
+    let result = run(cli.command, cli.profile, cli.vault).await;
+
+    match result {
+        Ok(_) => {
+            println!("{}", "✓ Success".green().bold());
+            Ok(())
         }
+        Err(e) => {
+            eprintln!("{} {}", "✗ Error:".red().bold(), e);
+            std::process::exit(1);
+        }
+    }
+}
 
-        Commands::Init { remote, kdf } => commands::init::execute(vault_path, remote, kdf).await,
-        Commands::Lock => commands::lock::execute(vault_path).await,
-        Commands::Unlock { remember } => commands::unlock::execute(vault_path, remember).await,
+/// Run a command, resolving the vault path only for commands that use it
+///
+/// Commands that never open a vault keep working with a broken config file.
+async fn run(command: Commands, profile: Option<String>, vault: Option<PathBuf>) -> Result<()> {
+    let vault_path = || -> Result<Option<PathBuf>> {
+        match &vault {
+            Some(path) => Ok(Some(path.clone())),
+            None => Ok(Some(ConfigManager::new()?.vault_dir(profile.as_deref())?)),
+        }
+    };
+
+    match command {
+        Commands::Version => unreachable!("handled before any vault is resolved"),
+
+        Commands::Init {
+            remote,
+            kdf,
+            keyfile,
+        } => commands::init::execute(vault_path()?, remote, kdf, keyfile).await,
+        Commands::Lock => commands::lock::execute(vault_path()?).await,
+        Commands::Passwd {
+            kdf,
+            rekey,
+            auto_upgrade,
+        } => commands::passwd::execute(vault_path()?, kdf, rekey, auto_upgrade).await,
+        Commands::Unlock {
+            remember,
+            keyfile,
+            pin,
+            ssh_key,
+            legacy_argon2,
+        } => {
+            commands::unlock::execute(
+                vault_path()?,
+                remember,
+                keyfile,
+                pin,
+                ssh_key,
+                legacy_argon2,
+            )
+            .await
+        }
 
         Commands::Add {
             name,
@@ -321,19 +932,54 @@ async fn main() -> Result<()> {
             username,
             generate,
             totp,
-        } => commands::add::execute(vault_path, name, item_type, username, generate, totp).await,
+            tags,
+        } => {
+            commands::add::execute(
+                vault_path()?,
+                name,
+                item_type,
+                username,
+                generate,
+                totp,
+                tags,
+            )
+            .await
+        }
         Commands::List {
             item_type,
+            folder,
+            tags,
             search,
             detailed,
-        } => commands::list::execute(vault_path, item_type, search, detailed).await,
-        Commands::Show { name, copy, totp } => {
-            commands::show::execute(vault_path, name, copy, totp).await
+        } => {
+            commands::list::execute(vault_path()?, item_type, folder, tags, search, detailed).await
         }
-        Commands::Edit { name } => commands::edit::execute(vault_path, name).await,
-        Commands::Remove { name, force } => {
-            commands::remove::execute(vault_path, name, force).await
+        Commands::Show {
+            name,
+            copy,
+            totp,
+            history,
+        } => commands::show::execute(vault_path()?, name, copy, totp, history).await,
+        Commands::Edit { name, tags, untags } => {
+            commands::edit::execute(vault_path()?, name, tags, untags).await
+        }
+        Commands::History {
+            name,
+            reveal,
+            restore,
+        } => commands::history::execute(vault_path()?, name, reveal, restore).await,
+        Commands::Diff { old, new, reveal } => {
+            commands::diff::execute(vault_path()?, old, new, reveal).await
         }
+        Commands::Restore { at, force } => {
+            commands::restore::execute(vault_path()?, at, force).await
+        }
+        Commands::Doctor { fix } => commands::doctor::execute(vault_path()?, fix).await,
+        Commands::Remove {
+            name,
+            permanent,
+            force,
+        } => commands::remove::execute(vault_path()?, name, permanent, force).await,
 
         Commands::Generate {
             length,
@@ -341,31 +987,180 @@ async fn main() -> Result<()> {
             symbols,
             copy,
         } => commands::generate::execute(length, numbers, symbols, copy).await,
-        Commands::Totp { name, copy } => commands::totp::execute(vault_path, name, copy).await,
+        Commands::Totp { name, copy } => commands::totp::execute(vault_path()?, name, copy).await,
 
         Commands::Import { file, format } => {
-            commands::import::execute(vault_path, file, format).await
+            commands::import::execute(vault_path()?, file, format).await
         }
         Commands::Export { file, format } => {
-            commands::export::execute(vault_path, file, format).await
+            commands::export::execute(vault_path()?, file, format).await
         }
 
+        Commands::Attach { command } => match command {
+            AttachCommands::Add { item, file, name } => {
+                commands::attach_add::execute(vault_path()?, item, file, name).await
+            }
+            AttachCommands::Get {
+                item,
+                attachment,
+                output,
+                force,
+            } => {
+                commands::attach_get::execute(vault_path()?, item, attachment, output, force).await
+            }
+            AttachCommands::Rm {
+                item,
+                attachment,
+                force,
+            } => commands::attach_rm::execute(vault_path()?, item, attachment, force).await,
+        },
+        Commands::Folder { command } => match command {
+            FolderCommands::List => commands::folder_list::execute(vault_path()?).await,
+            FolderCommands::Add { name } => {
+                commands::folder_add::execute(vault_path()?, name).await
+            }
+            FolderCommands::Rename { folder, new_name } => {
+                commands::folder_rename::execute(vault_path()?, folder, new_name).await
+            }
+            FolderCommands::Remove {
+                folder,
+                move_to,
+                force,
+            } => commands::folder_remove::execute(vault_path()?, folder, move_to, force).await,
+            FolderCommands::Move { item, folder } => {
+                commands::folder_move::execute(vault_path()?, item, folder).await
+            }
+        },
+        Commands::Tag { command } => match command {
+            TagCommands::List => commands::tag_list::execute(vault_path()?).await,
+            TagCommands::Rename { tag, new_tag } => {
+                commands::tag_rename::execute(vault_path()?, tag, new_tag).await
+            }
+            TagCommands::Merge { tags, into } => {
+                commands::tag_merge::execute(vault_path()?, tags, into).await
+            }
+            TagCommands::Remove { tag } => commands::tag_remove::execute(vault_path()?, tag).await,
+        },
+        Commands::SshKey { command } => match command {
+            SshKeyCommands::Add {
+                name,
+                file,
+                key_type,
+                comment,
+                tags,
+            } => {
+                commands::ssh_key_add::execute(vault_path()?, name, file, key_type, comment, tags)
+                    .await
+            }
+            SshKeyCommands::Export {
+                item,
+                public,
+                output,
+                passphrase,
+                force,
+            } => {
+                commands::ssh_key_export::execute(
+                    vault_path()?,
+                    item,
+                    public,
+                    output,
+                    passphrase,
+                    force,
+                )
+                .await
+            }
+        },
+        Commands::Trash { command } => match command {
+            TrashCommands::List => commands::trash_list::execute(vault_path()?).await,
+            TrashCommands::Restore { item } => {
+                commands::trash_restore::execute(vault_path()?, item).await
+            }
+            TrashCommands::Purge { item, force } => {
+                commands::trash_purge::execute(vault_path()?, item, force).await
+            }
+            TrashCommands::Retention { days } => commands::trash_retention::execute(days).await,
+        },
+        Commands::Backup { command } => match command {
+            BackupCommands::List => commands::backup_list::execute(vault_path()?).await,
+            BackupCommands::Create => commands::backup_create::execute(vault_path()?).await,
+            BackupCommands::Verify { backup } => {
+                commands::backup_verify::execute(vault_path()?, backup).await
+            }
+            BackupCommands::Restore { backup, force } => {
+                commands::backup_restore::execute(vault_path()?, backup, force).await
+            }
+            BackupCommands::Policy {
+                keep_last,
+                keep_daily,
+                keep_weekly,
+            } => commands::backup_policy::execute(keep_last, keep_daily, keep_weekly).await,
+        },
+        Commands::Vault { command } => match command {
+            VaultCommands::List => commands::vault_list::execute().await,
+            VaultCommands::Add { name, path, remote } => {
+                commands::vault_add::execute(name, path, remote).await
+            }
+            VaultCommands::Use { name } => commands::vault_use::execute(name).await,
+            VaultCommands::Remove { name } => commands::vault_remove::execute(name).await,
+        },
         Commands::Sync { command } => match command {
             None => {
                 // Default: sync both ways (pull then push)
-                commands::sync::execute(vault_path.clone(), true, false).await?;
-                commands::sync::execute(vault_path, false, true).await
+                commands::sync::execute(vault_path()?, true, false).await?;
+                commands::sync::execute(vault_path()?, false, true).await
             }
-            Some(SyncCommands::Push) => commands::sync::execute(vault_path, false, true).await,
-            Some(SyncCommands::Pull) => commands::sync::execute(vault_path, true, false).await,
+            Some(SyncCommands::Push) => commands::sync::execute(vault_path()?, false, true).await,
+            Some(SyncCommands::Pull) => commands::sync::execute(vault_path()?, true, false).await,
             Some(SyncCommands::Config { url }) => {
-                commands::sync_config::execute(vault_path, url).await
+                commands::sync_config::execute(vault_path()?, url).await
             }
-            Some(SyncCommands::Status) => commands::sync_status::execute(vault_path).await,
+            Some(SyncCommands::Status) => commands::sync_status::execute(vault_path()?).await,
             Some(SyncCommands::Enable { mode, interval }) => {
-                commands::sync_enable::execute(vault_path, mode, interval).await
+                commands::sync_enable::execute(vault_path()?, mode, interval).await
+            }
+            Some(SyncCommands::Disable) => commands::sync_disable::execute(vault_path()?).await,
+        },
+
+        Commands::Keyslot { command } => match command {
+            KeyslotCommands::List => commands::keyslot_list::execute(vault_path()?).await,
+            KeyslotCommands::Add {
+                label,
+                kdf,
+                ssh_key,
+            } => commands::keyslot_add::execute(vault_path()?, label, kdf, ssh_key).await,
+            KeyslotCommands::Remove { id, force } => {
+                commands::keyslot_remove::execute(vault_path()?, id, force).await
+            }
+        },
+
+        Commands::Keyfile { command } => match command {
+            KeyfileCommands::Generate { path } => commands::keyfile_generate::execute(path).await,
+            KeyfileCommands::Rotate { path } => {
+                commands::keyfile_rotate::execute(vault_path()?, path).await
+            }
+            KeyfileCommands::Remove { force } => {
+                commands::keyfile_remove::execute(vault_path()?, force).await
+            }
+        },
+
+        Commands::Pin { command } => match command {
+            PinCommands::Set {
+                max_attempts,
+                valid_hours,
+            } => commands::pin_set::execute(vault_path()?, max_attempts, valid_hours).await,
+            PinCommands::Remove => commands::pin_remove::execute(vault_path()?).await,
+            PinCommands::Status => commands::pin_status::execute(vault_path()?).await,
+        },
+
+        Commands::Recovery { command } => match command {
+            RecoveryCommands::Split {
+                shares,
+                threshold,
+                format,
+            } => commands::recovery_split::execute(vault_path()?, shares, threshold, format).await,
+            RecoveryCommands::Restore { reset, remember } => {
+                commands::recovery_restore::execute(vault_path()?, reset, remember).await
             }
-            Some(SyncCommands::Disable) => commands::sync_disable::execute(vault_path).await,
         },
 
         Commands::Service { command } => match command {
@@ -373,13 +1168,13 @@ async fn main() -> Result<()> {
                 port,
                 host,
                 timeout,
-            } => commands::service_start::execute(vault_path, host, port, timeout).await,
+            } => commands::service_start::execute(vault_path()?, host, port, timeout).await,
             ServiceCommands::Stop => commands::service_stop::execute().await,
             ServiceCommands::Restart {
                 port,
                 host,
                 timeout,
-            } => commands::service_restart::execute(vault_path, host, port, timeout).await,
+            } => commands::service_restart::execute(vault_path()?, host, port, timeout).await,
             ServiceCommands::Status => commands::service_status::execute().await,
             ServiceCommands::Install => commands::service_enable::execute().await,
             ServiceCommands::Uninstall => commands::service_disable::execute().await,
@@ -387,18 +1182,7 @@ async fn main() -> Result<()> {
                 port,
                 host,
                 timeout,
-            } => commands::service_run::execute(vault_path, host, port, timeout).await,
+            } => commands::service_run::execute(vault_path()?, host, port, timeout).await,
         },
-    };
-
-    match result {
-        Ok(_) => {
-            println!("{}", "✓ Success".green().bold());
-            Ok(())
-        }
-        Err(e) => {
-            eprintln!("{} {}", "✗ Error:".red().bold(), e);
-            std::process::exit(1);
-        }
     }
 }
@@ -33,7 +33,7 @@ impl SyncDaemon {
         let config_file = config_manager.load()?;
 
         let mut config = self.config.write().await;
-        *config = config_file.sync_config;
+        *config = config_file.sync_config_for(&self.vault_path).cloned();
 
         Ok(())
     }
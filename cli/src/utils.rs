@@ -1,39 +1,257 @@
 use dialoguer::Password;
-use securefox_core::storage::VaultStorage;
-use std::path::Path;
+use securefox_core::{
+    backup::Backup,
+    config::ConfigManager,
+    crypto::{EncryptionKey, KdfParams},
+    keyfile::Keyfile,
+    keyslot::Credentials,
+    models::{
+        Attachment, BackupPolicy, Folder, Item, PinPolicy, Vault, DEFAULT_TRASH_RETENTION_DAYS,
+    },
+    snapshot::VaultSnapshot,
+    ssh_agent::SshAgent,
+    storage::VaultStorage,
+};
+use std::path::{Path, PathBuf};
 
-/// Get password from user or keychain
+/// Prompt the user for the master password
 pub fn get_password(prompt: &str) -> anyhow::Result<String> {
-    // Try keychain first
+    Ok(Password::new().with_prompt(prompt).interact()?)
+}
+
+/// Directory holding the vault file of `storage`
+pub fn vault_dir(storage: &VaultStorage) -> &Path {
+    storage.path().parent().unwrap_or(Path::new("."))
+}
+
+/// Data key of the vault remembered by `securefox unlock --remember`, if any
+pub fn remembered_key(storage: &VaultStorage) -> Option<EncryptionKey> {
     #[cfg(feature = "keychain")]
     {
         use securefox_core::keychain::Keychain;
-        let keychain = Keychain::new();
+        let keychain = Keychain::for_vault(vault_dir(storage));
         if keychain.has_master_key() {
-            if let Ok(key) = keychain.get_master_key() {
-                // For simplicity, convert key to password
-                // In real implementation, would store password hash
-                return Ok(String::from_utf8_lossy(&key).to_string());
+            if let Ok(bytes) = keychain.get_master_key() {
+                return EncryptionKey::from_bytes(&bytes).ok();
             }
         }
     }
 
-    // Prompt for password
-    Ok(Password::new().with_prompt(prompt).interact()?)
+    #[cfg(not(feature = "keychain"))]
+    let _ = storage;
+
+    None
 }
 
-/// Load vault with password prompt
-pub fn load_vault(path: &Path) -> anyhow::Result<(securefox_core::models::Vault, String)> {
-    let storage = VaultStorage::with_path(path);
+/// Load vault with the remembered key or a password prompt
+///
+/// Returns the vault data key so changes can be saved without re-deriving it.
+/// Save through the same `storage` so concurrent changes are detected.
+/// Items that outlived the trash retention period are purged first.
+pub fn load_vault(storage: &VaultStorage) -> anyhow::Result<(Vault, EncryptionKey)> {
+    let (mut vault, key) = unlock_vault(storage)?;
 
+    match storage.purge_expired_trash(&mut vault, &key, trash_retention_days()) {
+        Ok(purged) if !purged.is_empty() => {
+            println!("Purged {} expired items from the trash", purged.len())
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Could not purge the trash: {}", e),
+    }
+
+    Ok((vault, key))
+}
+
+fn unlock_vault(storage: &VaultStorage) -> anyhow::Result<(Vault, EncryptionKey)> {
     if !storage.exists() {
         return Err(anyhow::anyhow!(
             "Vault not found. Run 'securefox init' to create one."
         ));
     }
 
+    if let Some(key) = remembered_key(storage) {
+        match storage.load_with_key(&key) {
+            Ok(vault) => return Ok((vault, key)),
+            Err(e) => tracing::debug!("Remembered key rejected: {}", e),
+        }
+    }
+
+    // SSH key keyslots open without a prompt when the agent holds the key
+    if std::env::var_os(securefox_core::ssh_agent::AUTH_SOCK_ENV).is_some() {
+        match SshAgent::connect_env()
+            .and_then(|mut agent| storage.unlock_with_ssh_agent(&mut agent, None))
+        {
+            Ok(unlocked) => return Ok(unlocked),
+            Err(e) => tracing::debug!("ssh-agent unlock skipped: {}", e),
+        }
+    }
+
+    let pin_armed = storage
+        .pin_store()
+        .status()
+        .ok()
+        .flatten()
+        .is_some_and(|status| status.armed);
+    if pin_armed {
+        let pin = get_password("Enter PIN")?;
+        match storage.unlock_with_pin(&pin, &pin_policy()) {
+            Ok(unlocked) => return Ok(unlocked),
+            Err(e) => println!("{}", e),
+        }
+    }
+
+    let keyfile = load_keyfile(storage, None)?;
     let password = get_password("Enter master password")?;
-    let vault = storage.load(&password)?;
+    let credentials = Credentials::new(&password, keyfile.as_ref());
+    let (vault, key) = storage.unlock(credentials)?;
+
+    upgrade_weak_kdf(storage, credentials);
+    rearm_pin(storage, &key);
+
+    Ok((vault, key))
+}
+
+/// PIN unlock limits from the config file
+pub fn pin_policy() -> PinPolicy {
+    ConfigManager::new()
+        .and_then(|m| m.load())
+        .ok()
+        .and_then(|c| c.pin_policy)
+        .unwrap_or_default()
+}
+
+/// Days trashed items are kept, from the config file
+pub fn trash_retention_days() -> u32 {
+    ConfigManager::new()
+        .and_then(|m| m.load())
+        .map(|c| c.trash_retention_days())
+        .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS)
+}
+
+/// Backup retention from the config file
+pub fn backup_policy() -> BackupPolicy {
+    ConfigManager::new()
+        .and_then(|m| m.load())
+        .ok()
+        .and_then(|c| c.backup_policy)
+        .unwrap_or_default()
+}
 
-    Ok((vault, password))
+/// Re-arm PIN unlock after a full unlock, if it expired
+pub fn rearm_pin(storage: &VaultStorage, key: &EncryptionKey) {
+    if let Err(e) = storage.rearm_pin(key, &pin_policy()) {
+        tracing::warn!("Could not re-arm PIN unlock: {}", e);
+    }
+}
+
+/// Load the keyfile at `path`, or the one configured for the vault
+///
+/// Returns `None` when no keyfile is given or configured.
+pub fn load_keyfile(
+    storage: &VaultStorage,
+    path: Option<PathBuf>,
+) -> anyhow::Result<Option<Keyfile>> {
+    let path = match path {
+        Some(path) => path,
+        None => match ConfigManager::new()?
+            .load()?
+            .keyfile_for(vault_dir(storage))
+        {
+            Some(path) => path.to_path_buf(),
+            None => return Ok(None),
+        },
+    };
+
+    Ok(Some(Keyfile::load(&path)?))
+}
+
+/// Decrypt a snapshot of the vault, asking for its password if the current
+/// key does not open it
+pub fn open_snapshot(
+    storage: &VaultStorage,
+    key: &EncryptionKey,
+    snapshot: &VaultSnapshot,
+) -> anyhow::Result<Vault> {
+    match snapshot.open(storage, key) {
+        Err(securefox_core::Error::KeyMismatch | securefox_core::Error::Decryption(_)) => {
+            let password = get_password(&format!("Enter master password of {}", snapshot))?;
+            Ok(snapshot.open_with_password(storage, password.as_str())?)
+        }
+        result => Ok(result?),
+    }
+}
+
+/// Find a backup by its number in `securefox backup list`, file name or path
+pub fn find_backup(storage: &VaultStorage, name: &str) -> anyhow::Result<Backup> {
+    let backups = storage.backups().list()?;
+    if let Ok(index) = name.parse::<usize>() {
+        if let Some(backup) = index.checked_sub(1).and_then(|i| backups.get(i)) {
+            return Ok(backup.clone());
+        }
+    }
+
+    backups
+        .into_iter()
+        .find(|b| b.file_name() == name || b.path == std::path::Path::new(name))
+        .ok_or_else(|| anyhow::anyhow!("Backup not found: {}", name))
+}
+
+/// Find an item outside the trash by name or ID
+pub fn find_item_mut<'a>(vault: &'a mut Vault, name: &str) -> anyhow::Result<&'a mut Item> {
+    vault
+        .items
+        .iter_mut()
+        .find(|i| !i.is_deleted() && (i.name == name || i.id == name))
+        .ok_or_else(|| anyhow::anyhow!("Item not found: {}", name))
+}
+
+/// Find a folder by full name or ID
+pub fn find_folder<'a>(vault: &'a Vault, name: &str) -> anyhow::Result<&'a Folder> {
+    vault
+        .find_folder(name)
+        .ok_or_else(|| anyhow::anyhow!("Folder not found: {}", name))
+}
+
+/// Find a trashed item by name or ID
+pub fn find_trashed_item<'a>(vault: &'a Vault, name: &str) -> anyhow::Result<&'a Item> {
+    vault
+        .trashed_items()
+        .find(|i| i.name == name || i.id == name)
+        .ok_or_else(|| anyhow::anyhow!("Item not found in the trash: {}", name))
+}
+
+/// Find an attachment of `item` by file name or ID
+pub fn find_attachment<'a>(item: &'a Item, name: &str) -> anyhow::Result<&'a Attachment> {
+    item.attachments
+        .iter()
+        .find(|a| a.file_name == name || a.id == name)
+        .ok_or_else(|| anyhow::anyhow!("Attachment not found on '{}': {}", item.name, name))
+}
+
+/// Apply the automatic KDF upgrade policy after a successful unlock
+fn upgrade_weak_kdf(storage: &VaultStorage, credentials: Credentials<'_>) {
+    let enabled = ConfigManager::new()
+        .and_then(|m| m.load())
+        .map(|c| c.auto_upgrade_kdf)
+        .unwrap_or(false);
+    if !enabled {
+        return;
+    }
+
+    match storage.upgrade_weak_kdf(credentials) {
+        Ok(true) => println!("Upgraded vault key derivation to Argon2id"),
+        Ok(false) => {}
+        Err(e) => tracing::warn!("KDF upgrade failed: {}", e),
+    }
+}
+
+/// Parse a KDF algorithm name into fresh KDF parameters
+pub fn parse_kdf(kdf: &str) -> anyhow::Result<KdfParams> {
+    match kdf.to_lowercase().as_str() {
+        "argon2" | "argon2id" => Ok(KdfParams::argon2()),
+        "pbkdf2" => Ok(KdfParams::pbkdf2()),
+        _ => Err(anyhow::anyhow!(
+            "Invalid KDF algorithm. Use 'pbkdf2' or 'argon2'"
+        )),
+    }
 }
@@ -0,0 +1,234 @@
+//! Encrypted file attachments
+//!
+//! Attachment contents are kept out of `vault.sf`, each in its own file
+//! under `attachments/` next to the vault, so adding a file does not bloat
+//! every vault revision in git. Every blob is encrypted under a random key
+//! of its own, stored with the attachment metadata inside the encrypted
+//! vault. Blobs therefore stay readable when the vault is re-keyed.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+use crate::crypto::{
+    decrypt_with_aad, encrypt_with_aad, generate_key, EncryptedData, EncryptionKey,
+};
+use crate::errors::{Error, Result};
+use crate::models::Attachment;
+
+/// Directory holding the blobs, next to the vault file
+pub const ATTACHMENTS_DIR: &str = "attachments";
+
+/// Extension of blob files
+const BLOB_EXTENSION: &str = "sfa";
+
+/// Largest attachment accepted (100MB)
+pub const MAX_ATTACHMENT_SIZE: usize = 100 * 1024 * 1024;
+
+/// Blob files of one vault
+pub struct AttachmentStore {
+    dir: PathBuf,
+}
+
+impl AttachmentStore {
+    pub fn with_dir<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Encrypt `contents` into a new blob and describe it
+    pub fn add(&self, file_name: &str, contents: &[u8]) -> Result<Attachment> {
+        if contents.len() > MAX_ATTACHMENT_SIZE {
+            return Err(Error::Attachment(format!(
+                "{} is larger than {} MB",
+                file_name,
+                MAX_ATTACHMENT_SIZE / (1024 * 1024)
+            )));
+        }
+
+        let key = generate_key();
+        let attachment = Attachment {
+            id: Uuid::new_v4().to_string(),
+            file_name: file_name.to_string(),
+            size: contents.len() as u64,
+            key: Some(BASE64.encode(key.as_bytes()).into()),
+        };
+
+        let blob = encrypt_with_aad(contents, &key, &associated_data(&attachment.id))?;
+        self.write(&attachment.id, &serde_json::to_vec(&blob)?)?;
+        Ok(attachment)
+    }
+
+    /// Decrypt the contents of `attachment`
+    pub fn read(&self, attachment: &Attachment) -> Result<Zeroizing<Vec<u8>>> {
+        let key = attachment_key(attachment)?;
+        let path = self.path(&attachment.id)?;
+        if !path.exists() {
+            return Err(Error::Attachment(format!(
+                "the contents of {} are missing",
+                attachment.file_name
+            )));
+        }
+
+        let blob: EncryptedData = serde_json::from_slice(&fs::read(path)?)?;
+        Ok(Zeroizing::new(decrypt_with_aad(
+            &blob,
+            &key,
+            &associated_data(&attachment.id),
+        )?))
+    }
+
+    /// Whether the blob of `attachment` is present
+    pub fn contains(&self, attachment: &Attachment) -> bool {
+        attachment.key.is_some() && self.path(&attachment.id).is_ok_and(|p| p.exists())
+    }
+
+    /// Delete the blob of `attachment`, if present
+    ///
+    /// Imported attachments have no key and no blob of their own; whatever
+    /// file their id might name belongs to someone else and is left alone.
+    pub fn remove(&self, attachment: &Attachment) -> Result<()> {
+        if attachment.key.is_none() {
+            return Ok(());
+        }
+        let path = self.path(&attachment.id)?;
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Delete the blobs of all `attachments`
+    pub fn remove_all(&self, attachments: &[Attachment]) -> Result<()> {
+        attachments.iter().try_for_each(|a| self.remove(a))
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf> {
+        // Ids may come from an imported or hand-edited vault; reject rather
+        // than rewrite odd ones, so two ids can never name the same blob
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(Error::Attachment(format!("invalid attachment id '{}'", id)));
+        }
+        Ok(self.dir.join(format!("{}.{}", id, BLOB_EXTENSION)))
+    }
+
+    /// Write a blob atomically
+    fn write(&self, id: &str, contents: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(id)?;
+        let tmp_path = path.with_extension(format!("{}.tmp", BLOB_EXTENSION));
+
+        let mut tmp = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp_path)?;
+        tmp.write_all(contents)?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+}
+
+fn attachment_key(attachment: &Attachment) -> Result<EncryptionKey> {
+    let encoded = attachment.key.as_ref().ok_or_else(|| {
+        Error::Attachment(format!(
+            "{} was imported without its contents",
+            attachment.file_name
+        ))
+    })?;
+    let bytes = Zeroizing::new(
+        BASE64
+            .decode(encoded.expose_secret())
+            .map_err(|_| Error::Attachment("attachment key is not valid base64".to_string()))?,
+    );
+    EncryptionKey::from_bytes(&bytes)
+}
+
+fn associated_data(id: &str) -> Vec<u8> {
+    format!("securefox attachment|{}", id).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_attachment_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let store = AttachmentStore::with_dir(temp_dir.path().join(ATTACHMENTS_DIR));
+
+        let attachment = store.add("codes.txt", b"recovery codes").unwrap();
+        assert_eq!(attachment.size, 14);
+        assert!(store.contains(&attachment));
+        assert_eq!(&store.read(&attachment).unwrap()[..], b"recovery codes");
+
+        // Blobs are encrypted
+        let blob = fs::read(store.path(&attachment.id).unwrap()).unwrap();
+        assert!(!String::from_utf8_lossy(&blob).contains("recovery codes"));
+
+        store.remove(&attachment).unwrap();
+        assert!(!store.contains(&attachment));
+        assert!(matches!(store.read(&attachment), Err(Error::Attachment(_))));
+    }
+
+    #[test]
+    fn test_blob_is_bound_to_its_attachment() {
+        let temp_dir = tempdir().unwrap();
+        let store = AttachmentStore::with_dir(temp_dir.path());
+
+        let first = store.add("a.txt", b"first").unwrap();
+        let second = store.add("b.txt", b"second").unwrap();
+        fs::copy(
+            store.path(&first.id).unwrap(),
+            store.path(&second.id).unwrap(),
+        )
+        .unwrap();
+
+        let swapped = Attachment {
+            key: first.key.clone(),
+            ..second
+        };
+        assert!(store.read(&swapped).is_err());
+    }
+
+    #[test]
+    fn test_imported_attachment_never_removes_a_blob() {
+        let temp_dir = tempdir().unwrap();
+        let store = AttachmentStore::with_dir(temp_dir.path());
+        let real = store.add("a.txt", b"contents").unwrap();
+
+        // Ids that would map onto the real blob once stripped of odd characters
+        for id in [
+            format!("{}/", real.id),
+            format!("../{}", real.id),
+            real.id.clone(),
+        ] {
+            let imported = Attachment {
+                id,
+                file_name: "imported.txt".to_string(),
+                size: 8,
+                key: None,
+            };
+            assert!(!store.contains(&imported));
+            store.remove(&imported).unwrap();
+            store.remove_all(&[imported]).unwrap();
+        }
+        assert!(store.contains(&real));
+
+        let odd = Attachment {
+            id: format!("{}.", real.id),
+            ..real.clone()
+        };
+        assert!(!store.contains(&odd));
+        assert!(store.remove(&odd).is_err());
+        assert!(store.read(&odd).is_err());
+        assert!(store.contains(&real));
+    }
+}
@@ -0,0 +1,302 @@
+//! Managed backups of the vault file
+//!
+//! A copy of `vault.sf` is taken before every operation that rewrites or
+//! replaces the vault, and old copies are pruned by a [`BackupPolicy`].
+//! Backups are named after their creation time and the operation that
+//! triggered them, e.g. `vault.sf.20240501T120000123Z.import.backup`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Datelike, NaiveDateTime, SubsecRound, Utc};
+
+use crate::crypto::EncryptionKey;
+use crate::errors::Result;
+use crate::models::BackupPolicy;
+use crate::storage::{EncryptedVault, VAULT_FILE_NAME};
+
+/// Directory holding the backups, next to the vault file
+pub const BACKUPS_DIR: &str = "backups";
+
+const BACKUP_EXTENSION: &str = "backup";
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
+
+/// A backup of the vault file
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub created: DateTime<Utc>,
+    /// Operation the backup was taken before, e.g. `import`
+    pub reason: Option<String>,
+    pub size: u64,
+}
+
+impl Backup {
+    /// File name of the backup
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// Result of decrypting a backup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackupStatus {
+    /// The backup decrypts and holds this many items
+    Valid { items: usize },
+    /// The backup is sealed under a data key the vault no longer uses, or
+    /// in the legacy password-only format, and needs its own password
+    OtherKey,
+    /// The backup cannot be read or fails authentication
+    Corrupt(String),
+}
+
+/// Backups of one vault
+pub struct BackupStore {
+    dir: PathBuf,
+}
+
+impl BackupStore {
+    pub fn with_dir<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Copy `vault_file` into a new backup taken before `reason`
+    pub fn create(&self, vault_file: &Path, reason: Option<&str>) -> Result<Backup> {
+        fs::create_dir_all(&self.dir)?;
+
+        let reason = reason.map(|r| {
+            r.chars()
+                .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+                .collect::<String>()
+        });
+        // Keep creation times distinct so backups sort in the order taken
+        let mut created = Utc::now().trunc_subsecs(3);
+        if let Some(latest) = self.list()?.first() {
+            if created <= latest.created {
+                created = latest.created + chrono::Duration::milliseconds(1);
+            }
+        }
+        let path = self.dir.join(backup_name(created, reason.as_deref()));
+
+        let size = fs::copy(vault_file, &path)?;
+        Ok(Backup {
+            path,
+            created,
+            reason,
+            size,
+        })
+    }
+
+    /// Backups, newest first
+    pub fn list(&self) -> Result<Vec<Backup>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut backups = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if let Some((created, reason)) = parse_backup_name(&name) {
+                backups.push(Backup {
+                    path: entry.path(),
+                    created,
+                    reason,
+                    size: entry.metadata()?.len(),
+                });
+            }
+        }
+
+        backups.sort_by_key(|b| std::cmp::Reverse(b.created));
+        Ok(backups)
+    }
+
+    /// Delete the backups `policy` does not keep, returning them
+    pub fn prune(&self, policy: &BackupPolicy) -> Result<Vec<Backup>> {
+        let backups = self.list()?;
+        let keep = retained(&backups, policy);
+
+        let mut removed = Vec::new();
+        for (backup, keep) in backups.into_iter().zip(keep) {
+            if !keep {
+                fs::remove_file(&backup.path)?;
+                removed.push(backup);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Decrypt `backup` with the vault data key to check it is intact
+    pub fn verify(&self, backup: &Backup, key: &EncryptionKey) -> BackupStatus {
+        let container = match fs::read(&backup.path)
+            .map_err(Into::into)
+            .and_then(|contents| EncryptedVault::from_slice(&contents))
+        {
+            Ok(container) => container,
+            Err(e) => return BackupStatus::Corrupt(e.to_string()),
+        };
+
+        if container.is_legacy() || !container.matches_key(key) {
+            return BackupStatus::OtherKey;
+        }
+        match container.open(key) {
+            Ok(vault) => BackupStatus::Valid {
+                items: vault.items.len(),
+            },
+            Err(e) => BackupStatus::Corrupt(e.to_string()),
+        }
+    }
+}
+
+fn backup_name(created: DateTime<Utc>, reason: Option<&str>) -> String {
+    let stamp = created.format(TIMESTAMP_FORMAT);
+    match reason.filter(|r| !r.is_empty()) {
+        Some(reason) => format!("{VAULT_FILE_NAME}.{stamp}.{reason}.{BACKUP_EXTENSION}"),
+        None => format!("{VAULT_FILE_NAME}.{stamp}.{BACKUP_EXTENSION}"),
+    }
+}
+
+/// Creation time and reason of a backup file name
+///
+/// Backups written by older versions are named `vault.sf.<unix seconds>.backup`.
+fn parse_backup_name(name: &str) -> Option<(DateTime<Utc>, Option<String>)> {
+    let middle = name
+        .strip_prefix(VAULT_FILE_NAME)?
+        .strip_prefix('.')?
+        .strip_suffix(BACKUP_EXTENSION)?
+        .strip_suffix('.')?;
+    let (stamp, reason) = match middle.split_once('.') {
+        Some((stamp, reason)) => (stamp, Some(reason.to_string())),
+        None => (middle, None),
+    };
+
+    let created = match stamp.parse::<i64>() {
+        Ok(seconds) => DateTime::from_timestamp(seconds, 0)?,
+        Err(_) => NaiveDateTime::parse_from_str(stamp, TIMESTAMP_FORMAT)
+            .ok()?
+            .and_utc(),
+    };
+    Some((created, reason))
+}
+
+/// Which of `backups` (newest first) the policy keeps
+///
+/// The newest `keep_last` are kept, along with the newest backup of each of
+/// the last `keep_daily` days and `keep_weekly` weeks that have one.
+fn retained(backups: &[Backup], policy: &BackupPolicy) -> Vec<bool> {
+    let mut keep: Vec<bool> = (0..backups.len()).map(|i| i < policy.keep_last).collect();
+
+    let mut mark_newest_per = |period: &dyn Fn(&DateTime<Utc>) -> (i32, u32), count: usize| {
+        let mut seen = Vec::new();
+        for (i, backup) in backups.iter().enumerate() {
+            let key = period(&backup.created);
+            if seen.len() >= count {
+                break;
+            }
+            if !seen.contains(&key) {
+                seen.push(key);
+                keep[i] = true;
+            }
+        }
+    };
+    mark_newest_per(&|t| (t.year(), t.ordinal()), policy.keep_daily);
+    mark_newest_per(
+        &|t| (t.iso_week().year(), t.iso_week().week()),
+        policy.keep_weekly,
+    );
+
+    keep
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_backup_names_round_trip() {
+        let created = "2024-05-01T12:00:00.123Z".parse::<DateTime<Utc>>().unwrap();
+        let name = backup_name(created, Some("import"));
+        assert_eq!(name, "vault.sf.20240501T120000123Z.import.backup");
+        assert_eq!(
+            parse_backup_name(&name),
+            Some((created, Some("import".to_string())))
+        );
+
+        // Names written by older versions
+        let legacy = parse_backup_name("vault.sf.1714564800.backup").unwrap();
+        assert_eq!(legacy.0.timestamp(), 1714564800);
+        assert_eq!(legacy.1, None);
+        assert!(parse_backup_name("vault.sf").is_none());
+    }
+
+    #[test]
+    fn test_retention_policy() {
+        let backup = |created: &str| Backup {
+            path: PathBuf::new(),
+            created: created.parse().unwrap(),
+            reason: None,
+            size: 0,
+        };
+        let backups = [
+            backup("2024-05-10T12:00:00Z"),
+            backup("2024-05-10T08:00:00Z"),
+            backup("2024-05-09T12:00:00Z"),
+            backup("2024-05-08T12:00:00Z"),
+            backup("2024-05-01T12:00:00Z"),
+            backup("2024-04-20T12:00:00Z"),
+        ];
+        let policy = BackupPolicy {
+            keep_last: 1,
+            keep_daily: 2,
+            keep_weekly: 3,
+        };
+
+        assert_eq!(
+            retained(&backups, &policy),
+            [true, false, true, false, true, true]
+        );
+    }
+
+    #[test]
+    fn test_create_prune_and_verify() {
+        let temp_dir = tempdir().unwrap();
+        let storage = crate::storage::VaultStorage::with_path(temp_dir.path().join("vault.sf"));
+        storage
+            .save(&crate::models::Vault::new(), "password")
+            .unwrap();
+        let (_, key) = storage.unlock("password").unwrap();
+
+        let store = BackupStore::with_dir(temp_dir.path().join(BACKUPS_DIR));
+        let first = store.create(storage.path(), Some("import")).unwrap();
+        let second = store.create(storage.path(), None).unwrap();
+        assert_ne!(first.path, second.path);
+
+        let backups = store.list().unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[1].reason.as_deref(), Some("import"));
+        assert_eq!(
+            store.verify(&backups[0], &key),
+            BackupStatus::Valid { items: 0 }
+        );
+
+        fs::write(&first.path, b"{ not a vault").unwrap();
+        assert!(matches!(
+            store.verify(&backups[1], &key),
+            BackupStatus::Corrupt(_)
+        ));
+
+        let keep_one = BackupPolicy {
+            keep_last: 1,
+            keep_daily: 0,
+            keep_weekly: 0,
+        };
+        assert_eq!(store.prune(&keep_one).unwrap().len(), 1);
+        assert_eq!(store.list().unwrap()[0].path, second.path);
+    }
+}
@@ -7,7 +7,7 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 use crate::errors::{Error, Result};
-use crate::models::SyncConfigFile;
+use crate::models::{SyncConfig, SyncConfigFile, VaultProfile, DEFAULT_PROFILE};
 
 /// Default config directory name
 const CONFIG_DIR_NAME: &str = ".securefox";
@@ -15,6 +15,13 @@ const CONFIG_DIR_NAME: &str = ".securefox";
 /// Default config file name
 const CONFIG_FILE_NAME: &str = "config";
 
+/// Directory of the default vault (~/.securefox)
+pub fn default_vault_dir() -> Result<PathBuf> {
+    let home = dirs::home_dir()
+        .ok_or_else(|| Error::Other("Could not determine home directory".to_string()))?;
+    Ok(home.join(CONFIG_DIR_NAME))
+}
+
 /// Config file manager
 pub struct ConfigManager {
     config_path: PathBuf,
@@ -23,11 +30,8 @@ pub struct ConfigManager {
 impl ConfigManager {
     /// Create a new config manager with default path (~/.securefox/config)
     pub fn new() -> Result<Self> {
-        let home = dirs::home_dir()
-            .ok_or_else(|| Error::Other("Could not determine home directory".to_string()))?;
-        let config_dir = home.join(CONFIG_DIR_NAME);
         Ok(Self {
-            config_path: config_dir.join(CONFIG_FILE_NAME),
+            config_path: default_vault_dir()?.join(CONFIG_FILE_NAME),
         })
     }
 
@@ -89,6 +93,153 @@ impl ConfigManager {
         config.sync_config = sync_config;
         self.save(&config)
     }
+
+    /// Update the automatic KDF upgrade policy
+    pub fn update_auto_upgrade_kdf(&self, enabled: bool) -> Result<()> {
+        let mut config = self.load()?;
+        config.auto_upgrade_kdf = enabled;
+        self.save(&config)
+    }
+
+    /// Update the PIN quick unlock limits
+    pub fn update_pin_policy(&self, policy: Option<crate::models::PinPolicy>) -> Result<()> {
+        let mut config = self.load()?;
+        config.pin_policy = policy;
+        self.save(&config)
+    }
+
+    /// Update how many days trashed items are kept
+    pub fn update_trash_retention_days(&self, days: Option<u32>) -> Result<()> {
+        let mut config = self.load()?;
+        config.trash_retention_days = days;
+        self.save(&config)
+    }
+
+    /// Update the git remote of the vault in `vault_dir`
+    pub fn update_remote_url_for(&self, vault_dir: &Path, url: Option<String>) -> Result<()> {
+        let mut config = self.load()?;
+        match profile_mut(&mut config, vault_dir) {
+            Some(profile) => profile.remote_url = url,
+            None => config.remote_url = url,
+        }
+        self.save(&config)
+    }
+
+    /// Update the auto-sync configuration of the vault in `vault_dir`
+    pub fn update_sync_config_for(
+        &self,
+        vault_dir: &Path,
+        sync_config: Option<SyncConfig>,
+    ) -> Result<()> {
+        let mut config = self.load()?;
+        match profile_mut(&mut config, vault_dir) {
+            Some(profile) => profile.sync_config = sync_config,
+            None => config.sync_config = sync_config,
+        }
+        self.save(&config)
+    }
+
+    /// Update the keyfile required to unlock the vault in `vault_dir`
+    pub fn update_keyfile_for(&self, vault_dir: &Path, keyfile: Option<PathBuf>) -> Result<()> {
+        let mut config = self.load()?;
+        match profile_mut(&mut config, vault_dir) {
+            Some(profile) => profile.keyfile = keyfile,
+            None => config.keyfile = keyfile,
+        }
+        self.save(&config)
+    }
+
+    /// Register a named vault
+    pub fn add_vault(&self, name: &str, profile: VaultProfile) -> Result<()> {
+        validate_profile_name(name)?;
+        let mut config = self.load()?;
+        if config.vaults.contains_key(name) {
+            return Err(Error::Other(format!("Vault '{}' already exists", name)));
+        }
+        if let Some((other, _)) = config.profile_for(&profile.path) {
+            return Err(Error::Other(format!(
+                "{} is already registered as '{}'",
+                profile.path.display(),
+                other
+            )));
+        }
+        config.vaults.insert(name.to_string(), profile);
+        self.save(&config)
+    }
+
+    /// Unregister a named vault, leaving its files in place
+    pub fn remove_vault(&self, name: &str) -> Result<VaultProfile> {
+        let mut config = self.load()?;
+        let profile = config
+            .vaults
+            .remove(name)
+            .ok_or_else(|| unknown_profile(name))?;
+        if config.current_vault.as_deref() == Some(name) {
+            config.current_vault = None;
+        }
+        self.save(&config)?;
+        Ok(profile)
+    }
+
+    /// Select the vault used when a command names none
+    pub fn use_vault(&self, name: &str) -> Result<()> {
+        let mut config = self.load()?;
+        if name == DEFAULT_PROFILE {
+            config.current_vault = None;
+        } else if config.vaults.contains_key(name) {
+            config.current_vault = Some(name.to_string());
+        } else {
+            return Err(unknown_profile(name));
+        }
+        self.save(&config)
+    }
+
+    /// Directory of the vault named `profile`, or of the current vault
+    pub fn vault_dir(&self, profile: Option<&str>) -> Result<PathBuf> {
+        let config = self.load()?;
+        match profile.or(config.current_vault.as_deref()) {
+            None | Some(DEFAULT_PROFILE) => default_vault_dir(),
+            Some(name) => config
+                .vaults
+                .get(name)
+                .map(|p| p.path.clone())
+                .ok_or_else(|| unknown_profile(name)),
+        }
+    }
+
+    /// Update how many vault backups are kept
+    pub fn update_backup_policy(&self, policy: Option<crate::models::BackupPolicy>) -> Result<()> {
+        let mut config = self.load()?;
+        config.backup_policy = policy;
+        self.save(&config)
+    }
+}
+
+fn profile_mut<'a>(
+    config: &'a mut SyncConfigFile,
+    vault_dir: &Path,
+) -> Option<&'a mut VaultProfile> {
+    config.vaults.values_mut().find(|p| p.path == vault_dir)
+}
+
+fn validate_profile_name(name: &str) -> Result<()> {
+    if name == DEFAULT_PROFILE {
+        return Err(Error::Other(format!("'{}' is reserved", DEFAULT_PROFILE)));
+    }
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(Error::Other(
+            "Vault names may only contain letters, digits, '-' and '_'".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn unknown_profile(name: &str) -> Error {
+    Error::Other(format!("Unknown vault '{}'", name))
 }
 
 impl Default for ConfigManager {
@@ -96,3 +247,82 @@ impl Default for ConfigManager {
         Self::new().expect("Failed to create default ConfigManager")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_vault_profiles() {
+        let temp_dir = tempdir().unwrap();
+        let manager = ConfigManager::with_path(temp_dir.path().join("config"));
+        let work = temp_dir.path().join("work");
+
+        manager.add_vault("work", VaultProfile::new(&work)).unwrap();
+        assert!(manager
+            .add_vault("work", VaultProfile::new("elsewhere"))
+            .is_err());
+        assert!(manager.add_vault("copy", VaultProfile::new(&work)).is_err());
+        assert!(manager
+            .add_vault(DEFAULT_PROFILE, VaultProfile::new("x"))
+            .is_err());
+        assert!(manager.add_vault("a/b", VaultProfile::new("x")).is_err());
+
+        assert_eq!(manager.vault_dir(Some("work")).unwrap(), work);
+        assert_eq!(
+            manager.vault_dir(None).unwrap(),
+            default_vault_dir().unwrap()
+        );
+        assert!(manager.vault_dir(Some("personal")).is_err());
+
+        manager.use_vault("work").unwrap();
+        assert_eq!(manager.vault_dir(None).unwrap(), work);
+        assert!(manager.use_vault("personal").is_err());
+
+        // Sync settings of a registered vault stay with it
+        manager
+            .update_remote_url_for(&work, Some("git@example.com:work.git".to_string()))
+            .unwrap();
+        manager
+            .update_remote_url_for(temp_dir.path(), Some("git@example.com:me.git".to_string()))
+            .unwrap();
+        let config = manager.load().unwrap();
+        assert_eq!(
+            config.remote_url_for(&work),
+            Some("git@example.com:work.git")
+        );
+        assert_eq!(config.remote_url.as_deref(), Some("git@example.com:me.git"));
+
+        manager.remove_vault("work").unwrap();
+        assert_eq!(manager.load().unwrap().current_vault, None);
+        assert!(manager.remove_vault("work").is_err());
+    }
+
+    #[test]
+    fn test_keyfile_is_kept_per_vault() {
+        let temp_dir = tempdir().unwrap();
+        let manager = ConfigManager::with_path(temp_dir.path().join("config"));
+        let work = temp_dir.path().join("work");
+        let personal = temp_dir.path().join("personal");
+        manager.add_vault("work", VaultProfile::new(&work)).unwrap();
+        manager
+            .add_vault("personal", VaultProfile::new(&personal))
+            .unwrap();
+
+        let keyfile = temp_dir.path().join("work.keyx");
+        manager
+            .update_keyfile_for(&work, Some(keyfile.clone()))
+            .unwrap();
+
+        // Only the work vault asks for the keyfile
+        let config = manager.load().unwrap();
+        assert_eq!(config.keyfile_for(&work), Some(keyfile.as_path()));
+        assert_eq!(config.keyfile_for(&personal), None);
+        assert_eq!(config.keyfile_for(temp_dir.path()), None);
+        assert_eq!(config.keyfile, None);
+
+        manager.update_keyfile_for(&work, None).unwrap();
+        assert_eq!(manager.load().unwrap().keyfile_for(&work), None);
+    }
+}
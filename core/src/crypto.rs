@@ -1,7 +1,7 @@
 //! Cryptographic operations for SecureFox
 
 use aes_gcm_siv::{
-    aead::{Aead, AeadCore, KeyInit, OsRng},
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
     Aes256GcmSiv, Nonce,
 };
 use argon2::{
@@ -9,20 +9,27 @@ use argon2::{
     Argon2, Params, Version,
 };
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, Mac};
 use pbkdf2::pbkdf2_hmac_array;
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
-use sha2::Sha256;
-use zeroize::{Zeroize, ZeroizeOnDrop};
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 use crate::errors::{Error, Result};
 
 /// Size of the encryption key in bytes
-const KEY_SIZE: usize = 32;
+pub const KEY_SIZE: usize = 32;
 
 /// Size of the nonce in bytes for AES-GCM-SIV
 const NONCE_SIZE: usize = 12;
 
+/// Size of key check values in bytes
+const KEY_CHECK_SIZE: usize = 16;
+
+/// Domain separation string for key check values
+const KEY_CHECK_CONTEXT: &[u8] = b"securefox key check v1";
+
 /// Argon2 memory cost in KB (19MB - OWASP recommended minimum)
 const ARGON2_MEMORY_KB: u32 = 19456;
 
@@ -33,8 +40,9 @@ const ARGON2_ITERATIONS: u32 = 2;
 const ARGON2_PARALLELISM: u32 = 1;
 
 /// PBKDF2 iterations (OWASP recommended: 600,000 for PBKDF2-HMAC-SHA256)
-/// We use 100,000 for better UX while still maintaining good security
-const PBKDF2_ITERATIONS: u32 = 100_000;
+///
+/// Fewer iterations are reported as weak; older vaults used 100,000.
+const PBKDF2_ITERATIONS: u32 = 600_000;
 
 /// Encryption key wrapper with automatic zeroing on drop
 #[derive(Clone, Zeroize, ZeroizeOnDrop)]
@@ -116,44 +124,53 @@ impl KdfParams {
     pub fn pbkdf2() -> Self {
         Self::default()
     }
+
+    /// Same algorithm and cost settings with a freshly generated salt
+    pub fn with_new_salt(&self) -> Self {
+        let fresh = match self.algorithm {
+            KdfAlgorithm::Argon2id => Self::argon2(),
+            KdfAlgorithm::Pbkdf2 => Self::pbkdf2(),
+        };
+        Self {
+            salt: fresh.salt,
+            ..self.clone()
+        }
+    }
+
+    /// Whether the cost settings fall below current OWASP recommendations
+    pub fn is_weak(&self) -> bool {
+        match self.algorithm {
+            KdfAlgorithm::Argon2id => {
+                self.memory_kb.unwrap_or(ARGON2_MEMORY_KB) < ARGON2_MEMORY_KB
+                    || self.iterations < ARGON2_ITERATIONS
+            }
+            KdfAlgorithm::Pbkdf2 => self.iterations < PBKDF2_ITERATIONS,
+        }
+    }
 }
 
 /// Encrypted data container
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedData {
-    pub kdf_params: KdfParams,
+    /// KDF parameters, present only when the key was derived from a secret
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kdf_params: Option<KdfParams>,
     pub nonce: String,
     pub ciphertext: String,
 }
 
 /// Derive an encryption key from a password
 pub fn derive_key(password: &str, params: &KdfParams) -> Result<EncryptionKey> {
+    derive_key_from_secret(password.as_bytes(), params)
+}
+
+/// Derive an encryption key from arbitrary secret bytes
+pub fn derive_key_from_secret(secret: &[u8], params: &KdfParams) -> Result<EncryptionKey> {
     match params.algorithm {
         KdfAlgorithm::Argon2id => {
+            // Argon2 memory cost is expressed in KiB
             let memory_kb = params.memory_kb.unwrap_or(ARGON2_MEMORY_KB);
-            let parallelism = params.parallelism.unwrap_or(ARGON2_PARALLELISM);
-
-            let argon2_params = Params::new(
-                memory_kb * 1024,
-                params.iterations,
-                parallelism,
-                Some(KEY_SIZE),
-            )
-            .map_err(|e| Error::Encryption(format!("Invalid Argon2 params: {}", e)))?;
-
-            let argon2 = Argon2::new(argon2::Algorithm::Argon2id, Version::V0x13, argon2_params);
-
-            let salt = SaltString::from_b64(&params.salt)
-                .map_err(|e| Error::Encryption(format!("Invalid salt: {}", e)))?;
-
-            let password_hash = argon2
-                .hash_password(password.as_bytes(), &salt)
-                .map_err(|e| Error::Encryption(format!("Key derivation failed: {}", e)))?;
-
-            let hash_bytes = password_hash.hash.unwrap();
-            let key_bytes = hash_bytes.as_bytes();
-
-            EncryptionKey::from_bytes(key_bytes)
+            derive_argon2_key(secret, params, memory_kb)
         }
         KdfAlgorithm::Pbkdf2 => {
             // Decode the base64 salt
@@ -162,17 +179,54 @@ pub fn derive_key(password: &str, params: &KdfParams) -> Result<EncryptionKey> {
                 .map_err(|e| Error::Encryption(format!("Invalid salt: {}", e)))?;
 
             // Derive key using PBKDF2-HMAC-SHA256
-            let key = pbkdf2_hmac_array::<Sha256, KEY_SIZE>(
-                password.as_bytes(),
-                &salt_bytes,
-                params.iterations,
-            );
+            let key = pbkdf2_hmac_array::<Sha256, KEY_SIZE>(secret, &salt_bytes, params.iterations);
 
             Ok(EncryptionKey { key })
         }
     }
 }
 
+/// Argon2id memory cost legacy containers used for `params`, in KiB
+///
+/// Before keyslots, the configured cost was multiplied by 1024 on top of
+/// Argon2's own KiB unit, so `KdfParams::argon2()` asked for about 19 GiB.
+/// Only legacy password-encrypted containers can have been derived that way.
+pub fn legacy_argon2_memory_kb(params: &KdfParams) -> Result<u32> {
+    params
+        .memory_kb
+        .unwrap_or(ARGON2_MEMORY_KB)
+        .checked_mul(1024)
+        .ok_or_else(|| Error::Encryption("Invalid Argon2 params: memory cost".to_string()))
+}
+
+fn derive_argon2_key(secret: &[u8], params: &KdfParams, memory_cost: u32) -> Result<EncryptionKey> {
+    let parallelism = params.parallelism.unwrap_or(ARGON2_PARALLELISM);
+    let argon2_params = Params::new(memory_cost, params.iterations, parallelism, Some(KEY_SIZE))
+        .map_err(|e| Error::Encryption(format!("Invalid Argon2 params: {}", e)))?;
+
+    let argon2 = Argon2::new(argon2::Algorithm::Argon2id, Version::V0x13, argon2_params);
+
+    let salt = SaltString::from_b64(&params.salt)
+        .map_err(|e| Error::Encryption(format!("Invalid salt: {}", e)))?;
+
+    let password_hash = argon2
+        .hash_password(secret, &salt)
+        .map_err(|e| Error::Encryption(format!("Key derivation failed: {}", e)))?;
+
+    let hash_bytes = password_hash.hash.unwrap();
+    EncryptionKey::from_bytes(hash_bytes.as_bytes())
+}
+
+/// Combine a password and keyfile key material into a single KDF input
+///
+/// Matches the KeePass composite key: `SHA-256(SHA-256(password) || keyfile)`.
+pub fn composite_secret(password: &[u8], keyfile_key: &[u8]) -> Zeroizing<[u8; KEY_SIZE]> {
+    let mut hasher = Sha256::new();
+    hasher.update(Sha256::digest(password));
+    hasher.update(keyfile_key);
+    Zeroizing::new(hasher.finalize().into())
+}
+
 /// Generate a new random encryption key
 pub fn generate_key() -> EncryptionKey {
     let mut key = [0u8; KEY_SIZE];
@@ -182,17 +236,35 @@ pub fn generate_key() -> EncryptionKey {
 
 /// Encrypt data using AES-256-GCM-SIV
 pub fn encrypt(plaintext: &[u8], key: &EncryptionKey) -> Result<EncryptedData> {
+    encrypt_with_aad(plaintext, key, &[])
+}
+
+/// Encrypt data using AES-256-GCM-SIV, authenticating `aad` alongside it
+///
+/// The associated data is not stored; the same bytes must be supplied to
+/// [`decrypt_with_aad`]. An empty `aad` is equivalent to [`encrypt`].
+pub fn encrypt_with_aad(
+    plaintext: &[u8],
+    key: &EncryptionKey,
+    aad: &[u8],
+) -> Result<EncryptedData> {
     let cipher = Aes256GcmSiv::new_from_slice(key.as_bytes())
         .map_err(|e| Error::Encryption(format!("Cipher creation failed: {}", e)))?;
 
     let nonce = Aes256GcmSiv::generate_nonce(&mut OsRng);
 
     let ciphertext = cipher
-        .encrypt(&nonce, plaintext)
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
         .map_err(|e| Error::Encryption(format!("Encryption failed: {}", e)))?;
 
     Ok(EncryptedData {
-        kdf_params: KdfParams::default(),
+        kdf_params: None,
         nonce: BASE64.encode(nonce),
         ciphertext: BASE64.encode(&ciphertext),
     })
@@ -222,7 +294,7 @@ pub fn encrypt_with_password_and_kdf(
         .map_err(|e| Error::Encryption(format!("Encryption failed: {}", e)))?;
 
     Ok(EncryptedData {
-        kdf_params,
+        kdf_params: Some(kdf_params),
         nonce: BASE64.encode(nonce),
         ciphertext: BASE64.encode(&ciphertext),
     })
@@ -230,6 +302,15 @@ pub fn encrypt_with_password_and_kdf(
 
 /// Decrypt data using AES-256-GCM-SIV
 pub fn decrypt(encrypted: &EncryptedData, key: &EncryptionKey) -> Result<Vec<u8>> {
+    decrypt_with_aad(encrypted, key, &[])
+}
+
+/// Decrypt data using AES-256-GCM-SIV, verifying the associated data
+pub fn decrypt_with_aad(
+    encrypted: &EncryptedData,
+    key: &EncryptionKey,
+    aad: &[u8],
+) -> Result<Vec<u8>> {
     let cipher = Aes256GcmSiv::new_from_slice(key.as_bytes())
         .map_err(|e| Error::Decryption(format!("Cipher creation failed: {}", e)))?;
 
@@ -252,22 +333,66 @@ pub fn decrypt(encrypted: &EncryptedData, key: &EncryptionKey) -> Result<Vec<u8>
         .map_err(|e| Error::Decryption(format!("Invalid ciphertext: {}", e)))?;
 
     cipher
-        .decrypt(nonce, ciphertext.as_ref())
+        .decrypt(
+            nonce,
+            Payload {
+                msg: &ciphertext,
+                aad,
+            },
+        )
         .map_err(|e| Error::Decryption(format!("Decryption failed: {}", e)))
 }
 
 /// Decrypt data with a password
 pub fn decrypt_with_password(encrypted: &EncryptedData, password: &str) -> Result<Vec<u8>> {
-    let key = derive_key(password, &encrypted.kdf_params)?;
+    let kdf_params = encrypted
+        .kdf_params
+        .as_ref()
+        .ok_or_else(|| Error::Decryption("Missing KDF parameters".to_string()))?;
+    let key = derive_key(password, kdf_params)?;
+    decrypt(encrypted, &key)
+}
+
+/// Decrypt an Argon2id legacy container with the cost it was written with
+///
+/// Needs `legacy_argon2_memory_kb` of memory, so it is only run on request.
+pub fn decrypt_with_legacy_argon2(encrypted: &EncryptedData, password: &str) -> Result<Vec<u8>> {
+    let kdf_params = encrypted
+        .kdf_params
+        .as_ref()
+        .filter(|params| matches!(params.algorithm, KdfAlgorithm::Argon2id))
+        .ok_or_else(|| Error::Decryption("Not an Argon2id container".to_string()))?;
+    let memory_kb = legacy_argon2_memory_kb(kdf_params)?;
+    let key = derive_argon2_key(password.as_bytes(), kdf_params, memory_kb)?;
     decrypt(encrypted, &key)
 }
 
-/// Verify a password against stored KDF params
-pub fn verify_password(password: &str, params: &KdfParams) -> Result<bool> {
-    // For verification, we just try to derive the key
-    // In a real implementation, we'd store a password verifier separately
-    derive_key(password, params)?;
-    Ok(true)
+/// Compute a key check value identifying a key without revealing it
+pub fn key_check(key: &EncryptionKey) -> String {
+    let mac = key_check_mac(key);
+    BASE64.encode(&mac.finalize().into_bytes()[..KEY_CHECK_SIZE])
+}
+
+/// Check in constant time whether a key matches a stored key check value
+pub fn matches_key_check(key: &EncryptionKey, check: &str) -> bool {
+    let expected = match BASE64.decode(check) {
+        Ok(bytes) if bytes.len() == KEY_CHECK_SIZE => bytes,
+        _ => return false,
+    };
+    key_check_mac(key).verify_truncated_left(&expected).is_ok()
+}
+
+fn key_check_mac(key: &EncryptionKey) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.as_bytes())
+        .expect("HMAC accepts keys of any size");
+    mac.update(KEY_CHECK_CONTEXT);
+    mac
+}
+
+/// Verify a password against stored KDF params and key check value
+pub fn verify_password(password: &str, params: &KdfParams, check: &str) -> Result<bool> {
+    let key = derive_key(password, params)?;
+    Ok(matches_key_check(&key, check))
 }
 
 #[cfg(test)]
@@ -308,6 +433,89 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_associated_data_is_authenticated() {
+        let key = generate_key();
+        let encrypted = encrypt_with_aad(b"payload", &key, b"header v3").unwrap();
+
+        assert_eq!(
+            decrypt_with_aad(&encrypted, &key, b"header v3").unwrap(),
+            b"payload"
+        );
+        assert!(decrypt_with_aad(&encrypted, &key, b"header v2").is_err());
+        assert!(decrypt(&encrypted, &key).is_err());
+    }
+
+    #[test]
+    fn test_key_check() {
+        let params = KdfParams::default();
+        let key = derive_key("test_password", &params).unwrap();
+        let check = key_check(&key);
+
+        assert!(matches_key_check(&key, &check));
+        assert!(!matches_key_check(&generate_key(), &check));
+        assert!(!matches_key_check(&key, "not base64!"));
+
+        assert!(verify_password("test_password", &params, &check).unwrap());
+        assert!(!verify_password("wrong_password", &params, &check).unwrap());
+    }
+
+    #[test]
+    fn test_weak_kdf_detection() {
+        assert!(!KdfParams::pbkdf2().is_weak());
+        assert!(!KdfParams::argon2().is_weak());
+
+        // Vaults created before the OWASP value became the default
+        let mut legacy = KdfParams::pbkdf2();
+        legacy.iterations = 100_000;
+        assert!(legacy.is_weak());
+
+        let params = KdfParams::argon2();
+        let resalted = params.with_new_salt();
+        assert_ne!(params.salt, resalted.salt);
+        assert_eq!(params.memory_kb, resalted.memory_kb);
+    }
+
+    fn fixed_argon2_params() -> KdfParams {
+        KdfParams {
+            algorithm: KdfAlgorithm::Argon2id,
+            salt: "c2VjdXJlZm94IHNhbHQ".to_string(),
+            memory_kb: Some(64),
+            iterations: 1,
+            parallelism: Some(1),
+        }
+    }
+
+    #[test]
+    fn test_argon2_key_is_pinned() {
+        // Changing this value locks users out of existing Argon2 vaults
+        let key = derive_key("test_password", &fixed_argon2_params()).unwrap();
+        assert_eq!(
+            hex::encode(key.as_bytes()),
+            "cc1aafc3afef29bcd9b789dd32ae140cf5fac0db869b0b338688482571512e27"
+        );
+    }
+
+    #[test]
+    fn test_legacy_argon2_container_needs_opt_in() {
+        let params = fixed_argon2_params();
+        let memory_kb = legacy_argon2_memory_kb(&params).unwrap();
+        assert_eq!(memory_kb, 64 * 1024);
+        let legacy_key = derive_argon2_key(b"test_password", &params, memory_kb).unwrap();
+        let mut encrypted = encrypt(b"legacy vault", &legacy_key).unwrap();
+        encrypted.kdf_params = Some(params);
+
+        // The plain path never runs the legacy derivation, even for the right password
+        assert!(decrypt_with_password(&encrypted, "test_password").is_err());
+        assert!(decrypt_with_password(&encrypted, "wrong_password").is_err());
+
+        assert_eq!(
+            decrypt_with_legacy_argon2(&encrypted, "test_password").unwrap(),
+            b"legacy vault"
+        );
+        assert!(decrypt_with_legacy_argon2(&encrypted, "wrong_password").is_err());
+    }
+
     #[test]
     fn test_key_zeroization() {
         let mut key = generate_key();
@@ -0,0 +1,279 @@
+//! Comparison of vaults and items
+//!
+//! Items are matched by id and compared through their JSON form, so new
+//! item fields are covered without changes here. Fields are named by their
+//! JSON path, e.g. `login.password` or `login.uris[0].uri`.
+
+use serde_json::Value;
+
+use crate::errors::Result;
+use crate::models::{FieldType, Folder, Item, Vault};
+
+/// Fields that change on every edit or follow from other changes
+const IGNORED_FIELDS: &[&str] = &["revisionDate", "passwordHistory"];
+
+/// Fields whose values are secrets
+const SECRET_FIELDS: &[&str] = &[
+    "notes",
+    "login.password",
+    "login.totp",
+    "card.number",
+    "card.code",
+    "sshKey.privateKey",
+];
+
+/// A field that differs between two versions of an item
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    /// JSON path of the field
+    pub field: String,
+    /// Value before the change, `None` if the field was absent
+    pub old: Option<String>,
+    /// Value after the change, `None` if the field was removed
+    pub new: Option<String>,
+    /// Whether the values are secrets that should be masked when shown
+    pub secret: bool,
+}
+
+/// An item present in both vaults that differs between them
+#[derive(Debug, Clone)]
+pub struct ItemDiff {
+    pub id: String,
+    /// Name in the newer vault
+    pub name: String,
+    pub fields: Vec<FieldChange>,
+}
+
+/// Differences between two vaults
+#[derive(Debug, Clone, Default)]
+pub struct VaultDiff {
+    pub added_items: Vec<Item>,
+    pub removed_items: Vec<Item>,
+    pub modified_items: Vec<ItemDiff>,
+    pub added_folders: Vec<Folder>,
+    pub removed_folders: Vec<Folder>,
+    /// Folders with a new name, as `(old, new)`
+    pub renamed_folders: Vec<(Folder, Folder)>,
+}
+
+impl VaultDiff {
+    /// Whether the vaults hold the same items and folders
+    pub fn is_empty(&self) -> bool {
+        self.added_items.is_empty()
+            && self.removed_items.is_empty()
+            && self.modified_items.is_empty()
+            && self.added_folders.is_empty()
+            && self.removed_folders.is_empty()
+            && self.renamed_folders.is_empty()
+    }
+}
+
+/// Compare the items and folders of `old` and `new`
+pub fn vault_diff(old: &Vault, new: &Vault) -> Result<VaultDiff> {
+    let mut diff = VaultDiff::default();
+
+    for item in &new.items {
+        match old.get_item(&item.id) {
+            None => diff.added_items.push(item.clone()),
+            Some(old_item) => {
+                let fields = item_changes(old_item, item)?;
+                if !fields.is_empty() {
+                    diff.modified_items.push(ItemDiff {
+                        id: item.id.clone(),
+                        name: item.name.clone(),
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+    diff.removed_items = old
+        .items
+        .iter()
+        .filter(|i| new.get_item(&i.id).is_none())
+        .cloned()
+        .collect();
+
+    for folder in &new.folders {
+        match old.folders.iter().find(|f| f.id == folder.id) {
+            None => diff.added_folders.push(folder.clone()),
+            Some(old_folder) if old_folder.name != folder.name => {
+                diff.renamed_folders
+                    .push((old_folder.clone(), folder.clone()));
+            }
+            Some(_) => {}
+        }
+    }
+    diff.removed_folders = old
+        .folders
+        .iter()
+        .filter(|f| !new.folders.iter().any(|n| n.id == f.id))
+        .cloned()
+        .collect();
+
+    Ok(diff)
+}
+
+/// Fields that differ between `old` and `new`, in path order
+pub fn item_changes(old: &Item, new: &Item) -> Result<Vec<FieldChange>> {
+    let old_fields = flatten_item(old)?;
+    let new_fields = flatten_item(new)?;
+
+    let mut changes = Vec::new();
+    for (field, old_value) in &old_fields {
+        let new_value = new_fields.iter().find(|(f, _)| f == field).map(|(_, v)| v);
+        if new_value != Some(old_value) {
+            changes.push(FieldChange {
+                field: field.clone(),
+                old: Some(old_value.clone()),
+                new: new_value.cloned(),
+                secret: is_secret(old, field),
+            });
+        }
+    }
+    for (field, new_value) in &new_fields {
+        if !old_fields.iter().any(|(f, _)| f == field) {
+            changes.push(FieldChange {
+                field: field.clone(),
+                old: None,
+                new: Some(new_value.clone()),
+                secret: is_secret(new, field),
+            });
+        }
+    }
+
+    changes.sort_by(|a, b| a.field.cmp(&b.field));
+    Ok(changes)
+}
+
+/// Leaf values of `item` keyed by JSON path
+fn flatten_item(item: &Item) -> Result<Vec<(String, String)>> {
+    let mut fields = Vec::new();
+    flatten(&serde_json::to_value(item)?, String::new(), &mut fields);
+    fields.retain(|(field, _)| is_compared(field));
+    Ok(fields)
+}
+
+fn is_compared(field: &str) -> bool {
+    let root = field.split(['.', '[']).next().unwrap_or_default();
+    if IGNORED_FIELDS.contains(&root) {
+        return false;
+    }
+
+    // Attachment keys are never shown; a changed blob shows up as a new id
+    root != "attachments" || !field.ends_with(".key")
+}
+
+fn flatten(value: &Value, path: String, fields: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                flatten(value, path, fields);
+            }
+        }
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                flatten(value, format!("{}[{}]", path, i), fields);
+            }
+        }
+        Value::Null => {}
+        Value::String(s) => fields.push((path, s.clone())),
+        other => fields.push((path, other.to_string())),
+    }
+}
+
+fn is_secret(item: &Item, field: &str) -> bool {
+    if SECRET_FIELDS.contains(&field) {
+        return true;
+    }
+
+    // Custom fields are secret when hidden
+    field
+        .strip_prefix("fields[")
+        .and_then(|rest| rest.strip_suffix("].value"))
+        .and_then(|i| i.parse::<usize>().ok())
+        .and_then(|i| item.fields.as_ref()?.get(i))
+        .is_some_and(|f| f.field_type.0 == FieldType::HIDDEN.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CustomField;
+
+    #[test]
+    fn test_item_changes() {
+        let mut old = Item::new_login("GitHub");
+        old.login.as_mut().unwrap().username = Some("old-user".to_string());
+        old.login.as_mut().unwrap().password = Some("old-password".into());
+
+        let mut new = old.clone();
+        new.name = "GitHub (work)".to_string();
+        new.login.as_mut().unwrap().password = Some("new-password".into());
+        new.fields = Some(vec![CustomField {
+            name: "pin".to_string(),
+            value: "1234".into(),
+            field_type: FieldType::HIDDEN,
+        }]);
+        new.revision_date = chrono::Utc::now() + chrono::Duration::seconds(5);
+        new.remember_password(old.login.as_ref().unwrap().password.as_ref());
+
+        let changes = item_changes(&old, &new).unwrap();
+        let fields: Vec<_> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            [
+                "fields[0].name",
+                "fields[0].type",
+                "fields[0].value",
+                "login.password",
+                "name"
+            ]
+        );
+
+        let password = &changes[3];
+        assert!(password.secret);
+        assert_eq!(password.old.as_deref(), Some("old-password"));
+        assert!(changes[2].secret);
+        assert!(!changes[4].secret);
+        assert_eq!(changes[0].old, None);
+
+        assert!(item_changes(&old, &old).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_vault_diff() {
+        let mut old = Vault::new();
+        let kept = Item::new_login("kept");
+        let removed = Item::new_login("removed");
+        let folder = Folder::new("Work");
+        old.add_item(kept.clone());
+        old.add_item(removed.clone());
+        old.folders.push(folder.clone());
+        old.folders.push(Folder::new("Old"));
+
+        let mut new = old.clone();
+        assert!(vault_diff(&old, &new).unwrap().is_empty());
+
+        new.remove_item(&removed.id);
+        new.add_item(Item::new_secure_note("added"));
+        new.trash_item(&kept.id);
+        new.folders.remove(1);
+        new.folders[0].name = "Office".to_string();
+        new.folders.push(Folder::new("New"));
+
+        let diff = vault_diff(&old, &new).unwrap();
+        assert_eq!(diff.added_items[0].name, "added");
+        assert_eq!(diff.removed_items[0].id, removed.id);
+        assert_eq!(diff.modified_items[0].id, kept.id);
+        assert_eq!(diff.modified_items[0].fields[0].field, "deletedDate");
+        assert_eq!(diff.added_folders[0].name, "New");
+        assert_eq!(diff.removed_folders[0].name, "Old");
+        assert_eq!(diff.renamed_folders[0].1.name, "Office");
+    }
+}
@@ -21,15 +21,60 @@ pub enum Error {
     #[error("Invalid password")]
     InvalidPassword,
 
+    #[error("Invalid password; Argon2 vaults made by older versions need `securefox unlock --legacy-argon2`, which uses about {0} GiB of memory")]
+    LegacyArgon2Password(u32),
+
+    #[error("This vault requires a keyfile; pass --keyfile or configure one")]
+    KeyfileRequired,
+
+    #[error("Keyfile error: {0}")]
+    Keyfile(String),
+
+    #[error("Invalid PIN ({0} attempts left)")]
+    InvalidPin(u32),
+
+    #[error("PIN unlock unavailable: {0}")]
+    PinUnavailable(String),
+
+    #[error("ssh-agent error: {0}")]
+    SshAgent(String),
+
+    #[error("Recovery error: {0}")]
+    Recovery(String),
+
+    #[error("Attachment error: {0}")]
+    Attachment(String),
+
+    #[error("Vault key does not match the vault on disk")]
+    KeyMismatch,
+
+    #[error("Unsupported vault format: {0}")]
+    UnsupportedVersion(String),
+
+    #[error("Vault was changed by another process; reload it and try again")]
+    StaleWrite,
+
     #[error("Vault not found")]
     VaultNotFound,
 
     #[error("Item not found: {0}")]
     ItemNotFound(String),
 
+    #[error("Folder not found: {0}")]
+    FolderNotFound(String),
+
+    #[error("Folder error: {0}")]
+    Folder(String),
+
+    #[error("Invalid tag: {0:?}")]
+    InvalidTag(String),
+
     #[error("Invalid TOTP secret")]
     InvalidTotp,
 
+    #[error("SSH key error: {0}")]
+    SshKey(String),
+
     #[error("Keychain error: {0}")]
     Keychain(String),
 
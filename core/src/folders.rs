@@ -0,0 +1,268 @@
+//! Folder management and nested folders
+//!
+//! As in Bitwarden, nesting is encoded in folder names: `Work/Cloud/AWS` is
+//! shown under `Work/Cloud` if that folder exists, otherwise under `Work`,
+//! otherwise at the top. Renaming a folder renames the folders nested under
+//! it; deleting one leaves them in place, under its parent.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{Error, Result};
+use crate::models::{Folder, Item, Vault};
+
+/// Separator of the levels of a nested folder name
+pub const FOLDER_SEPARATOR: char = '/';
+
+/// A folder and the folders nested under it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderNode {
+    #[serde(flatten)]
+    pub folder: Folder,
+    pub children: Vec<FolderNode>,
+}
+
+impl Folder {
+    /// Name without the parent levels, e.g. `AWS` for `Work/Cloud/AWS`
+    pub fn leaf_name(&self) -> &str {
+        self.name
+            .rsplit(FOLDER_SEPARATOR)
+            .next()
+            .unwrap_or(&self.name)
+    }
+
+    /// Whether this folder is named `path` or nested under it
+    pub fn is_within(&self, path: &str) -> bool {
+        self.name
+            .strip_prefix(path)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(FOLDER_SEPARATOR))
+    }
+}
+
+/// Trim every level of a folder name, rejecting empty levels
+pub fn normalize_name(name: &str) -> Result<String> {
+    let levels: Vec<_> = name.split(FOLDER_SEPARATOR).map(str::trim).collect();
+    if levels.iter().any(|level| level.is_empty()) {
+        return Err(Error::Folder(format!("Invalid folder name '{}'", name)));
+    }
+    Ok(levels.join(&FOLDER_SEPARATOR.to_string()))
+}
+
+/// Arrange `folders` as a tree, sorted by name
+pub fn folder_tree(folders: &[Folder]) -> Vec<FolderNode> {
+    let mut sorted: Vec<&Folder> = folders.iter().collect();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name));
+
+    // The parent is the folder with the longest name the folder is nested under
+    let parent = |folder: &Folder| {
+        sorted
+            .iter()
+            .filter(|p| p.name != folder.name && folder.is_within(&p.name))
+            .max_by_key(|p| p.name.len())
+            .map(|p| p.id.clone())
+    };
+    let parents: Vec<Option<String>> = sorted.iter().map(|f| parent(f)).collect();
+
+    fn children(
+        parent: Option<&str>,
+        sorted: &[&Folder],
+        parents: &[Option<String>],
+    ) -> Vec<FolderNode> {
+        sorted
+            .iter()
+            .zip(parents)
+            .filter(|(_, p)| p.as_deref() == parent)
+            .map(|(folder, _)| FolderNode {
+                folder: (*folder).clone(),
+                children: children(Some(&folder.id), sorted, parents),
+            })
+            .collect()
+    }
+    children(None, &sorted, &parents)
+}
+
+impl Vault {
+    pub fn get_folder(&self, id: &str) -> Option<&Folder> {
+        self.folders.iter().find(|f| f.id == id)
+    }
+
+    /// Folder with the ID or full name `query`
+    pub fn find_folder(&self, query: &str) -> Option<&Folder> {
+        let name = normalize_name(query).unwrap_or_else(|_| query.to_string());
+        self.get_folder(query)
+            .or_else(|| self.folders.iter().find(|f| f.name == name))
+    }
+
+    /// Folders arranged as a tree
+    pub fn folder_tree(&self) -> Vec<FolderNode> {
+        folder_tree(&self.folders)
+    }
+
+    /// Create a folder; `Work/Cloud` creates `Cloud` nested under `Work`
+    pub fn add_folder(&mut self, name: &str) -> Result<&Folder> {
+        let name = normalize_name(name)?;
+        if self.folders.iter().any(|f| f.name == name) {
+            return Err(Error::Folder(format!("Folder '{}' already exists", name)));
+        }
+
+        self.folders.push(Folder::new(name));
+        self.sync_time = Utc::now();
+        Ok(self.folders.last().expect("folder was just added"))
+    }
+
+    /// Rename a folder along with the folders nested under it
+    ///
+    /// Returns the number of folders renamed.
+    pub fn rename_folder(&mut self, id: &str, new_name: &str) -> Result<usize> {
+        let new_name = normalize_name(new_name)?;
+        let old_name = self
+            .get_folder(id)
+            .ok_or_else(|| Error::FolderNotFound(id.to_string()))?
+            .name
+            .clone();
+
+        let renamed: Vec<(usize, String)> = self
+            .folders
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.is_within(&old_name))
+            .map(|(i, f)| (i, format!("{}{}", new_name, &f.name[old_name.len()..])))
+            .collect();
+
+        // Folders outside the renamed subtree keep their names; avoid clashes
+        for (_, name) in &renamed {
+            if self
+                .folders
+                .iter()
+                .any(|f| f.name == *name && !f.is_within(&old_name))
+            {
+                return Err(Error::Folder(format!("Folder '{}' already exists", name)));
+            }
+        }
+
+        for (i, name) in &renamed {
+            self.folders[*i].name = name.clone();
+        }
+        self.sync_time = Utc::now();
+        Ok(renamed.len())
+    }
+
+    /// Delete a folder, moving its items into `move_to` or out of any folder
+    ///
+    /// Folders nested under the deleted one are kept and move up the tree.
+    /// Returns the deleted folder and the number of items moved.
+    pub fn remove_folder(&mut self, id: &str, move_to: Option<&str>) -> Result<(Folder, usize)> {
+        let position = self
+            .folders
+            .iter()
+            .position(|f| f.id == id)
+            .ok_or_else(|| Error::FolderNotFound(id.to_string()))?;
+        if let Some(target) = move_to {
+            if target == id {
+                return Err(Error::Folder(
+                    "Cannot move items into the folder being deleted".to_string(),
+                ));
+            }
+            self.get_folder(target)
+                .ok_or_else(|| Error::FolderNotFound(target.to_string()))?;
+        }
+
+        let now = Utc::now();
+        let mut moved = 0;
+        for item in self
+            .items
+            .iter_mut()
+            .filter(|i| i.folder_id.as_deref() == Some(id))
+        {
+            item.folder_id = move_to.map(str::to_string);
+            item.revision_date = now;
+            moved += 1;
+        }
+
+        self.sync_time = now;
+        Ok((self.folders.remove(position), moved))
+    }
+
+    /// Put an item into a folder, or out of any folder with `None`
+    pub fn move_item(&mut self, item_id: &str, folder_id: Option<&str>) -> Result<&Item> {
+        if let Some(folder_id) = folder_id {
+            self.get_folder(folder_id)
+                .ok_or_else(|| Error::FolderNotFound(folder_id.to_string()))?;
+        }
+
+        let item = self
+            .get_item_mut(item_id)
+            .ok_or_else(|| Error::ItemNotFound(item_id.to_string()))?;
+        if item.folder_id.as_deref() != folder_id {
+            item.folder_id = folder_id.map(str::to_string);
+            item.revision_date = Utc::now();
+        }
+        Ok(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folder_tree() {
+        let mut vault = Vault::new();
+        for name in [
+            "Work/Cloud/AWS",
+            "Personal",
+            "Work",
+            "Work / Cloud",
+            "Archive/2020",
+        ] {
+            vault.add_folder(name).unwrap();
+        }
+        assert!(vault.add_folder("Work/Cloud").is_err());
+        assert!(vault.add_folder("Work//Cloud").is_err());
+
+        let tree = vault.folder_tree();
+        let names: Vec<_> = tree.iter().map(|n| n.folder.name.as_str()).collect();
+        assert_eq!(names, ["Archive/2020", "Personal", "Work"]);
+
+        let work = &tree[2];
+        assert_eq!(work.children.len(), 1);
+        assert_eq!(work.children[0].folder.leaf_name(), "Cloud");
+        assert_eq!(work.children[0].children[0].folder.name, "Work/Cloud/AWS");
+        assert!(!work.children[0].children[0].folder.is_within("Work/Clo"));
+    }
+
+    #[test]
+    fn test_rename_remove_and_move() {
+        let mut vault = Vault::new();
+        let work = vault.add_folder("Work").unwrap().id.clone();
+        let aws = vault.add_folder("Work/AWS").unwrap().id.clone();
+        let personal = vault.add_folder("Personal").unwrap().id.clone();
+        vault.add_folder("Job/AWS").unwrap();
+
+        let item = Item::new_login("Console");
+        let item_id = item.id.clone();
+        vault.add_item(item);
+        vault.move_item(&item_id, Some(&work)).unwrap();
+        assert!(vault.move_item(&item_id, Some("missing")).is_err());
+
+        // Renaming a folder renames its subfolders, without clashing
+        assert!(vault.rename_folder(&work, "Job").is_err());
+        assert_eq!(vault.rename_folder(&work, "Office").unwrap(), 2);
+        assert_eq!(vault.get_folder(&aws).unwrap().name, "Office/AWS");
+
+        // Deleting a folder moves its items and keeps its subfolders
+        assert!(vault.remove_folder(&work, Some(&work)).is_err());
+        let (removed, moved) = vault.remove_folder(&work, Some(&personal)).unwrap();
+        assert_eq!((removed.name.as_str(), moved), ("Office", 1));
+        assert_eq!(
+            vault.get_item(&item_id).unwrap().folder_id.as_deref(),
+            Some(personal.as_str())
+        );
+        assert!(vault.get_folder(&aws).is_some());
+
+        vault.remove_folder(&personal, None).unwrap();
+        assert_eq!(vault.get_item(&item_id).unwrap().folder_id, None);
+        assert_eq!(vault.find_folder("Office/AWS").unwrap().id, aws);
+    }
+}
@@ -1,5 +1,6 @@
 //! Git synchronization for vault
 
+use chrono::{DateTime, Utc};
 use git2::{
     Cred, CredentialType, FetchOptions, PushOptions, RemoteCallbacks, Repository, Signature, Status,
 };
@@ -8,6 +9,7 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 use crate::errors::{Error, Result};
+use crate::storage::{VaultStorage, VAULT_FILE_NAME};
 
 /// Git sync manager
 pub struct GitSync {
@@ -51,7 +53,7 @@ impl GitSync {
             return Ok(());
         }
 
-        let gitignore_content = "# SecureFox service files\nservice.err\nservice.pid\nservice.log\n\n# Backups\nbackups/\n\n# System files\n.DS_Store\nThumbs.db\n";
+        let gitignore_content = "# SecureFox service files\nservice.err\nservice.pid\nservice.log\n\n# Vault lock and interrupted writes\n*.lock\n*.tmp\n\n# PIN unlock, local to this machine\n*.pin\n\n# Backups\nbackups/\n\n# System files\n.DS_Store\nThumbs.db\n";
 
         fs::write(&gitignore_path, gitignore_content)?;
         Ok(())
@@ -81,8 +83,17 @@ impl GitSync {
     pub fn auto_commit(&self, message: &str) -> Result<()> {
         let mut index = self.repo.index()?;
 
-        // Add all changes to index
-        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        // Add all changes to index, including attachment blobs, except the
+        // vault lock, interrupted writes and the machine-local PIN file
+        let mut skip_transient = |path: &Path, _: &[u8]| -> i32 {
+            let name = path.to_string_lossy();
+            i32::from(name.ends_with(".lock") || name.ends_with(".tmp") || name.ends_with(".pin"))
+        };
+        index.add_all(
+            ["*"].iter(),
+            git2::IndexAddOption::DEFAULT,
+            Some(&mut skip_transient),
+        )?;
         index.write()?;
 
         // Check if there are changes to commit
@@ -122,6 +133,45 @@ impl GitSync {
         Ok(())
     }
 
+    /// Commits that changed the vault file, newest first
+    pub fn vault_revisions(&self) -> Result<Vec<VaultRevision>> {
+        if self.repo.head().is_err() {
+            return Ok(Vec::new());
+        }
+
+        let mut walk = self.repo.revwalk()?;
+        walk.push_head()?;
+        walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+
+        let mut revisions = Vec::new();
+        for oid in walk {
+            let commit = self.repo.find_commit(oid?)?;
+            let Some(blob) = vault_blob(&commit) else {
+                continue;
+            };
+            let parent_blob = commit.parent(0).ok().and_then(|p| vault_blob(&p));
+            if parent_blob == Some(blob) {
+                continue;
+            }
+
+            revisions.push(VaultRevision {
+                commit: commit.id().to_string(),
+                time: DateTime::from_timestamp(commit.time().seconds(), 0).unwrap_or_default(),
+                message: commit.summary().unwrap_or_default().to_string(),
+            });
+        }
+        Ok(revisions)
+    }
+
+    /// Contents of the vault file at `revision`, a commit hash or other git revision
+    pub fn vault_file_at(&self, revision: &str) -> Result<Vec<u8>> {
+        let commit = self.repo.revparse_single(revision)?.peel_to_commit()?;
+        let blob = vault_blob(&commit).ok_or_else(|| {
+            Error::Other(format!("Commit {} has no {}", revision, VAULT_FILE_NAME))
+        })?;
+        Ok(self.repo.find_blob(blob)?.content().to_vec())
+    }
+
     /// Push changes to remote
     pub fn push(&self) -> Result<()> {
         // Ensure we have at least one commit
@@ -165,7 +215,12 @@ impl GitSync {
     }
 
     /// Pull changes from remote
+    ///
+    /// Holds the vault lock so a checkout never races a vault write, and
+    /// backs up the vault before remote changes are applied.
     pub fn pull(&self) -> Result<()> {
+        let storage = VaultStorage::with_path(self.repo_path.join(VAULT_FILE_NAME));
+        let _lock = storage.lock()?;
         let mut remote = self.repo.find_remote(&self.remote_name)?;
 
         let mut callbacks = RemoteCallbacks::new();
@@ -193,6 +248,11 @@ impl GitSync {
             return Ok(());
         }
 
+        // Keep the local vault in case the remote version replaces it
+        if storage.exists() {
+            storage.backup_before("pull")?;
+        }
+
         if analysis.0.is_fast_forward() {
             // Fast-forward
             let mut reference = self.repo.find_reference("HEAD")?;
@@ -469,6 +529,30 @@ pub struct SmartSyncResult {
     pub pushed: bool,
     pub already_up_to_date: bool,
 }
+
+/// A commit that changed the vault file
+#[derive(Debug, Clone)]
+pub struct VaultRevision {
+    /// Full commit hash
+    pub commit: String,
+    pub time: DateTime<Utc>,
+    /// First line of the commit message
+    pub message: String,
+}
+
+impl VaultRevision {
+    /// Abbreviated commit hash, as shown by `git log --oneline`
+    pub fn short_commit(&self) -> &str {
+        &self.commit[..self.commit.len().min(7)]
+    }
+}
+
+/// Blob id of the vault file in `commit`
+fn vault_blob(commit: &git2::Commit) -> Option<git2::Oid> {
+    let tree = commit.tree().ok()?;
+    let entry = tree.get_path(Path::new(VAULT_FILE_NAME)).ok()?;
+    Some(entry.id())
+}
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -499,4 +583,45 @@ mod tests {
         let commit = head.peel_to_commit().unwrap();
         assert_eq!(commit.message().unwrap(), "Test commit");
     }
+
+    #[test]
+    fn test_auto_commit_includes_attachments() {
+        let temp_dir = tempdir().unwrap();
+        let sync = GitSync::init(temp_dir.path()).unwrap();
+
+        let store = crate::attachments::AttachmentStore::with_dir(
+            temp_dir.path().join(crate::attachments::ATTACHMENTS_DIR),
+        );
+        let attachment = store.add("codes.txt", b"recovery codes").unwrap();
+        sync.auto_commit("Add attachment").unwrap();
+
+        let tree = sync.repo.head().unwrap().peel_to_tree().unwrap();
+        let path = format!("attachments/{}.sfa", attachment.id);
+        assert!(tree.get_path(Path::new(&path)).is_ok());
+    }
+
+    #[test]
+    fn test_vault_revisions() {
+        let temp_dir = tempdir().unwrap();
+        let sync = GitSync::init(temp_dir.path()).unwrap();
+        assert!(sync.vault_revisions().unwrap().is_empty());
+
+        let vault_file = temp_dir.path().join(VAULT_FILE_NAME);
+        std::fs::write(&vault_file, "first").unwrap();
+        sync.auto_commit("First").unwrap();
+        std::fs::write(temp_dir.path().join("other.txt"), "x").unwrap();
+        sync.auto_commit("Unrelated").unwrap();
+        std::fs::write(&vault_file, "second").unwrap();
+        sync.auto_commit("Second").unwrap();
+
+        // Only commits that changed the vault are listed
+        let revisions = sync.vault_revisions().unwrap();
+        let messages: Vec<_> = revisions.iter().map(|r| r.message.as_str()).collect();
+        assert_eq!(messages, ["Second", "First"]);
+        assert_eq!(sync.vault_file_at(&revisions[1].commit).unwrap(), b"first");
+        assert_eq!(
+            sync.vault_file_at(revisions[0].short_commit()).unwrap(),
+            b"second"
+        );
+    }
 }
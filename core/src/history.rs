@@ -0,0 +1,209 @@
+//! Per-item change history from the git log of the vault
+//!
+//! Every revision of `vault.sf` committed by [`GitSync`] is decrypted with
+//! the current data key and items are compared by id. Revisions written
+//! before the vault was re-keyed, or in the legacy password-only format, do
+//! not open with the current key and are skipped.
+
+use crate::crypto::EncryptionKey;
+use crate::diff::{item_changes, FieldChange};
+use crate::errors::Result;
+use crate::git_sync::{GitSync, VaultRevision};
+use crate::models::{Item, Vault};
+use crate::storage::EncryptedVault;
+
+/// How an item changed in a revision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemChangeKind {
+    Created,
+    Modified,
+    Trashed,
+    Restored,
+    /// Removed from the vault for good
+    Deleted,
+}
+
+/// A change to an item in one revision
+#[derive(Debug, Clone)]
+pub struct ItemChange {
+    pub revision: VaultRevision,
+    pub kind: ItemChangeKind,
+    /// Fields that changed; empty for creation and deletion
+    pub fields: Vec<FieldChange>,
+}
+
+/// Changes to one item, newest first
+#[derive(Debug, Clone, Default)]
+pub struct ItemHistory {
+    pub changes: Vec<ItemChange>,
+    /// Revisions that could not be decrypted with the current key
+    pub skipped: usize,
+}
+
+/// Decrypt the vault as of `revision`
+pub fn vault_at(sync: &GitSync, key: &EncryptionKey, revision: &str) -> Result<Vault> {
+    let container = EncryptedVault::from_slice(&sync.vault_file_at(revision)?)?;
+    if container.is_legacy() {
+        return Err(crate::Error::KeyMismatch);
+    }
+    container.open(key)
+}
+
+/// The item with `item_id` as of `revision`, if it existed then
+pub fn item_at(
+    sync: &GitSync,
+    key: &EncryptionKey,
+    revision: &str,
+    item_id: &str,
+) -> Result<Option<Item>> {
+    let mut vault = vault_at(sync, key, revision)?;
+    Ok(vault.remove_item(item_id))
+}
+
+/// Walk the vault revisions and collect the changes to the item with `item_id`
+pub fn item_history(sync: &GitSync, key: &EncryptionKey, item_id: &str) -> Result<ItemHistory> {
+    let mut history = ItemHistory::default();
+    let mut previous: Option<Item> = None;
+
+    for revision in sync.vault_revisions()?.into_iter().rev() {
+        let current = match item_at(sync, key, &revision.commit, item_id) {
+            Ok(item) => item,
+            Err(e) => {
+                tracing::debug!(commit = %revision.commit, "Skipping revision: {}", e);
+                history.skipped += 1;
+                continue;
+            }
+        };
+
+        let change = match (&previous, &current) {
+            (None, None) => None,
+            (None, Some(_)) => Some((ItemChangeKind::Created, Vec::new())),
+            (Some(_), None) => Some((ItemChangeKind::Deleted, Vec::new())),
+            (Some(old), Some(new)) => {
+                // Moves to and from the trash are reported as the change kind
+                let mut fields = item_changes(old, new)?;
+                fields.retain(|f| f.field != "deletedDate");
+                let kind = match (old.is_deleted(), new.is_deleted()) {
+                    (false, true) => Some(ItemChangeKind::Trashed),
+                    (true, false) => Some(ItemChangeKind::Restored),
+                    _ if !fields.is_empty() => Some(ItemChangeKind::Modified),
+                    _ => None,
+                };
+                kind.map(|kind| (kind, fields))
+            }
+        };
+
+        if let Some((kind, fields)) = change {
+            history.changes.push(ItemChange {
+                revision,
+                kind,
+                fields,
+            });
+        }
+        previous = current;
+    }
+
+    history.changes.reverse();
+    Ok(history)
+}
+
+/// Put a past version of an item back into `vault`, leaving other items alone
+///
+/// The item is taken out of the trash, its password history is kept and
+/// the current password is added to it. The attachments of the current
+/// version are kept, as blobs of older versions may have been deleted; an
+/// item that no longer exists comes back without attachments.
+pub fn restore_item(vault: &mut Vault, mut past: Item) {
+    past.revision_date = chrono::Utc::now();
+    past.deleted_date = None;
+
+    match vault.get_item(&past.id) {
+        Some(current) => {
+            past.attachments = current.attachments.clone();
+            let id = past.id.clone();
+            vault.update_item(past);
+            vault.restore_item(&id);
+        }
+        None => {
+            past.attachments.clear();
+            vault.add_item(past);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::VaultStorage;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_item_history_and_restore() {
+        let temp_dir = tempdir().unwrap();
+        let storage = VaultStorage::with_path(temp_dir.path().join("vault.sf"));
+        let sync = GitSync::init(temp_dir.path()).unwrap();
+
+        let mut vault = Vault::new();
+        let mut item = Item::new_login("GitHub");
+        item.login.as_mut().unwrap().password = Some("first".into());
+        let id = item.id.clone();
+        vault.add_item(item.clone());
+        vault.add_item(Item::new_login("Other"));
+        storage.save(&vault, "password").unwrap();
+        sync.auto_commit("Add GitHub").unwrap();
+        let (mut vault, key) = storage.unlock("password").unwrap();
+
+        item.login.as_mut().unwrap().password = Some("second".into());
+        vault.update_item(item.clone());
+        storage.save_with_key(&vault, &key).unwrap();
+        sync.auto_commit("Change password").unwrap();
+
+        vault.trash_item(&id);
+        vault.get_item_mut(&id).unwrap().name = "Renamed".to_string();
+        storage.save_with_key(&vault, &key).unwrap();
+        sync.auto_commit("Trash").unwrap();
+
+        let history = item_history(&sync, &key, &id).unwrap();
+        let kinds: Vec<_> = history.changes.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ItemChangeKind::Trashed,
+                ItemChangeKind::Modified,
+                ItemChangeKind::Created
+            ]
+        );
+        assert_eq!(history.skipped, 0);
+        assert_eq!(history.changes[0].fields[0].field, "name");
+        let password = &history.changes[1].fields[0];
+        assert_eq!(password.field, "login.password");
+        assert!(password.secret);
+
+        // Restore the first version without touching the other item
+        let first = &history.changes[2].revision.commit;
+        let past = item_at(&sync, &key, first, &id).unwrap().unwrap();
+        let other_id = vault.items[1].id.clone();
+        vault.get_item_mut(&other_id).unwrap().name = "Other (edited)".into();
+        restore_item(&mut vault, past);
+
+        let restored = vault.get_item(&id).unwrap();
+        assert!(!restored.is_deleted());
+        assert_eq!(restored.name, "GitHub");
+        assert_eq!(
+            restored
+                .login
+                .as_ref()
+                .unwrap()
+                .password
+                .as_ref()
+                .unwrap()
+                .expose_secret(),
+            "first"
+        );
+        assert_eq!(
+            restored.password_history[0].password.expose_secret(),
+            "second"
+        );
+        assert_eq!(vault.get_item(&other_id).unwrap().name, "Other (edited)");
+    }
+}
@@ -0,0 +1,475 @@
+//! Integrity checks and repairs of vault contents
+//!
+//! Imports and hand-edited JSON can leave a vault that decrypts fine but
+//! holds inconsistent data. [`check`] reports such problems; [`repair`]
+//! applies the fixes that lose no data and leaves the rest for the user.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::models::{
+    CardData, IdentityData, Item, ItemType, LoginData, SecureNoteData, SecureNoteType, SshKeyData,
+    UriMatchType, Vault,
+};
+use crate::totp::parse_totp_secret;
+
+/// How serious an issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Harmless, but worth tidying up
+    Info,
+    /// Part of an item does not work, e.g. a TOTP secret or URI
+    Warning,
+    /// The vault is inconsistent, e.g. two items share an id
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Info => write!(f, "info"),
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A repair that loses no data
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fix {
+    /// Move the item out of a folder that does not exist
+    ClearFolder,
+    /// Give the item a new id
+    NewItemId,
+    /// Give the folder a new id
+    NewFolderId,
+    /// Change the item type to match the data it holds
+    SetType(ItemType),
+    /// Add empty data of the item's type
+    AddPayload,
+    /// Remove URIs that are empty
+    RemoveEmptyUris,
+    /// Recompute the public key and fingerprint from the private key
+    DerivePublicKey,
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ClearFolder => write!(f, "move the item out of the folder"),
+            Self::NewItemId => write!(f, "give the item a new id"),
+            Self::NewFolderId => write!(f, "give the folder a new id"),
+            Self::SetType(item_type) => write!(f, "make it a {} item", type_name(*item_type)),
+            Self::AddPayload => write!(f, "add empty data of its type"),
+            Self::RemoveEmptyUris => write!(f, "remove the empty URIs"),
+            Self::DerivePublicKey => write!(
+                f,
+                "recompute the public key and fingerprint from the private key"
+            ),
+        }
+    }
+}
+
+/// What an issue is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subject {
+    /// Index into `Vault::items`; ids may be duplicated, positions are not
+    Item(usize),
+    /// Index into `Vault::folders`
+    Folder(usize),
+}
+
+/// A problem found in a vault
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    pub subject: Subject,
+    /// Name of the item or folder
+    pub name: String,
+    pub message: String,
+    /// Safe repair, if there is one
+    pub fix: Option<Fix>,
+}
+
+/// Check the items and folders of `vault`
+pub fn check(vault: &Vault) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    let mut folder_ids = HashSet::new();
+    for (index, folder) in vault.folders.iter().enumerate() {
+        if !folder_ids.insert(folder.id.as_str()) {
+            issues.push(Issue {
+                severity: Severity::Error,
+                subject: Subject::Folder(index),
+                name: folder.name.clone(),
+                message: format!("Another folder has the id {}", folder.id),
+                fix: Some(Fix::NewFolderId),
+            });
+        }
+    }
+
+    let mut item_ids = HashSet::new();
+    for (index, item) in vault.items.iter().enumerate() {
+        let mut issue = |severity, message: String, fix| {
+            issues.push(Issue {
+                severity,
+                subject: Subject::Item(index),
+                name: item.name.clone(),
+                message,
+                fix,
+            })
+        };
+
+        if !item_ids.insert(item.id.as_str()) {
+            issue(
+                Severity::Error,
+                format!("Another item has the id {}", item.id),
+                Some(Fix::NewItemId),
+            );
+        }
+
+        if let Some(folder_id) = &item.folder_id {
+            if !folder_ids.contains(folder_id.as_str()) {
+                issue(
+                    Severity::Warning,
+                    format!("Folder {} does not exist", folder_id),
+                    Some(Fix::ClearFolder),
+                );
+            }
+        }
+
+        if let Some((severity, message, fix)) = check_payload(item) {
+            issue(severity, message, fix);
+        }
+
+        if let Some(login) = &item.login {
+            check_login(login, &mut issue);
+        }
+
+        if let Some(ssh_key) = &item.ssh_key {
+            check_ssh_key(ssh_key, &mut issue);
+        }
+    }
+
+    issues
+}
+
+/// Apply every safe fix, returning the issues that were repaired
+pub fn repair(vault: &mut Vault) -> Vec<Issue> {
+    let mut repaired = Vec::new();
+
+    for issue in check(vault) {
+        let Some(fix) = &issue.fix else {
+            continue;
+        };
+
+        match issue.subject {
+            Subject::Folder(index) => {
+                if *fix == Fix::NewFolderId {
+                    vault.folders[index].id = Uuid::new_v4().to_string();
+                }
+            }
+            Subject::Item(index) => {
+                let item = &mut vault.items[index];
+                match fix {
+                    Fix::ClearFolder => item.folder_id = None,
+                    Fix::NewItemId => item.id = Uuid::new_v4().to_string(),
+                    Fix::SetType(item_type) => item.item_type = *item_type,
+                    Fix::AddPayload => add_payload(item),
+                    Fix::RemoveEmptyUris => {
+                        if let Some(uris) = item.login.as_mut().and_then(|l| l.uris.as_mut()) {
+                            uris.retain(|u| !u.uri.trim().is_empty());
+                        }
+                    }
+                    Fix::DerivePublicKey => {
+                        if let Some(ssh_key) = item.ssh_key.as_mut() {
+                            if let Ok((public_key, fingerprint)) = ssh_key.derived_public_key() {
+                                ssh_key.public_key = public_key;
+                                ssh_key.key_fingerprint = fingerprint;
+                            }
+                        }
+                    }
+                    Fix::NewFolderId => {}
+                }
+                item.revision_date = Utc::now();
+            }
+        }
+        repaired.push(issue);
+    }
+
+    repaired
+}
+
+fn type_name(item_type: ItemType) -> &'static str {
+    match item_type {
+        ItemType::LOGIN => "login",
+        ItemType::SECURE_NOTE => "secure note",
+        ItemType::CARD => "card",
+        ItemType::IDENTITY => "identity",
+        ItemType::SSH_KEY => "SSH key",
+        _ => "unknown",
+    }
+}
+
+/// Types whose data the item holds
+fn payload_types(item: &Item) -> Vec<ItemType> {
+    [
+        (item.login.is_some(), ItemType::LOGIN),
+        (item.secure_note.is_some(), ItemType::SECURE_NOTE),
+        (item.card.is_some(), ItemType::CARD),
+        (item.identity.is_some(), ItemType::IDENTITY),
+        (item.ssh_key.is_some(), ItemType::SSH_KEY),
+    ]
+    .into_iter()
+    .filter_map(|(present, item_type)| present.then_some(item_type))
+    .collect()
+}
+
+fn check_payload(item: &Item) -> Option<(Severity, String, Option<Fix>)> {
+    let name = type_name(item.item_type);
+    if name == "unknown" {
+        return Some((
+            Severity::Error,
+            format!("Unknown item type {}", item.item_type.0),
+            None,
+        ));
+    }
+
+    let present = payload_types(item);
+    let others: Vec<_> = present
+        .iter()
+        .filter(|t| **t != item.item_type)
+        .map(|t| type_name(*t))
+        .collect();
+    let has_own = present.contains(&item.item_type);
+
+    match (has_own, present.as_slice()) {
+        (true, _) if others.is_empty() => None,
+        // Dropping the other data could lose secrets; leave it to the user
+        (true, _) => Some((
+            Severity::Error,
+            format!("{} item also holds {} data", name, others.join(" and ")),
+            None,
+        )),
+        // There is no such thing as an empty key
+        (false, []) if item.item_type == ItemType::SSH_KEY => {
+            Some((Severity::Error, "SSH key item has no key".to_string(), None))
+        }
+        (false, []) => Some((
+            Severity::Warning,
+            format!("{} item has no {} data", name, name),
+            Some(Fix::AddPayload),
+        )),
+        (false, [only]) => Some((
+            Severity::Error,
+            format!("{} item holds {} data instead", name, type_name(*only)),
+            Some(Fix::SetType(*only)),
+        )),
+        (false, _) => Some((
+            Severity::Error,
+            format!("{} item holds {} data instead", name, others.join(" and ")),
+            None,
+        )),
+    }
+}
+
+fn check_login(login: &LoginData, issue: &mut impl FnMut(Severity, String, Option<Fix>)) {
+    if let Some(totp) = login.totp.as_ref().filter(|t| !t.is_empty()) {
+        if parse_totp_secret(totp.expose_secret()).is_err() {
+            issue(
+                Severity::Warning,
+                "TOTP secret is not valid base32 or an otpauth:// URI".to_string(),
+                None,
+            );
+        }
+    }
+
+    let uris = login.uris.as_deref().unwrap_or_default();
+    if uris.iter().any(|u| u.uri.trim().is_empty()) {
+        issue(
+            Severity::Info,
+            "Login has empty URIs".to_string(),
+            Some(Fix::RemoveEmptyUris),
+        );
+    }
+    for uri in uris.iter().filter(|u| !u.uri.trim().is_empty()) {
+        let valid = if uri.match_type.map(|m| m.0) == Some(UriMatchType::REGEX.0) {
+            regex::Regex::new(&uri.uri).is_ok()
+        } else {
+            is_valid_uri(&uri.uri)
+        };
+        if !valid {
+            issue(Severity::Warning, format!("Invalid URI: {}", uri.uri), None);
+        }
+    }
+}
+
+fn check_ssh_key(ssh_key: &SshKeyData, issue: &mut impl FnMut(Severity, String, Option<Fix>)) {
+    match ssh_key.derived_public_key() {
+        Err(_) => issue(
+            Severity::Error,
+            "SSH private key is not a valid unencrypted OpenSSH key".to_string(),
+            None,
+        ),
+        Ok((public_key, fingerprint)) => {
+            if public_key != ssh_key.public_key || fingerprint != ssh_key.key_fingerprint {
+                issue(
+                    Severity::Warning,
+                    "SSH public key or fingerprint does not match the private key".to_string(),
+                    Some(Fix::DerivePublicKey),
+                );
+            }
+        }
+    }
+}
+
+/// Whether `uri` parses as a URL, allowing bare host names as Bitwarden does
+fn is_valid_uri(uri: &str) -> bool {
+    match url::Url::parse(uri.trim()) {
+        Ok(_) => true,
+        Err(url::ParseError::RelativeUrlWithoutBase) => {
+            url::Url::parse(&format!("https://{}", uri.trim())).is_ok_and(|u| u.has_host())
+        }
+        Err(_) => false,
+    }
+}
+
+fn add_payload(item: &mut Item) {
+    match item.item_type {
+        ItemType::LOGIN => item.login = Some(LoginData::default()),
+        ItemType::SECURE_NOTE => {
+            item.secure_note = Some(SecureNoteData {
+                type_: SecureNoteType::GENERIC,
+            })
+        }
+        ItemType::CARD => item.card = Some(CardData::default()),
+        ItemType::IDENTITY => item.identity = Some(IdentityData::default()),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Folder, LoginUri};
+
+    fn uri(uri: &str) -> LoginUri {
+        LoginUri {
+            uri: uri.to_string(),
+            match_type: None,
+        }
+    }
+
+    #[test]
+    fn test_check_and_repair() {
+        let mut vault = Vault::new();
+        let folder = Folder::new("Work");
+        vault.folders.push(folder.clone());
+        vault.folders.push(folder.clone());
+
+        let mut login = Item::new_login("GitHub");
+        login.folder_id = Some("missing".to_string());
+        login.login.as_mut().unwrap().totp = Some("not base32!".into());
+        login.login.as_mut().unwrap().uris = Some(vec![
+            uri("github.com"),
+            uri("https://github.com/login"),
+            uri(""),
+            uri("http://[broken"),
+        ]);
+        vault.add_item(login.clone());
+        vault.add_item(login.clone());
+
+        // A card holding login data only
+        let mut card = Item::new_login("Visa");
+        card.item_type = ItemType::CARD;
+        vault.add_item(card);
+
+        // A card holding card and login data
+        let mut mixed = Item::new_login("Mixed");
+        mixed.item_type = ItemType::CARD;
+        mixed.card = Some(CardData::default());
+        vault.add_item(mixed);
+
+        let mut empty = Item::new_secure_note("Empty");
+        empty.secure_note = None;
+        vault.add_item(empty);
+
+        let issues = check(&vault);
+        let messages: Vec<_> = issues.iter().map(|i| i.message.as_str()).collect();
+        assert!(messages.contains(&"card item holds login data instead"));
+        assert!(messages.contains(&"card item also holds login data"));
+        assert!(messages.contains(&"secure note item has no secure note data"));
+        assert!(messages.contains(&"Invalid URI: http://[broken"));
+        assert!(!messages.iter().any(|m| m.contains("github.com")));
+        assert_eq!(
+            issues
+                .iter()
+                .filter(|i| i.severity == Severity::Error && i.fix.is_some())
+                .count(),
+            3
+        );
+
+        let repaired = repair(&mut vault);
+        assert_eq!(
+            repaired.len(),
+            issues.iter().filter(|i| i.fix.is_some()).count()
+        );
+        assert_ne!(vault.folders[0].id, vault.folders[1].id);
+        assert_ne!(vault.items[0].id, vault.items[1].id);
+        assert_eq!(vault.items[0].folder_id, None);
+        assert_eq!(
+            vault.items[0]
+                .login
+                .as_ref()
+                .unwrap()
+                .uris
+                .as_ref()
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(vault.items[2].item_type, ItemType::LOGIN);
+        assert!(vault.items[4].secure_note.is_some());
+
+        // What is left needs the user
+        let remaining = check(&vault);
+        assert!(remaining.iter().all(|i| i.fix.is_none()));
+        assert_eq!(remaining.len(), 5);
+    }
+
+    #[test]
+    fn test_check_ssh_keys() {
+        let mut vault = Vault::new();
+        let mut key = SshKeyData::generate(crate::ssh_keys::KeyAlgorithm::Ed25519, "ci").unwrap();
+        let (public_key, fingerprint) = (key.public_key.clone(), key.key_fingerprint.clone());
+        key.key_fingerprint = "SHA256:stale".to_string();
+        vault.add_item(Item::new_ssh_key("Deploy", key));
+
+        let mut broken = Item::new_ssh_key("Broken", SshKeyData::default());
+        broken.ssh_key.as_mut().unwrap().private_key = "not a key".into();
+        vault.add_item(broken);
+
+        let mut empty = Item::new_ssh_key("Empty", SshKeyData::default());
+        empty.ssh_key = None;
+        vault.add_item(empty);
+
+        let issues = check(&vault);
+        let messages: Vec<_> = issues.iter().map(|i| i.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "SSH public key or fingerprint does not match the private key",
+                "SSH private key is not a valid unencrypted OpenSSH key",
+                "SSH key item has no key",
+            ]
+        );
+
+        assert_eq!(repair(&mut vault).len(), 1);
+        let ssh_key = vault.items[0].ssh_key.as_ref().unwrap();
+        assert_eq!(ssh_key.public_key, public_key);
+        assert_eq!(ssh_key.key_fingerprint, fingerprint);
+    }
+}
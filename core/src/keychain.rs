@@ -1,8 +1,11 @@
 //! System keychain integration
 
+use std::path::Path;
+
 use keyring::Entry;
 use zeroize::Zeroizing;
 
+use crate::config::default_vault_dir;
 use crate::errors::{Error, Result};
 
 const SERVICE_NAME: &str = "SecureFox";
@@ -11,26 +14,38 @@ const MASTER_KEY_NAME: &str = "master_key";
 /// Keychain manager for secure credential storage
 pub struct Keychain {
     service: String,
+    master_key_name: String,
 }
 
 impl Keychain {
     /// Create a new keychain instance
     pub fn new() -> Self {
-        Self {
-            service: SERVICE_NAME.to_string(),
-        }
+        Self::with_service(SERVICE_NAME)
     }
 
     /// Create a keychain with custom service name
     pub fn with_service(service: impl Into<String>) -> Self {
         Self {
             service: service.into(),
+            master_key_name: MASTER_KEY_NAME.to_string(),
+        }
+    }
+
+    /// Keychain holding the master key of the vault in `vault_dir`
+    ///
+    /// The default vault keeps the original entry, so keys remembered
+    /// before named vaults existed still work.
+    pub fn for_vault(vault_dir: &Path) -> Self {
+        let mut keychain = Self::new();
+        if default_vault_dir().ok().as_deref() != Some(vault_dir) {
+            keychain.master_key_name = format!("{}:{}", MASTER_KEY_NAME, vault_dir.display());
         }
+        keychain
     }
 
     /// Store the master key in the system keychain
     pub fn store_master_key(&self, key: &[u8]) -> Result<()> {
-        let entry = Entry::new(&self.service, MASTER_KEY_NAME)
+        let entry = Entry::new(&self.service, &self.master_key_name)
             .map_err(|e| Error::Keychain(format!("Failed to create entry: {}", e)))?;
 
         // Convert key to base64 for storage
@@ -46,7 +61,7 @@ impl Keychain {
 
     /// Retrieve the master key from the system keychain
     pub fn get_master_key(&self) -> Result<Zeroizing<Vec<u8>>> {
-        let entry = Entry::new(&self.service, MASTER_KEY_NAME)
+        let entry = Entry::new(&self.service, &self.master_key_name)
             .map_err(|e| Error::Keychain(format!("Failed to create entry: {}", e)))?;
 
         let encoded = entry
@@ -64,7 +79,7 @@ impl Keychain {
 
     /// Delete the master key from the system keychain
     pub fn delete_master_key(&self) -> Result<()> {
-        let entry = Entry::new(&self.service, MASTER_KEY_NAME)
+        let entry = Entry::new(&self.service, &self.master_key_name)
             .map_err(|e| Error::Keychain(format!("Failed to create entry: {}", e)))?;
 
         entry
@@ -76,7 +91,7 @@ impl Keychain {
 
     /// Check if master key exists in keychain
     pub fn has_master_key(&self) -> bool {
-        if let Ok(entry) = Entry::new(&self.service, MASTER_KEY_NAME) {
+        if let Ok(entry) = Entry::new(&self.service, &self.master_key_name) {
             entry.get_password().is_ok()
         } else {
             false
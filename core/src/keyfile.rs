@@ -0,0 +1,286 @@
+//! Keyfiles used as a second unlock factor
+//!
+//! Key material is read with the same rules as KeePass so existing keyfiles
+//! can be reused: XML keyfiles (version 1.0 and 2.0), 32 raw bytes, 64 hex
+//! characters, or the SHA-256 of any other file. Generated keyfiles use the
+//! KeePass XML version 2.0 format.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::errors::{Error, Result};
+
+/// Size of keyfile key material in bytes
+const KEYFILE_KEY_SIZE: usize = 32;
+
+/// Key material of a keyfile
+pub struct Keyfile {
+    key: Zeroizing<[u8; KEYFILE_KEY_SIZE]>,
+}
+
+impl Keyfile {
+    /// Generate a keyfile with random key material
+    pub fn generate() -> Self {
+        let mut key = Zeroizing::new([0u8; KEYFILE_KEY_SIZE]);
+        OsRng.fill_bytes(key.as_mut());
+        Self { key }
+    }
+
+    /// Read a keyfile from disk
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(Error::Keyfile(format!(
+                "keyfile not found at {}",
+                path.display()
+            )));
+        }
+
+        let contents = Zeroizing::new(fs::read(path)?);
+        Self::from_bytes(&contents)
+    }
+
+    /// Derive key material from keyfile contents
+    pub fn from_bytes(contents: &[u8]) -> Result<Self> {
+        if let Some(key) = parse_xml(contents)? {
+            return Ok(Self { key });
+        }
+
+        let mut key = Zeroizing::new([0u8; KEYFILE_KEY_SIZE]);
+        if contents.len() == KEYFILE_KEY_SIZE {
+            key.copy_from_slice(contents);
+        } else if contents.len() == KEYFILE_KEY_SIZE * 2
+            && hex::decode_to_slice(contents, key.as_mut()).is_ok()
+        {
+            // 64 hex characters
+        } else {
+            key.copy_from_slice(&Sha256::digest(contents));
+        }
+
+        Ok(Self { key })
+    }
+
+    /// Key material mixed into key derivation
+    pub fn key(&self) -> &[u8] {
+        self.key.as_ref()
+    }
+
+    /// Short identifier of the key material, the `Hash` of KeePass XML v2 keyfiles
+    pub fn fingerprint(&self) -> String {
+        hex::encode_upper(&Sha256::digest(self.key.as_ref())[..4])
+    }
+
+    /// Serialize as a KeePass XML version 2.0 keyfile
+    pub fn to_xml(&self) -> String {
+        let data = hex::encode_upper(self.key.as_ref());
+        let groups: Vec<&str> = (0..data.len())
+            .step_by(8)
+            .map(|i| &data[i..i + 8])
+            .collect();
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <KeyFile>\n\
+             \t<Meta>\n\
+             \t\t<Version>2.0</Version>\n\
+             \t</Meta>\n\
+             \t<Key>\n\
+             \t\t<Data Hash=\"{}\">\n\
+             \t\t\t{}\n\
+             \t\t\t{}\n\
+             \t\t</Data>\n\
+             \t</Key>\n\
+             </KeyFile>\n",
+            self.fingerprint(),
+            groups[..4].join(" "),
+            groups[4..].join(" ")
+        )
+    }
+
+    /// Write the keyfile as KeePass XML, refusing to overwrite an existing file
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options.open(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => {
+                Error::Keyfile(format!("{} already exists", path.display()))
+            }
+            _ => Error::Io(e),
+        })?;
+        let xml = Zeroizing::new(self.to_xml());
+        file.write_all(xml.as_bytes())?;
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+/// Parse a KeePass XML keyfile, returning `None` for non-XML contents
+fn parse_xml(contents: &[u8]) -> Result<Option<Zeroizing<[u8; KEYFILE_KEY_SIZE]>>> {
+    let Ok(text) = std::str::from_utf8(contents) else {
+        return Ok(None);
+    };
+    let Ok(document) = roxmltree::Document::parse(text) else {
+        return Ok(None);
+    };
+
+    let root = document.root_element();
+    if !root.has_tag_name("KeyFile") {
+        return Ok(None);
+    }
+
+    let version = child(child(root, "Meta")?, "Version")?
+        .text()
+        .unwrap_or_default()
+        .trim();
+    let data = child(child(root, "Key")?, "Data")?;
+    let encoded: Zeroizing<String> = Zeroizing::new(
+        data.text()
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect(),
+    );
+
+    let mut key = Zeroizing::new([0u8; KEYFILE_KEY_SIZE]);
+    match version {
+        "1.0" | "1.00" => {
+            let decoded = Zeroizing::new(
+                BASE64
+                    .decode(encoded.as_bytes())
+                    .map_err(|e| Error::Keyfile(format!("invalid key data: {}", e)))?,
+            );
+            if decoded.len() != KEYFILE_KEY_SIZE {
+                return Err(Error::Keyfile("key data must be 32 bytes".to_string()));
+            }
+            key.copy_from_slice(&decoded);
+        }
+        "2.0" => {
+            hex::decode_to_slice(encoded.as_bytes(), key.as_mut())
+                .map_err(|e| Error::Keyfile(format!("invalid key data: {}", e)))?;
+            if let Some(expected) = data.attribute("Hash") {
+                let actual = hex::encode_upper(&Sha256::digest(key.as_ref())[..4]);
+                if !actual.eq_ignore_ascii_case(expected) {
+                    return Err(Error::Keyfile(
+                        "key data does not match its checksum".to_string(),
+                    ));
+                }
+            }
+        }
+        other => {
+            return Err(Error::Keyfile(format!(
+                "unsupported XML keyfile version {}",
+                other
+            )))
+        }
+    }
+
+    Ok(Some(key))
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Result<roxmltree::Node<'a, 'input>> {
+    node.children()
+        .find(|n| n.has_tag_name(name))
+        .ok_or_else(|| Error::Keyfile(format!("XML keyfile has no <{}> element", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keyfile in the layout KeePass 2.x writes
+    const KEEPASS_V2: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<KeyFile>
+	<Meta>
+		<Version>2.0</Version>
+	</Meta>
+	<Key>
+		<Data Hash="28A6EB16">
+			6B4F9A33 24E0A1D8 21F6D8D7 1C5B3A41
+			0B5E2A64 05E2D1F9 3D4C8E45 E24B2A63
+		</Data>
+	</Key>
+</KeyFile>
+"#;
+
+    #[test]
+    fn test_generated_keyfile_round_trips() {
+        let keyfile = Keyfile::generate();
+        let parsed = Keyfile::from_bytes(keyfile.to_xml().as_bytes()).unwrap();
+
+        assert_eq!(parsed.key(), keyfile.key());
+        assert_eq!(parsed.fingerprint(), keyfile.fingerprint());
+    }
+
+    #[test]
+    fn test_keepass_v2_keyfile() {
+        let keyfile = Keyfile::from_bytes(KEEPASS_V2.as_bytes()).unwrap();
+        assert_eq!(
+            hex::encode_upper(keyfile.key()),
+            "6B4F9A3324E0A1D821F6D8D71C5B3A410B5E2A6405E2D1F93D4C8E45E24B2A63"
+        );
+
+        let tampered = KEEPASS_V2.replace("6B4F9A33", "6B4F9A34");
+        assert!(matches!(
+            Keyfile::from_bytes(tampered.as_bytes()),
+            Err(Error::Keyfile(_))
+        ));
+    }
+
+    #[test]
+    fn test_keepass_v1_and_plain_keyfiles() {
+        let key = [7u8; KEYFILE_KEY_SIZE];
+        let v1 = format!(
+            "<KeyFile><Meta><Version>1.00</Version></Meta><Key><Data>{}</Data></Key></KeyFile>",
+            BASE64.encode(key)
+        );
+        assert_eq!(Keyfile::from_bytes(v1.as_bytes()).unwrap().key(), key);
+
+        assert_eq!(Keyfile::from_bytes(&key).unwrap().key(), key);
+        assert_eq!(
+            Keyfile::from_bytes(hex::encode(key).as_bytes())
+                .unwrap()
+                .key(),
+            key
+        );
+
+        let arbitrary = b"any file can be a keyfile";
+        assert_eq!(
+            Keyfile::from_bytes(arbitrary).unwrap().key(),
+            Sha256::digest(arbitrary).as_slice()
+        );
+    }
+
+    #[test]
+    fn test_missing_keyfile_is_reported() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("vault.keyx");
+
+        let err = Keyfile::load(&path).err().unwrap();
+        assert!(err.to_string().contains("not found"));
+
+        let keyfile = Keyfile::generate();
+        keyfile.write(&path).unwrap();
+        assert!(keyfile.write(&path).is_err());
+        assert_eq!(Keyfile::load(&path).unwrap().key(), keyfile.key());
+    }
+}
@@ -0,0 +1,413 @@
+//! Keyslots wrapping the vault data key
+//!
+//! The vault payload is encrypted with a random data key. Each keyslot holds
+//! a copy of that key encrypted under a key derived from one unlock secret,
+//! so unlock methods can be added or revoked without touching the payload.
+//!
+//! Slots written since format 3.0.0 carry the id of their vault, and the
+//! vault id, slot id, kind and KDF parameters are authenticated as associated
+//! data of the wrapped key, so none of them can be altered or transplanted.
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+use crate::crypto::{
+    composite_secret, decrypt_with_aad, derive_key_from_secret, encrypt_with_aad, key_check,
+    matches_key_check, EncryptedData, EncryptionKey, KdfParams,
+};
+use crate::errors::{Error, Result};
+use crate::keyfile::Keyfile;
+use crate::ssh_agent::{SshAgent, SshIdentity};
+
+/// Unlock method protecting a keyslot
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum KeyslotKind {
+    /// Master password
+    Password,
+    /// Master password combined with a keyfile
+    PasswordKeyfile,
+    /// Recovery key split into Shamir shares
+    Recovery,
+    /// Quick unlock PIN, kept on this machine only
+    Pin,
+    /// Signature by an Ed25519 key held in ssh-agent
+    SshAgent,
+}
+
+impl fmt::Display for KeyslotKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyslotKind::Password => write!(f, "password"),
+            KeyslotKind::PasswordKeyfile => write!(f, "password+keyfile"),
+            KeyslotKind::Recovery => write!(f, "recovery"),
+            KeyslotKind::Pin => write!(f, "pin"),
+            KeyslotKind::SshAgent => write!(f, "ssh-agent"),
+        }
+    }
+}
+
+/// Secrets presented to unlock a vault: the master password and an optional keyfile
+#[derive(Clone, Copy)]
+pub struct Credentials<'a> {
+    pub password: &'a str,
+    pub keyfile: Option<&'a Keyfile>,
+    /// Allow the legacy Argon2id cost for old password-encrypted containers
+    pub legacy_argon2: bool,
+}
+
+impl<'a> Credentials<'a> {
+    pub fn new(password: &'a str, keyfile: Option<&'a Keyfile>) -> Self {
+        Self {
+            password,
+            keyfile,
+            legacy_argon2: false,
+        }
+    }
+
+    /// Also try the legacy Argon2id cost, which needs about 1024 times the memory
+    pub fn with_legacy_argon2(mut self, enabled: bool) -> Self {
+        self.legacy_argon2 = enabled;
+        self
+    }
+
+    /// Kind of keyslot these credentials open
+    pub fn kind(&self) -> KeyslotKind {
+        match self.keyfile {
+            Some(_) => KeyslotKind::PasswordKeyfile,
+            None => KeyslotKind::Password,
+        }
+    }
+
+    /// Input to key derivation
+    fn secret(&self) -> Zeroizing<Vec<u8>> {
+        match self.keyfile {
+            Some(keyfile) => {
+                Zeroizing::new(composite_secret(self.password.as_bytes(), keyfile.key()).to_vec())
+            }
+            None => Zeroizing::new(self.password.as_bytes().to_vec()),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Credentials<'a> {
+    fn from(password: &'a str) -> Self {
+        Self::new(password, None)
+    }
+}
+
+impl<'a> From<&'a String> for Credentials<'a> {
+    fn from(password: &'a String) -> Self {
+        Self::new(password, None)
+    }
+}
+
+/// A copy of the vault data key wrapped by one unlock secret
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Keyslot {
+    pub id: String,
+    pub kind: KeyslotKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// Fingerprint of the keyfile a `PasswordKeyfile` slot needs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyfile: Option<String>,
+    /// Fingerprint of the SSH key an `SshAgent` slot needs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_key: Option<String>,
+    /// Vault this slot is bound to; absent on slots written before format 3.0.0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault_id: Option<String>,
+    /// Key check value of the wrapping key, used to detect a wrong secret
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_check: Option<String>,
+    pub wrapped_key: EncryptedData,
+}
+
+impl Keyslot {
+    /// Wrap the vault key with a key derived from `secret`, bound to `vault_id`
+    pub fn new(
+        kind: KeyslotKind,
+        secret: &[u8],
+        kdf_params: KdfParams,
+        vault_key: &EncryptionKey,
+        vault_id: &str,
+    ) -> Result<Self> {
+        Self::with_id(
+            Uuid::new_v4().to_string(),
+            kind,
+            secret,
+            kdf_params,
+            vault_key,
+            vault_id,
+        )
+    }
+
+    fn with_id(
+        id: String,
+        kind: KeyslotKind,
+        secret: &[u8],
+        kdf_params: KdfParams,
+        vault_key: &EncryptionKey,
+        vault_id: &str,
+    ) -> Result<Self> {
+        let aad = associated_data(vault_id, &id, kind, &kdf_params)?;
+
+        let wrapping_key = derive_key_from_secret(secret, &kdf_params)?;
+        let mut wrapped_key = encrypt_with_aad(vault_key.as_bytes(), &wrapping_key, &aad)?;
+        wrapped_key.kdf_params = Some(kdf_params);
+
+        Ok(Self {
+            id,
+            kind,
+            label: None,
+            created_at: Utc::now(),
+            keyfile: None,
+            ssh_key: None,
+            vault_id: Some(vault_id.to_string()),
+            key_check: Some(key_check(&wrapping_key)),
+            wrapped_key,
+        })
+    }
+
+    /// Create a master password keyslot
+    pub fn password(
+        password: &str,
+        kdf_params: KdfParams,
+        vault_key: &EncryptionKey,
+        vault_id: &str,
+    ) -> Result<Self> {
+        Self::new(
+            KeyslotKind::Password,
+            password.as_bytes(),
+            kdf_params,
+            vault_key,
+            vault_id,
+        )
+    }
+
+    /// Create a keyslot opened by a recovery key
+    pub fn recovery(
+        recovery_key: &EncryptionKey,
+        vault_key: &EncryptionKey,
+        vault_id: &str,
+    ) -> Result<Self> {
+        Self::new(
+            KeyslotKind::Recovery,
+            recovery_key.as_bytes(),
+            KdfParams::default(),
+            vault_key,
+            vault_id,
+        )
+    }
+
+    /// Create a keyslot opened by an Ed25519 key held in ssh-agent
+    ///
+    /// The agent signs a challenge naming the vault and slot; Ed25519
+    /// signatures are deterministic, so the same signature opens the slot later.
+    pub fn ssh_agent(
+        agent: &mut SshAgent,
+        identity: &SshIdentity,
+        vault_key: &EncryptionKey,
+        vault_id: &str,
+    ) -> Result<Self> {
+        let id = Uuid::new_v4().to_string();
+        let signature = agent.sign(identity, &ssh_challenge(vault_id, &id))?;
+
+        let mut slot = Self::with_id(
+            id,
+            KeyslotKind::SshAgent,
+            &signature,
+            KdfParams::default(),
+            vault_key,
+            vault_id,
+        )?;
+        slot.ssh_key = Some(identity.fingerprint());
+        slot.label = Some(identity.comment.clone()).filter(|c| !c.is_empty());
+        Ok(slot)
+    }
+
+    /// Recover the vault key by having the agent sign this slot's challenge
+    pub fn unlock_with_agent(
+        &self,
+        agent: &mut SshAgent,
+        identity: &SshIdentity,
+    ) -> Result<EncryptionKey> {
+        let vault_id = self
+            .vault_id
+            .as_deref()
+            .ok_or_else(|| Error::Decryption("SSH keyslot is not bound to a vault".to_string()))?;
+        let signature = agent.sign(identity, &ssh_challenge(vault_id, &self.id))?;
+        self.unwrap_key(&signature)
+    }
+
+    /// Create a keyslot opened by `credentials`, with a keyfile if they include one
+    pub fn from_credentials(
+        credentials: Credentials<'_>,
+        kdf_params: KdfParams,
+        vault_key: &EncryptionKey,
+        vault_id: &str,
+    ) -> Result<Self> {
+        let mut slot = Self::new(
+            credentials.kind(),
+            &credentials.secret(),
+            kdf_params,
+            vault_key,
+            vault_id,
+        )?;
+        slot.keyfile = credentials.keyfile.map(Keyfile::fingerprint);
+        Ok(slot)
+    }
+
+    /// Attach a human readable label
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// KDF parameters used to derive the wrapping key
+    pub fn kdf_params(&self) -> Option<&KdfParams> {
+        self.wrapped_key.kdf_params.as_ref()
+    }
+
+    /// Recover the vault key with the credentials this slot was created from
+    ///
+    /// A keyfile whose fingerprint differs from the one recorded in the slot
+    /// is reported as `Error::Keyfile` without running key derivation.
+    pub fn unlock(&self, credentials: Credentials<'_>) -> Result<EncryptionKey> {
+        if let (Some(expected), Some(keyfile)) = (&self.keyfile, credentials.keyfile) {
+            if expected != &keyfile.fingerprint() {
+                return Err(Error::Keyfile(format!(
+                    "wrong keyfile (this vault expects {})",
+                    expected
+                )));
+            }
+        }
+        self.unwrap_key(&credentials.secret())
+    }
+
+    /// Recover the vault key using `secret`
+    ///
+    /// Returns `Error::InvalidPassword` for a wrong secret and
+    /// `Error::Decryption` when the secret is right but the slot is damaged.
+    pub fn unwrap_key(&self, secret: &[u8]) -> Result<EncryptionKey> {
+        let kdf_params = self
+            .kdf_params()
+            .ok_or_else(|| Error::Decryption("Keyslot has no KDF parameters".to_string()))?;
+        let aad = match &self.vault_id {
+            Some(vault_id) => associated_data(vault_id, &self.id, self.kind, kdf_params)?,
+            None => Vec::new(),
+        };
+        let wrapping_key = derive_key_from_secret(secret, kdf_params)?;
+
+        let key_bytes = match &self.key_check {
+            Some(check) if !matches_key_check(&wrapping_key, check) => {
+                return Err(Error::InvalidPassword)
+            }
+            Some(_) => decrypt_with_aad(&self.wrapped_key, &wrapping_key, &aad)?,
+            // Without a check value a failed decryption most likely means a wrong secret
+            None => decrypt_with_aad(&self.wrapped_key, &wrapping_key, &aad)
+                .map_err(|_| Error::InvalidPassword)?,
+        };
+
+        EncryptionKey::from_bytes(&Zeroizing::new(key_bytes))
+    }
+}
+
+/// Data signed by the SSH key of an `SshAgent` slot
+fn ssh_challenge(vault_id: &str, slot_id: &str) -> Vec<u8> {
+    format!("securefox ssh-agent keyslot|{}|{}", vault_id, slot_id).into_bytes()
+}
+
+/// Associated data authenticated with a bound keyslot's wrapped key
+fn associated_data(
+    vault_id: &str,
+    slot_id: &str,
+    kind: KeyslotKind,
+    kdf_params: &KdfParams,
+) -> Result<Vec<u8>> {
+    let kdf = serde_json::to_string(kdf_params)?;
+    Ok(format!(
+        "securefox keyslot|{}|{}|{}|{}",
+        vault_id, slot_id, kind, kdf
+    )
+    .into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::generate_key;
+
+    const VAULT_ID: &str = "00000000-0000-0000-0000-000000000001";
+
+    #[test]
+    fn test_wrap_and_unwrap() {
+        let vault_key = generate_key();
+        let slot =
+            Keyslot::password("hunter2", KdfParams::default(), &vault_key, VAULT_ID).unwrap();
+
+        let unwrapped = slot.unwrap_key(b"hunter2").unwrap();
+        assert_eq!(unwrapped.as_bytes(), vault_key.as_bytes());
+        assert!(matches!(
+            slot.unwrap_key(b"hunter3"),
+            Err(Error::InvalidPassword)
+        ));
+    }
+
+    #[test]
+    fn test_damaged_slot_is_not_a_wrong_password() {
+        let vault_key = generate_key();
+        let mut slot =
+            Keyslot::password("hunter2", KdfParams::default(), &vault_key, VAULT_ID).unwrap();
+        slot.wrapped_key.ciphertext = slot.wrapped_key.nonce.clone();
+
+        assert!(matches!(
+            slot.unwrap_key(b"hunter2"),
+            Err(Error::Decryption(_))
+        ));
+    }
+
+    #[test]
+    fn test_slots_are_independent() {
+        let vault_key = generate_key();
+        let first = Keyslot::password("first", KdfParams::default(), &vault_key, VAULT_ID).unwrap();
+        let second =
+            Keyslot::password("second", KdfParams::default(), &vault_key, VAULT_ID).unwrap();
+
+        assert_ne!(first.id, second.id);
+        assert!(first.unwrap_key(b"second").is_err());
+        assert_eq!(
+            second.unwrap_key(b"second").unwrap().as_bytes(),
+            vault_key.as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_slot_metadata_is_authenticated() {
+        let vault_key = generate_key();
+        let slot =
+            Keyslot::password("hunter2", KdfParams::default(), &vault_key, VAULT_ID).unwrap();
+
+        let mut moved = slot.clone();
+        moved.vault_id = Some("another-vault".to_string());
+        assert!(matches!(
+            moved.unwrap_key(b"hunter2"),
+            Err(Error::Decryption(_))
+        ));
+
+        let mut unbound = slot.clone();
+        unbound.vault_id = None;
+        assert!(unbound.unwrap_key(b"hunter2").is_err());
+
+        let mut relabelled = slot;
+        relabelled.label = Some("labels are not bound".to_string());
+        assert!(relabelled.unwrap_key(b"hunter2").is_ok());
+    }
+}
@@ -1,15 +1,34 @@
 //! SecureFox Core - Password management library
 
+pub mod attachments;
+pub mod backup;
 pub mod config;
 pub mod crypto;
+pub mod diff;
 pub mod errors;
+pub mod folders;
+pub mod integrity;
+pub mod keyfile;
+pub mod keyslot;
+pub mod logging;
+pub mod memory;
+pub mod migration;
 pub mod models;
+pub mod pin;
+pub mod recovery;
+pub mod snapshot;
+pub mod ssh_agent;
+pub mod ssh_keys;
 pub mod storage;
+pub mod tags;
 pub mod totp;
 
 #[cfg(feature = "git")]
 pub mod git_sync;
 
+#[cfg(feature = "git")]
+pub mod history;
+
 #[cfg(feature = "keychain")]
 pub mod keychain;
 
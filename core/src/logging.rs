@@ -0,0 +1,209 @@
+//! Secret-safe logging
+//!
+//! Log output is passed through [`redact`] before it is written, so a
+//! secret that slips into an event does not end up in `service.log`.
+//!
+//! Field conventions: never pass a vault secret to `tracing` on purpose.
+//! Values that may be sensitive, such as request parameters, are recorded
+//! under one of [`SENSITIVE_FIELDS`] (e.g. `token = %value`), which the
+//! layer blanks out. Free text is also scrubbed of otpauth URIs, bearer
+//! tokens and base32 secrets.
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::sync::OnceLock;
+
+use regex::Regex;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Placeholder written instead of a secret
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Field and parameter names whose values are always blanked out
+pub const SENSITIVE_FIELDS: &[&str] = &[
+    "password",
+    "master_password",
+    "passphrase",
+    "secret",
+    "totp",
+    "token",
+    "pin",
+    "keyfile",
+    "recovery_key",
+];
+
+/// Shortest run of base32 characters treated as a secret
+const MIN_BASE32_SECRET_LEN: usize = 16;
+
+struct Patterns {
+    otpauth: Regex,
+    bearer: Regex,
+    field: Regex,
+    base32: Regex,
+    base32_grouped: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let fields = SENSITIVE_FIELDS.join("|");
+        Patterns {
+            otpauth: Regex::new(r#"(?i)otpauth://[^\s"']+"#).unwrap(),
+            bearer: Regex::new(r"(?i)\b(bearer)\s+[A-Za-z0-9._~+/=-]+").unwrap(),
+            // `password=x`, `password: x`, `"password":"x"` and `?token=x&`
+            field: Regex::new(&format!(
+                r#"(?i)\b({})("?\s*[=:]\s*)("[^"]*"|[^\s,;&}}\]]+)"#,
+                fields
+            ))
+            .unwrap(),
+            base32: Regex::new(&format!(
+                r"\b[A-Z2-7]{{{},}}={{0,6}}",
+                MIN_BASE32_SECRET_LEN
+            ))
+            .unwrap(),
+            // Secrets as shown to users, e.g. `JBSW Y3DP EHPK 3PXP`
+            base32_grouped: Regex::new(r"\b[A-Z2-7]{4}(?:[ -][A-Z2-7]{4}){3,}\b").unwrap(),
+        }
+    })
+}
+
+/// Replace known secret patterns in `text` with [`REDACTED`]
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let patterns = patterns();
+    let mut text = Cow::Borrowed(text);
+
+    for (regex, replacement) in [
+        (&patterns.otpauth, REDACTED.to_string()),
+        (&patterns.bearer, format!("$1 {}", REDACTED)),
+        (&patterns.field, format!("$1$2{}", REDACTED)),
+        (&patterns.base32_grouped, REDACTED.to_string()),
+        (&patterns.base32, REDACTED.to_string()),
+    ] {
+        if let Cow::Owned(replaced) = regex.replace_all(&text, replacement.as_str()) {
+            text = Cow::Owned(replaced);
+        }
+    }
+    text
+}
+
+/// Writer factory for `tracing_subscriber::fmt` that redacts each event
+///
+/// ```no_run
+/// use securefox_core::logging::RedactingWriter;
+///
+/// tracing_subscriber::fmt()
+///     .with_writer(RedactingWriter::new(std::io::stdout))
+///     .init();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RedactingWriter<M> {
+    inner: M,
+}
+
+impl<M> RedactingWriter<M> {
+    pub fn new(inner: M) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingWriter<M> {
+    type Writer = Redacting<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Redacting(self.inner.make_writer())
+    }
+}
+
+/// Writer that redacts everything written to it
+///
+/// The fmt layer writes each event in one piece, so patterns are never
+/// split across calls.
+pub struct Redacting<W>(W);
+
+impl<W: Write> Write for Redacting<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_redacts_secret_patterns() {
+        let cases = [
+            (
+                "uri otpauth://totp/GitHub:me?secret=JBSWY3DPEHPK3PXP&issuer=GitHub",
+                "uri [REDACTED]",
+            ),
+            (
+                "authorization: Bearer eyJhbGciOi.abc-123",
+                "authorization: Bearer [REDACTED]",
+            ),
+            (
+                "GET /api/items?token=abc123&search=git",
+                "GET /api/items?token=[REDACTED]&search=git",
+            ),
+            (
+                r#"{"password":"hunter2","name":"x"}"#,
+                r#"{"password":[REDACTED],"name":"x"}"#,
+            ),
+            (
+                "secret JBSWY3DPEHPK3PXP rejected",
+                "secret [REDACTED] rejected",
+            ),
+            ("secret JBSW Y3DP EHPK 3PXP", "secret [REDACTED]"),
+            ("pin = 1234", "pin = [REDACTED]"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(redact(input), expected, "input: {}", input);
+        }
+
+        // Ordinary log lines are left alone
+        let line = "GET /api/items/4f1c2a9e-0b7d-4c51-9a37-2f0e6c1d8b55 status=200 latency=3 ms";
+        assert!(matches!(redact(line), Cow::Borrowed(_)));
+    }
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_layer_redacts_events() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_max_level(tracing::Level::DEBUG)
+            .with_writer(RedactingWriter::new(move || writer.clone()))
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!(password = "hunter2", "unlocking");
+            tracing::info!("otpauth://totp/x?secret=JBSWY3DPEHPK3PXP");
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("unlocking"));
+        assert!(!output.contains("hunter2"));
+        assert!(!output.contains("JBSWY3DPEHPK3PXP"));
+    }
+}
@@ -0,0 +1,272 @@
+//! Protection of secrets kept in memory by long-running processes
+//!
+//! [`SealedVault`] holds an unlocked vault encrypted under an ephemeral key,
+//! with each item sealed on its own. Items are decrypted only when asked
+//! for, into buffers that are locked in RAM and wiped after parsing. A
+//! memory dump of the service then reveals at most the items in use.
+//!
+//! Locking memory with `mlock` is best effort: it fails when the process
+//! exceeds its locked memory limit, and is unavailable outside Unix.
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use crate::crypto::{
+    decrypt_with_aad, encrypt_with_aad, generate_key, EncryptedData, EncryptionKey,
+};
+use crate::errors::Result;
+use crate::models::{Folder, Item, Vault};
+
+/// Alignment of locked keys, at least one page on all supported platforms
+const KEY_PAGE_ALIGN: usize = 16384;
+
+/// Lock `bytes` into RAM so they are not swapped out, returning whether it worked
+pub fn lock(bytes: &[u8]) -> bool {
+    #[cfg(unix)]
+    {
+        if bytes.is_empty() {
+            return false;
+        }
+        // SAFETY: the range is a live allocation; mlock does not access it
+        unsafe { libc::mlock(bytes.as_ptr().cast(), bytes.len()) == 0 }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = bytes;
+        false
+    }
+}
+
+/// Undo [`lock`]
+pub fn unlock(bytes: &[u8]) {
+    #[cfg(unix)]
+    if !bytes.is_empty() {
+        // SAFETY: as in `lock`
+        unsafe {
+            libc::munlock(bytes.as_ptr().cast(), bytes.len());
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = bytes;
+}
+
+/// Keys get a page of their own, so unlocking a neighbouring buffer never
+/// unlocks a key
+#[repr(C, align(16384))]
+struct KeyPage(EncryptionKey);
+
+const _: () = assert!(std::mem::align_of::<KeyPage>() == KEY_PAGE_ALIGN);
+
+/// An encryption key kept in locked memory and wiped on drop
+pub struct LockedKey {
+    page: Box<KeyPage>,
+    locked: bool,
+}
+
+impl LockedKey {
+    pub fn new(key: EncryptionKey) -> Self {
+        let page = Box::new(KeyPage(key));
+        let locked = lock(page.0.as_bytes());
+        Self { page, locked }
+    }
+
+    /// A fresh random key
+    pub fn generate() -> Self {
+        Self::new(generate_key())
+    }
+
+    pub fn key(&self) -> &EncryptionKey {
+        &self.page.0
+    }
+}
+
+impl Drop for LockedKey {
+    fn drop(&mut self) {
+        self.page.0.zeroize();
+        if self.locked {
+            unlock(self.page.0.as_bytes());
+        }
+    }
+}
+
+/// Plaintext that is locked in RAM while alive and wiped on drop
+struct LockedBuffer {
+    bytes: Vec<u8>,
+    locked: bool,
+}
+
+impl LockedBuffer {
+    fn new(bytes: Vec<u8>) -> Self {
+        let locked = lock(&bytes);
+        Self { bytes, locked }
+    }
+}
+
+impl Drop for LockedBuffer {
+    fn drop(&mut self) {
+        // Wipe through a slice, as zeroizing the Vec would also clear its length
+        self.bytes.as_mut_slice().zeroize();
+        if self.locked {
+            unlock(&self.bytes);
+        }
+    }
+}
+
+/// Vault fields other than items
+#[derive(Serialize, Deserialize)]
+struct Header {
+    folders: Vec<Folder>,
+    version: String,
+}
+
+struct SealedItem {
+    id: String,
+    data: EncryptedData,
+}
+
+/// An unlocked vault kept encrypted in memory under an ephemeral key
+pub struct SealedVault {
+    key: LockedKey,
+    header: EncryptedData,
+    items: Vec<SealedItem>,
+    sync_time: DateTime<Utc>,
+}
+
+impl SealedVault {
+    /// Encrypt `vault` under a new ephemeral key
+    pub fn seal(vault: Vault) -> Result<Self> {
+        let key = LockedKey::generate();
+        let header = seal_value(
+            &Header {
+                folders: vault.folders,
+                version: vault.version,
+            },
+            key.key(),
+            b"header",
+        )?;
+        let items = vault
+            .items
+            .iter()
+            .map(|item| {
+                Ok(SealedItem {
+                    id: item.id.clone(),
+                    data: seal_value(item, key.key(), item.id.as_bytes())?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            key,
+            header,
+            items,
+            sync_time: vault.sync_time,
+        })
+    }
+
+    /// Number of items
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn sync_time(&self) -> DateTime<Utc> {
+        self.sync_time
+    }
+
+    pub fn folders(&self) -> Result<Vec<Folder>> {
+        Ok(open_value::<Header>(&self.header, self.key.key(), b"header")?.folders)
+    }
+
+    /// Decrypt the item with `id`
+    pub fn item(&self, id: &str) -> Result<Option<Item>> {
+        self.items
+            .iter()
+            .find(|sealed| sealed.id == id)
+            .map(|sealed| self.open_item(sealed))
+            .transpose()
+    }
+
+    /// Decrypt items one at a time
+    pub fn items(&self) -> impl Iterator<Item = Result<Item>> + '_ {
+        self.items.iter().map(|sealed| self.open_item(sealed))
+    }
+
+    /// Decrypt the whole vault
+    pub fn unseal(&self) -> Result<Vault> {
+        let header: Header = open_value(&self.header, self.key.key(), b"header")?;
+        Ok(Vault {
+            encrypted: false,
+            folders: header.folders,
+            items: self.items().collect::<Result<_>>()?,
+            version: header.version,
+            sync_time: self.sync_time,
+        })
+    }
+
+    fn open_item(&self, sealed: &SealedItem) -> Result<Item> {
+        open_value(&sealed.data, self.key.key(), sealed.id.as_bytes())
+    }
+}
+
+fn seal_value<T: Serialize>(value: &T, key: &EncryptionKey, aad: &[u8]) -> Result<EncryptedData> {
+    let plaintext = LockedBuffer::new(serde_json::to_vec(value)?);
+    encrypt_with_aad(&plaintext.bytes, key, aad)
+}
+
+fn open_value<T: DeserializeOwned>(
+    data: &EncryptedData,
+    key: &EncryptionKey,
+    aad: &[u8],
+) -> Result<T> {
+    let plaintext = LockedBuffer::new(decrypt_with_aad(data, key, aad)?);
+    Ok(serde_json::from_slice(&plaintext.bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sealed_vault_round_trip() {
+        let mut vault = Vault::new();
+        let mut item = Item::new_login("GitHub");
+        item.login.as_mut().unwrap().password = Some("hunter2".into());
+        let id = item.id.clone();
+        vault.add_item(item);
+        vault.folders.push(Folder::new("Work"));
+
+        let sealed = SealedVault::seal(vault).unwrap();
+        assert_eq!(sealed.len(), 1);
+        assert_eq!(sealed.folders().unwrap()[0].name, "Work");
+
+        let item = sealed.item(&id).unwrap().unwrap();
+        assert_eq!(
+            item.login.unwrap().password.unwrap().expose_secret(),
+            "hunter2"
+        );
+        assert!(sealed.item("missing").unwrap().is_none());
+        assert_eq!(sealed.unseal().unwrap().items.len(), 1);
+    }
+
+    #[test]
+    fn test_sealed_items_cannot_be_swapped() {
+        let mut vault = Vault::new();
+        vault.add_item(Item::new_login("a"));
+        vault.add_item(Item::new_login("b"));
+        let mut sealed = SealedVault::seal(vault).unwrap();
+
+        let first = sealed.items[0].data.clone();
+        sealed.items[1].data = first;
+        assert!(sealed.items().nth(1).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_locked_key_is_page_aligned() {
+        let key = LockedKey::generate();
+        assert_eq!(key.key().as_bytes().as_ptr() as usize % KEY_PAGE_ALIGN, 0);
+    }
+}
@@ -0,0 +1,266 @@
+//! Vault format versions and step-by-step migrations
+//!
+//! Two versions are tracked: the container version of `vault.sf` and the
+//! version of the decrypted vault payload. Older data is upgraded one
+//! registered step at a time; data written by a newer SecureFox is refused
+//! rather than risk dropping fields it does not understand.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::errors::{Error, Result};
+use crate::models::Vault;
+
+/// Container format written by this version
+pub const CONTAINER_VERSION: &str = "3.0.0";
+
+/// Vault payload format written by this version
+pub const PAYLOAD_VERSION: &str = "1.0.0";
+
+/// Payload version assumed when the field is missing
+const INITIAL_PAYLOAD_VERSION: &str = "1.0.0";
+
+/// A `major.minor.patch` format version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FormatVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl FromStr for FormatVersion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::UnsupportedVersion(format!("invalid format version '{}'", s));
+        let mut parts = s
+            .trim()
+            .split('.')
+            .map(|p| p.parse::<u32>().map_err(|_| invalid()));
+
+        let major = parts.next().ok_or_else(invalid)??;
+        let minor = parts.next().transpose()?.unwrap_or(0);
+        let patch = parts.next().transpose()?.unwrap_or(0);
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+impl fmt::Display for FormatVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Layout of the encrypted container, derived from its version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerFormat {
+    /// 1.x: payload encrypted directly with the master password
+    Legacy,
+    /// 2.x: payload encrypted with a data key wrapped in keyslots
+    Keyslots,
+    /// 3.x: keyslots with the header bound to the payload as associated data
+    Bound,
+}
+
+impl ContainerFormat {
+    /// Classify a container version, refusing versions newer than this build
+    pub fn detect(version: &str) -> Result<Self> {
+        let found: FormatVersion = version.parse()?;
+        ensure_not_newer(found, CONTAINER_VERSION, "vault container")?;
+
+        Ok(match found.major {
+            0 | 1 => ContainerFormat::Legacy,
+            2 => ContainerFormat::Keyslots,
+            _ => ContainerFormat::Bound,
+        })
+    }
+}
+
+/// One upgrade step of the decrypted vault payload
+pub struct PayloadMigration {
+    pub from: &'static str,
+    pub to: &'static str,
+    pub apply: fn(&mut Value) -> Result<()>,
+}
+
+/// Registered payload migrations, chained from the oldest version upwards
+const PAYLOAD_MIGRATIONS: &[PayloadMigration] = &[];
+
+/// Parse a decrypted payload, upgrading it to the current version
+///
+/// Returns the vault and whether any migration step ran.
+pub fn read_payload(bytes: &[u8]) -> Result<(Vault, bool)> {
+    let mut value: Value = serde_json::from_slice(bytes)?;
+    let migrated = migrate_payload(&mut value, PAYLOAD_MIGRATIONS, PAYLOAD_VERSION)?;
+    Ok((serde_json::from_value(value)?, migrated))
+}
+
+/// Apply `migrations` in order until the payload reaches `target`
+fn migrate_payload(
+    value: &mut Value,
+    migrations: &[PayloadMigration],
+    target: &str,
+) -> Result<bool> {
+    let target_version: FormatVersion = target.parse()?;
+    let mut current = payload_version(value)?;
+    ensure_not_newer(current, target, "vault data")?;
+
+    let mut migrated = false;
+    while current < target_version {
+        let step = migrations
+            .iter()
+            .find(|m| m.from.parse::<FormatVersion>().ok() == Some(current))
+            .ok_or_else(|| {
+                Error::UnsupportedVersion(format!(
+                    "no migration from vault data version {}",
+                    current
+                ))
+            })?;
+
+        (step.apply)(value)?;
+        current = step.to.parse()?;
+        set_payload_version(value, &current.to_string())?;
+        migrated = true;
+    }
+
+    Ok(migrated)
+}
+
+/// Version recorded in a payload, defaulting to the first format
+fn payload_version(value: &Value) -> Result<FormatVersion> {
+    value
+        .get("version")
+        .and_then(Value::as_str)
+        .unwrap_or(INITIAL_PAYLOAD_VERSION)
+        .parse()
+}
+
+fn set_payload_version(value: &mut Value, version: &str) -> Result<()> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| Error::Decryption("Vault data is not a JSON object".to_string()))?;
+    object.insert("version".to_string(), Value::String(version.to_string()));
+    Ok(())
+}
+
+fn ensure_not_newer(found: FormatVersion, supported: &str, what: &str) -> Result<()> {
+    let supported: FormatVersion = supported.parse()?;
+    if found > supported {
+        return Err(Error::UnsupportedVersion(format!(
+            "{} version {} was written by a newer SecureFox (this build supports up to {}); \
+             please upgrade",
+            what, found, supported
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rename_entries(value: &mut Value) -> Result<()> {
+        let object = value.as_object_mut().unwrap();
+        if let Some(entries) = object.remove("entries") {
+            object.insert("items".to_string(), entries);
+        }
+        Ok(())
+    }
+
+    fn add_folders(value: &mut Value) -> Result<()> {
+        value
+            .as_object_mut()
+            .unwrap()
+            .entry("folders")
+            .or_insert_with(|| json!([]));
+        Ok(())
+    }
+
+    const TEST_MIGRATIONS: &[PayloadMigration] = &[
+        PayloadMigration {
+            from: "0.8.0",
+            to: "0.9.0",
+            apply: rename_entries,
+        },
+        PayloadMigration {
+            from: "0.9.0",
+            to: "1.0.0",
+            apply: add_folders,
+        },
+    ];
+
+    #[test]
+    fn test_version_parsing() {
+        let version: FormatVersion = "2.10.1".parse().unwrap();
+        assert_eq!(version.to_string(), "2.10.1");
+        assert!(version > "2.9.9".parse().unwrap());
+        assert_eq!("3".parse::<FormatVersion>().unwrap().to_string(), "3.0.0");
+        assert!("3.x".parse::<FormatVersion>().is_err());
+    }
+
+    #[test]
+    fn test_container_detection() {
+        assert_eq!(
+            ContainerFormat::detect("1.0.0").unwrap(),
+            ContainerFormat::Legacy
+        );
+        assert_eq!(
+            ContainerFormat::detect("2.0.0").unwrap(),
+            ContainerFormat::Keyslots
+        );
+        assert_eq!(
+            ContainerFormat::detect(CONTAINER_VERSION).unwrap(),
+            ContainerFormat::Bound
+        );
+        assert!(matches!(
+            ContainerFormat::detect("3.1.0"),
+            Err(Error::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_payload_migrates_step_by_step() {
+        let mut value = json!({ "version": "0.8.0", "entries": [] });
+
+        assert!(migrate_payload(&mut value, TEST_MIGRATIONS, "1.0.0").unwrap());
+        assert_eq!(value["version"], "1.0.0");
+        assert!(value["items"].is_array());
+        assert!(value["folders"].is_array());
+
+        assert!(!migrate_payload(&mut value, TEST_MIGRATIONS, "1.0.0").unwrap());
+    }
+
+    #[test]
+    fn test_payload_from_newer_build_is_refused() {
+        let mut value = json!({ "version": "1.1.0" });
+        assert!(matches!(
+            migrate_payload(&mut value, TEST_MIGRATIONS, "1.0.0"),
+            Err(Error::UnsupportedVersion(_))
+        ));
+
+        let mut value = json!({ "version": "0.5.0" });
+        assert!(matches!(
+            migrate_payload(&mut value, TEST_MIGRATIONS, "1.0.0"),
+            Err(Error::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_current_payload_reads_unchanged() {
+        let bytes = serde_json::to_vec(&Vault::new()).unwrap();
+        let (vault, migrated) = read_payload(&bytes).unwrap();
+        assert_eq!(vault.version, PAYLOAD_VERSION);
+        assert!(!migrated);
+    }
+}
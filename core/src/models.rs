@@ -1,8 +1,53 @@
 //! Data models compatible with Bitwarden format
 
+use std::collections::BTreeSet;
+use std::fmt;
+
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// A string holding secret data
+///
+/// The contents are wiped from memory on drop and hidden from `Debug`
+/// output. Serde sees a plain string, so the vault format is unchanged.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+#[serde(transparent)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self(secret.into())
+    }
+
+    /// Borrow the secret; keep the borrow short and never log it
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString(***)")
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(secret: String) -> Self {
+        Self(secret)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(secret: &str) -> Self {
+        Self(secret.to_string())
+    }
+}
 
 /// Main vault container
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,7 +66,7 @@ impl Vault {
             encrypted: false,
             folders: Vec::new(),
             items: Vec::new(),
-            version: "1.0.0".to_string(),
+            version: crate::migration::PAYLOAD_VERSION.to_string(),
             sync_time: Utc::now(),
         }
     }
@@ -47,6 +92,71 @@ impl Vault {
         self.sync_time = Utc::now();
         self.items.iter_mut().find(|i| i.id == id)
     }
+
+    /// Replace the item with the same ID, returning the previous version
+    ///
+    /// The password history and trash state are kept, and a changed login
+    /// password is added to the history.
+    pub fn update_item(&mut self, mut item: Item) -> Option<Item> {
+        let existing = self.get_item_mut(&item.id)?;
+        item.password_history = existing.password_history.clone();
+        item.deleted_date = existing.deleted_date;
+        item.remember_password(existing.login.as_ref().and_then(|l| l.password.as_ref()));
+        Some(std::mem::replace(existing, item))
+    }
+
+    /// Items that are not in the trash
+    pub fn active_items(&self) -> impl Iterator<Item = &Item> {
+        self.items.iter().filter(|i| !i.is_deleted())
+    }
+
+    /// Items in the trash
+    pub fn trashed_items(&self) -> impl Iterator<Item = &Item> {
+        self.items.iter().filter(|i| i.is_deleted())
+    }
+
+    /// Move an item to the trash; it is kept until purged
+    pub fn trash_item(&mut self, id: &str) -> Option<&Item> {
+        let item = self.get_item_mut(id)?;
+        if item.deleted_date.is_none() {
+            let now = Utc::now();
+            item.deleted_date = Some(now);
+            item.revision_date = now;
+        }
+        Some(item)
+    }
+
+    /// Take an item back out of the trash
+    pub fn restore_item(&mut self, id: &str) -> Option<&Item> {
+        let item = self.get_item_mut(id)?;
+        if item.deleted_date.take().is_some() {
+            item.revision_date = Utc::now();
+        }
+        Some(item)
+    }
+
+    /// Permanently remove items trashed before `cutoff`, returning them
+    ///
+    /// Callers delete the attachment blobs of the returned items.
+    pub fn purge_trash(&mut self, cutoff: DateTime<Utc>) -> Vec<Item> {
+        self.take_items(|i| i.deleted_date.is_some_and(|d| d < cutoff))
+    }
+
+    /// Permanently remove every trashed item, returning them
+    pub fn empty_trash(&mut self) -> Vec<Item> {
+        self.take_items(Item::is_deleted)
+    }
+
+    fn take_items(&mut self, take: impl Fn(&Item) -> bool) -> Vec<Item> {
+        let (taken, kept) = std::mem::take(&mut self.items)
+            .into_iter()
+            .partition(|i| take(i));
+        self.items = kept;
+        if !taken.is_empty() {
+            self.sync_time = Utc::now();
+        }
+        taken
+    }
 }
 
 impl Default for Vault {
@@ -72,6 +182,9 @@ impl Folder {
     }
 }
 
+/// Number of previous passwords kept per item, as in Bitwarden
+pub const PASSWORD_HISTORY_LIMIT: usize = 5;
+
 /// Item types matching Bitwarden
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 #[serde(transparent)]
@@ -82,6 +195,7 @@ impl ItemType {
     pub const SECURE_NOTE: Self = Self(2);
     pub const CARD: Self = Self(3);
     pub const IDENTITY: Self = Self(4);
+    pub const SSH_KEY: Self = Self(5);
 }
 
 /// Main item structure
@@ -96,7 +210,7 @@ pub struct Item {
     pub folder_id: Option<String>,
     pub favorite: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub notes: Option<String>,
+    pub notes: Option<SecretString>,
 
     // Type-specific fields
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -107,16 +221,33 @@ pub struct Item {
     pub identity: Option<IdentityData>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub secure_note: Option<SecureNoteData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_key: Option<SshKeyData>,
 
     // Custom fields
     #[serde(skip_serializing_if = "Option::is_none")]
     pub fields: Option<Vec<CustomField>>,
 
+    /// Labels for filtering, e.g. `prod`; unlike folders an item can have many
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+
+    // Attachments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+
+    /// Previous login passwords, newest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub password_history: Vec<PasswordHistory>,
+
     // Metadata
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reprompt: Option<u8>,
     pub creation_date: DateTime<Utc>,
     pub revision_date: DateTime<Utc>,
+    /// When the item was moved to the trash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_date: Option<DateTime<Utc>>,
 }
 
 impl Item {
@@ -133,10 +264,15 @@ impl Item {
             card: None,
             identity: None,
             secure_note: None,
+            ssh_key: None,
             fields: None,
+            tags: BTreeSet::new(),
+            attachments: Vec::new(),
+            password_history: Vec::new(),
             reprompt: None,
             creation_date: now,
             revision_date: now,
+            deleted_date: None,
         }
     }
 
@@ -155,12 +291,60 @@ impl Item {
             secure_note: Some(SecureNoteData {
                 type_: SecureNoteType::GENERIC,
             }),
+            ssh_key: None,
             fields: None,
+            tags: BTreeSet::new(),
+            attachments: Vec::new(),
+            password_history: Vec::new(),
             reprompt: None,
             creation_date: now,
             revision_date: now,
+            deleted_date: None,
         }
     }
+
+    /// An SSH key item; see `SshKeyData::generate` and `SshKeyData::from_openssh`
+    pub fn new_ssh_key(name: impl Into<String>, ssh_key: SshKeyData) -> Self {
+        Self {
+            item_type: ItemType::SSH_KEY,
+            login: None,
+            ssh_key: Some(ssh_key),
+            ..Self::new_login(name)
+        }
+    }
+
+    /// Whether the item is in the trash
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_date.is_some()
+    }
+
+    /// Record `previous` in the password history if it is no longer the password
+    pub fn remember_password(&mut self, previous: Option<&SecretString>) {
+        let Some(previous) = previous.filter(|p| !p.is_empty()) else {
+            return;
+        };
+        if self.login.as_ref().and_then(|l| l.password.as_ref()) == Some(previous) {
+            return;
+        }
+
+        self.password_history.insert(
+            0,
+            PasswordHistory {
+                password: previous.clone(),
+                last_used_date: Utc::now(),
+            },
+        );
+        self.password_history.truncate(PASSWORD_HISTORY_LIMIT);
+    }
+}
+
+/// A password an item used before
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordHistory {
+    pub password: SecretString,
+    /// When the password was replaced
+    pub last_used_date: DateTime<Utc>,
 }
 
 /// Login-specific data
@@ -170,9 +354,9 @@ pub struct LoginData {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub username: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub password: Option<String>,
+    pub password: Option<SecretString>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub totp: Option<String>, // otpauth://totp/... format
+    pub totp: Option<SecretString>, // otpauth://totp/... format
     #[serde(skip_serializing_if = "Option::is_none")]
     pub uris: Option<Vec<LoginUri>>,
 }
@@ -201,25 +385,25 @@ impl UriMatchType {
 }
 
 /// Card data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CardData {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cardholder_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub number: Option<String>,
+    pub number: Option<SecretString>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exp_month: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub exp_year: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub code: Option<String>,
+    pub code: Option<SecretString>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub brand: Option<String>,
 }
 
 /// Identity data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IdentityData {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -250,6 +434,19 @@ pub struct IdentityData {
     pub country: Option<String>,
 }
 
+/// SSH key data, as in Bitwarden
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SshKeyData {
+    /// Unencrypted private key in OpenSSH format; left out of item listings
+    #[serde(default, skip_serializing_if = "SecretString::is_empty")]
+    pub private_key: SecretString,
+    /// Public key in `authorized_keys` format
+    pub public_key: String,
+    /// SHA256 fingerprint, e.g. `SHA256:...`
+    pub key_fingerprint: String,
+}
+
 /// Secure note data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -278,7 +475,7 @@ impl Default for SecureNoteType {
 #[serde(rename_all = "camelCase")]
 pub struct CustomField {
     pub name: String,
-    pub value: String,
+    pub value: SecretString,
     #[serde(rename = "type")]
     pub field_type: FieldType,
 }
@@ -294,6 +491,22 @@ impl FieldType {
     pub const BOOLEAN: Self = Self(2);
 }
 
+/// File attached to an item
+///
+/// The contents are stored encrypted outside the vault file; see
+/// [`crate::attachments`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: String,
+    pub file_name: String,
+    /// Size of the contents in bytes
+    pub size: u64,
+    /// Base64 key of the blob; absent when imported without the contents
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<SecretString>,
+}
+
 /// Sync configuration for git synchronization
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -362,6 +575,85 @@ impl SyncMode {
     }
 }
 
+/// Days trashed items are kept by default, as in Bitwarden
+pub const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+
+/// Limits of PIN quick unlock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinPolicy {
+    /// Wrong PIN attempts before PIN unlock is wiped
+    pub max_attempts: u32,
+
+    /// Hours after a full unlock before the master password is required again
+    pub valid_hours: u64,
+}
+
+impl Default for PinPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            valid_hours: 24,
+        }
+    }
+}
+
+/// How many vault backups are kept
+///
+/// The newest `keep_last` backups are kept, along with the newest backup of
+/// each of the last `keep_daily` days and `keep_weekly` weeks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupPolicy {
+    pub keep_last: usize,
+    pub keep_daily: usize,
+    pub keep_weekly: usize,
+}
+
+impl Default for BackupPolicy {
+    fn default() -> Self {
+        Self {
+            keep_last: 10,
+            keep_daily: 7,
+            keep_weekly: 4,
+        }
+    }
+}
+
+/// Name of the vault in ~/.securefox when no profile is selected
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// A named vault registered in the config file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultProfile {
+    /// Directory holding the vault file
+    pub path: std::path::PathBuf,
+
+    /// Git remote URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_url: Option<String>,
+
+    /// Auto-sync configuration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sync_config: Option<SyncConfig>,
+
+    /// Keyfile used as a second unlock factor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyfile: Option<std::path::PathBuf>,
+}
+
+impl VaultProfile {
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            remote_url: None,
+            sync_config: None,
+            keyfile: None,
+        }
+    }
+}
+
 /// Configuration file stored in ~/.securefox/config
 /// This contains sync-related configuration that doesn't need encryption
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -374,6 +666,77 @@ pub struct SyncConfigFile {
     /// Auto-sync configuration
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sync_config: Option<SyncConfig>,
+
+    /// Re-wrap weak password keyslots with Argon2id on the next successful unlock
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub auto_upgrade_kdf: bool,
+
+    /// Keyfile of the default vault, used as a second unlock factor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keyfile: Option<std::path::PathBuf>,
+
+    /// PIN quick unlock limits; defaults apply when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin_policy: Option<PinPolicy>,
+
+    /// Days trashed items are kept before they are purged; 0 keeps them forever
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trash_retention_days: Option<u32>,
+
+    /// Backup retention; defaults apply when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_policy: Option<BackupPolicy>,
+
+    /// Named vaults besides the default one in ~/.securefox
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    pub vaults: std::collections::BTreeMap<String, VaultProfile>,
+
+    /// Profile used when a command names none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_vault: Option<String>,
+}
+
+impl SyncConfigFile {
+    /// Trash retention, with the default applied
+    pub fn trash_retention_days(&self) -> u32 {
+        self.trash_retention_days
+            .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS)
+    }
+
+    /// Profile whose vault lives in `vault_dir`
+    pub fn profile_for(&self, vault_dir: &std::path::Path) -> Option<(&str, &VaultProfile)> {
+        self.vaults
+            .iter()
+            .find(|(_, profile)| profile.path == vault_dir)
+            .map(|(name, profile)| (name.as_str(), profile))
+    }
+
+    /// Git remote of the vault in `vault_dir`
+    ///
+    /// Registered vaults keep their own settings; the top-level ones belong
+    /// to the default vault.
+    pub fn remote_url_for(&self, vault_dir: &std::path::Path) -> Option<&str> {
+        match self.profile_for(vault_dir) {
+            Some((_, profile)) => profile.remote_url.as_deref(),
+            None => self.remote_url.as_deref(),
+        }
+    }
+
+    /// Auto-sync configuration of the vault in `vault_dir`
+    pub fn sync_config_for(&self, vault_dir: &std::path::Path) -> Option<&SyncConfig> {
+        match self.profile_for(vault_dir) {
+            Some((_, profile)) => profile.sync_config.as_ref(),
+            None => self.sync_config.as_ref(),
+        }
+    }
+
+    /// Keyfile required to unlock the vault in `vault_dir`
+    pub fn keyfile_for(&self, vault_dir: &std::path::Path) -> Option<&std::path::Path> {
+        match self.profile_for(vault_dir) {
+            Some((_, profile)) => profile.keyfile.as_deref(),
+            None => self.keyfile.as_deref(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -401,8 +764,8 @@ mod tests {
         let mut item = Item::new_login("GitHub");
         if let Some(ref mut login) = item.login {
             login.username = Some("user@example.com".to_string());
-            login.password = Some("secure123".to_string());
-            login.totp = Some("otpauth://totp/GitHub:user?secret=JBSWY3DPEHPK3PXP".to_string());
+            login.password = Some("secure123".into());
+            login.totp = Some("otpauth://totp/GitHub:user?secret=JBSWY3DPEHPK3PXP".into());
         }
         vault.add_item(item);
 
@@ -412,4 +775,81 @@ mod tests {
         assert_eq!(deserialized.items.len(), 1);
         assert_eq!(deserialized.items[0].name, "GitHub");
     }
+
+    #[test]
+    fn test_secret_string_is_redacted_and_transparent() {
+        let secret = SecretString::from("hunter2");
+        assert_eq!(format!("{:?}", secret), "SecretString(***)");
+        assert_eq!(serde_json::to_string(&secret).unwrap(), "\"hunter2\"");
+
+        let login = LoginData {
+            password: Some(secret),
+            ..LoginData::default()
+        };
+        assert!(!format!("{:?}", login).contains("hunter2"));
+        let parsed: SecretString = serde_json::from_str("\"hunter2\"").unwrap();
+        assert_eq!(parsed.expose_secret(), "hunter2");
+    }
+
+    #[test]
+    fn test_update_item_records_password_history() {
+        let mut vault = Vault::new();
+        let mut item = Item::new_login("GitHub");
+        item.login.as_mut().unwrap().password = Some("first".into());
+        vault.add_item(item.clone());
+
+        for password in ["second", "second", "third", "4", "5", "6", "7"] {
+            item.login.as_mut().unwrap().password = Some(password.into());
+            vault.update_item(item.clone()).unwrap();
+        }
+
+        // Unchanged passwords are not recorded, and the history is capped
+        let history = &vault.get_item(&item.id).unwrap().password_history;
+        assert_eq!(history.len(), PASSWORD_HISTORY_LIMIT);
+        let passwords: Vec<_> = history.iter().map(|h| h.password.expose_secret()).collect();
+        assert_eq!(passwords, ["6", "5", "4", "third", "second"]);
+
+        assert!(vault.update_item(Item::new_login("missing")).is_none());
+    }
+
+    #[test]
+    fn test_trash_restore_and_purge() {
+        let mut vault = Vault::new();
+        let kept = Item::new_login("kept");
+        let trashed = Item::new_login("trashed");
+        let (kept_id, trashed_id) = (kept.id.clone(), trashed.id.clone());
+        vault.add_item(kept);
+        vault.add_item(trashed);
+
+        assert!(vault.trash_item(&trashed_id).unwrap().is_deleted());
+        assert_eq!(vault.active_items().count(), 1);
+        assert_eq!(vault.trashed_items().next().unwrap().id, trashed_id);
+
+        // Updates do not take an item out of the trash
+        let mut update = vault.get_item(&trashed_id).unwrap().clone();
+        update.deleted_date = None;
+        vault.update_item(update).unwrap();
+        assert!(vault.get_item(&trashed_id).unwrap().is_deleted());
+
+        assert!(!vault.restore_item(&trashed_id).unwrap().is_deleted());
+        assert_eq!(vault.trashed_items().count(), 0);
+
+        // Only items trashed before the cutoff are purged
+        vault.trash_item(&trashed_id).unwrap();
+        assert!(vault
+            .purge_trash(Utc::now() - chrono::Duration::days(1))
+            .is_empty());
+        let purged = vault.purge_trash(Utc::now() + chrono::Duration::seconds(1));
+        assert_eq!(purged.len(), 1);
+        assert_eq!(purged[0].id, trashed_id);
+        assert!(vault.get_item(&kept_id).is_some());
+        assert_eq!(vault.items.len(), 1);
+
+        vault.trash_item(&kept_id).unwrap();
+        assert_eq!(vault.empty_trash().len(), 1);
+        assert!(vault.items.is_empty());
+
+        let json = serde_json::to_string(&Item::new_login("x")).unwrap();
+        assert!(!json.contains("deletedDate"));
+    }
 }
@@ -0,0 +1,391 @@
+//! PIN quick unlock
+//!
+//! After a full unlock the data key can be wrapped with a short PIN and kept
+//! in a file next to the vault. The file stays on this machine and is never
+//! synced. Because a PIN has little entropy, the wrap uses costly Argon2id
+//! settings. It is wiped after too many wrong attempts, or once the policy's
+//! validity period ends; the master password is then needed again.
+//!
+//! The PIN is also kept encrypted under the data key. Once the validity
+//! period ends, a later full unlock can then re-arm PIN unlock without
+//! asking for the PIN again. A PIN wiped after too many wrong attempts stays
+//! off until it is set again. The attempt limit is enforced by SecureFox
+//! only and does not protect a copied file.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::crypto::{decrypt_with_aad, encrypt_with_aad, EncryptedData, EncryptionKey, KdfParams};
+use crate::errors::{Error, Result};
+use crate::keyslot::{Keyslot, KeyslotKind};
+use crate::models::PinPolicy;
+
+/// Shortest PIN accepted
+pub const MIN_PIN_LENGTH: usize = 4;
+
+/// Argon2 memory cost of the PIN wrap in KB (64MB)
+const PIN_ARGON2_MEMORY_KB: u32 = 65536;
+
+/// Argon2 iterations of the PIN wrap
+const PIN_ARGON2_ITERATIONS: u32 = 3;
+
+/// Contents of the PIN file
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PinFile {
+    vault_id: String,
+    /// The PIN encrypted under the data key, used to re-arm after a full unlock
+    protected_pin: EncryptedData,
+    /// Data key wrapped by the PIN; absent while PIN unlock is disarmed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    slot: Option<Keyslot>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    failed_attempts: u32,
+    /// Set when the wrap was wiped after too many wrong attempts
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    locked_out: bool,
+}
+
+/// State of PIN unlock on this machine
+#[derive(Debug, Clone)]
+pub struct PinStatus {
+    /// Whether the PIN currently unlocks the vault
+    pub armed: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub failed_attempts: u32,
+    /// Whether too many wrong attempts turned the PIN off until it is set again
+    pub locked_out: bool,
+}
+
+/// Local PIN file of one vault
+pub struct PinStore {
+    path: PathBuf,
+}
+
+impl PinStore {
+    pub fn with_path<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Whether PIN unlock is enabled for the vault
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Current state, or `None` when PIN unlock is not enabled
+    pub fn status(&self) -> Result<Option<PinStatus>> {
+        Ok(self.read()?.map(|file| PinStatus {
+            armed: file.slot.is_some() && !is_expired(&file),
+            expires_at: file.expires_at,
+            failed_attempts: file.failed_attempts,
+            locked_out: file.locked_out,
+        }))
+    }
+
+    /// Enable PIN unlock with the data key of an unlocked vault
+    pub fn enable(
+        &self,
+        pin: &str,
+        key: &EncryptionKey,
+        vault_id: &str,
+        policy: &PinPolicy,
+    ) -> Result<()> {
+        if pin.chars().count() < MIN_PIN_LENGTH {
+            return Err(Error::Other(format!(
+                "PIN must be at least {} characters",
+                MIN_PIN_LENGTH
+            )));
+        }
+
+        let mut file = PinFile {
+            vault_id: vault_id.to_string(),
+            protected_pin: encrypt_with_aad(pin.as_bytes(), key, &associated_data(vault_id))?,
+            slot: None,
+            expires_at: None,
+            failed_attempts: 0,
+            locked_out: false,
+        };
+        self.arm(&mut file, pin, key, policy)
+    }
+
+    /// Re-arm PIN unlock after a full unlock, returning whether it is armed
+    ///
+    /// Only an expired wrap is renewed, as wrapping takes a costly Argon2
+    /// run; a PIN locked out by wrong attempts stays off until `enable`.
+    /// A PIN file left over from a previous data key is removed.
+    pub fn rearm(&self, key: &EncryptionKey, vault_id: &str, policy: &PinPolicy) -> Result<bool> {
+        let Some(mut file) = self.read()? else {
+            return Ok(false);
+        };
+        if file.locked_out {
+            return Ok(false);
+        }
+
+        let pin = match decrypt_with_aad(&file.protected_pin, key, &associated_data(vault_id)) {
+            Ok(pin) if file.vault_id == vault_id => Zeroizing::new(pin),
+            _ => {
+                self.disable()?;
+                return Ok(false);
+            }
+        };
+        if file.slot.is_some() && !is_expired(&file) {
+            return Ok(true);
+        }
+        let pin = std::str::from_utf8(&pin)
+            .map_err(|_| Error::Decryption("Stored PIN is not valid UTF-8".to_string()))?;
+
+        self.arm(&mut file, pin, key, policy)?;
+        Ok(true)
+    }
+
+    /// Recover the data key with the PIN, counting wrong attempts
+    pub fn unlock(&self, pin: &str, policy: &PinPolicy) -> Result<EncryptionKey> {
+        let mut file = self
+            .read()?
+            .ok_or_else(|| Error::PinUnavailable("PIN unlock is not enabled".to_string()))?;
+
+        let Some(slot) = file.slot.clone() else {
+            return Err(Error::PinUnavailable(
+                "unlock with the master password to use the PIN again".to_string(),
+            ));
+        };
+        if is_expired(&file) {
+            self.disarm(&mut file, false)?;
+            return Err(Error::PinUnavailable(
+                "PIN unlock expired; unlock with the master password".to_string(),
+            ));
+        }
+        if file.failed_attempts >= policy.max_attempts {
+            self.disarm(&mut file, true)?;
+            return Err(too_many_attempts());
+        }
+
+        // Count the attempt before deriving, so an interrupted attempt still counts
+        file.failed_attempts += 1;
+        self.write(&file)?;
+
+        match slot.unwrap_key(pin.as_bytes()) {
+            Ok(key) => {
+                file.failed_attempts = 0;
+                self.write(&file)?;
+                Ok(key)
+            }
+            Err(Error::InvalidPassword) => {
+                let remaining = policy.max_attempts.saturating_sub(file.failed_attempts);
+                if remaining == 0 {
+                    self.disarm(&mut file, true)?;
+                    return Err(too_many_attempts());
+                }
+                Err(Error::InvalidPin(remaining))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Turn PIN unlock off, removing the PIN file
+    pub fn disable(&self) -> Result<()> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+
+    /// Wrap the data key with the PIN and start a new validity period
+    fn arm(
+        &self,
+        file: &mut PinFile,
+        pin: &str,
+        key: &EncryptionKey,
+        policy: &PinPolicy,
+    ) -> Result<()> {
+        file.slot = Some(Keyslot::new(
+            KeyslotKind::Pin,
+            pin.as_bytes(),
+            pin_kdf_params(),
+            key,
+            &file.vault_id,
+        )?);
+        file.expires_at = Some(Utc::now() + Duration::hours(policy.valid_hours as i64));
+        file.failed_attempts = 0;
+        self.write(file)
+    }
+
+    /// Wipe the PIN wrap, keeping the protected PIN for re-arming unless
+    /// `locked_out`
+    fn disarm(&self, file: &mut PinFile, locked_out: bool) -> Result<()> {
+        file.slot = None;
+        file.expires_at = None;
+        file.failed_attempts = 0;
+        file.locked_out = locked_out;
+        self.write(file)
+    }
+
+    fn read(&self) -> Result<Option<PinFile>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read(&self.path)?;
+        Ok(Some(serde_json::from_slice(&contents)?))
+    }
+
+    /// Write the PIN file atomically, readable by the owner only
+    fn write(&self, file: &PinFile) -> Result<()> {
+        let contents = serde_json::to_vec_pretty(file)?;
+
+        let mut tmp_name = self.path.as_os_str().to_os_string();
+        tmp_name.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_name);
+
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut tmp = options.open(&tmp_path)?;
+        tmp.write_all(&contents)?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Argon2id settings well above the vault default, as a PIN is easy to guess
+fn pin_kdf_params() -> KdfParams {
+    KdfParams {
+        memory_kb: Some(PIN_ARGON2_MEMORY_KB),
+        iterations: PIN_ARGON2_ITERATIONS,
+        ..KdfParams::argon2()
+    }
+}
+
+fn associated_data(vault_id: &str) -> Vec<u8> {
+    format!("securefox pin|{}", vault_id).into_bytes()
+}
+
+fn is_expired(file: &PinFile) -> bool {
+    file.expires_at.map(|at| at <= Utc::now()).unwrap_or(true)
+}
+
+fn too_many_attempts() -> Error {
+    Error::PinUnavailable(
+        "too many wrong PIN attempts; unlock with the master password and set the PIN again"
+            .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::generate_key;
+    use tempfile::tempdir;
+
+    const VAULT_ID: &str = "00000000-0000-0000-0000-000000000001";
+
+    #[test]
+    fn test_pin_unlocks_and_rearms() {
+        let temp_dir = tempdir().unwrap();
+        let store = PinStore::with_path(temp_dir.path().join("vault.sf.pin"));
+        let policy = PinPolicy::default();
+        let key = generate_key();
+
+        assert!(store.enable("12", &key, VAULT_ID, &policy).is_err());
+        store.enable("1234", &key, VAULT_ID, &policy).unwrap();
+        assert!(store.status().unwrap().unwrap().armed);
+        assert_eq!(
+            store.unlock("1234", &policy).unwrap().as_bytes(),
+            key.as_bytes()
+        );
+
+        // A new data key invalidates the stored PIN
+        assert!(!store.rearm(&generate_key(), VAULT_ID, &policy).unwrap());
+        assert!(!store.exists());
+    }
+
+    #[test]
+    fn test_wrong_attempts_wipe_the_wrap() {
+        let temp_dir = tempdir().unwrap();
+        let store = PinStore::with_path(temp_dir.path().join("vault.sf.pin"));
+        let policy = PinPolicy {
+            max_attempts: 2,
+            ..PinPolicy::default()
+        };
+        let key = generate_key();
+        store.enable("1234", &key, VAULT_ID, &policy).unwrap();
+
+        assert!(matches!(
+            store.unlock("0000", &policy),
+            Err(Error::InvalidPin(1))
+        ));
+        assert!(matches!(
+            store.unlock("0000", &policy),
+            Err(Error::PinUnavailable(_))
+        ));
+        assert!(matches!(
+            store.unlock("1234", &policy),
+            Err(Error::PinUnavailable(_))
+        ));
+
+        // A full unlock does not re-arm a locked out PIN; setting it does
+        assert!(!store.rearm(&key, VAULT_ID, &policy).unwrap());
+        assert!(store.status().unwrap().unwrap().locked_out);
+        assert!(store.unlock("1234", &policy).is_err());
+        store.enable("1234", &key, VAULT_ID, &policy).unwrap();
+        assert!(store.unlock("1234", &policy).is_ok());
+    }
+
+    #[test]
+    fn test_rearm_only_renews_an_expired_wrap() {
+        let temp_dir = tempdir().unwrap();
+        let store = PinStore::with_path(temp_dir.path().join("vault.sf.pin"));
+        let policy = PinPolicy::default();
+        let key = generate_key();
+        store.enable("1234", &key, VAULT_ID, &policy).unwrap();
+        let armed = fs::read(temp_dir.path().join("vault.sf.pin")).unwrap();
+
+        // Still valid: the file is left untouched
+        assert!(store.rearm(&key, VAULT_ID, &policy).unwrap());
+        assert_eq!(
+            fs::read(temp_dir.path().join("vault.sf.pin")).unwrap(),
+            armed
+        );
+
+        // Expired: a full unlock renews it
+        let mut file = store.read().unwrap().unwrap();
+        file.expires_at = Some(Utc::now() - Duration::hours(1));
+        store.write(&file).unwrap();
+        assert!(!store.status().unwrap().unwrap().armed);
+        assert!(store.rearm(&key, VAULT_ID, &policy).unwrap());
+        assert!(store.status().unwrap().unwrap().armed);
+    }
+
+    #[test]
+    fn test_expired_pin_requires_full_unlock() {
+        let temp_dir = tempdir().unwrap();
+        let store = PinStore::with_path(temp_dir.path().join("vault.sf.pin"));
+        let policy = PinPolicy {
+            valid_hours: 0,
+            ..PinPolicy::default()
+        };
+        store
+            .enable("1234", &generate_key(), VAULT_ID, &policy)
+            .unwrap();
+
+        assert!(!store.status().unwrap().unwrap().armed);
+        assert!(matches!(
+            store.unlock("1234", &policy),
+            Err(Error::PinUnavailable(_))
+        ));
+    }
+}
@@ -0,0 +1,329 @@
+//! Shamir secret sharing of the vault recovery key
+//!
+//! A random recovery key wraps the data key in a recovery keyslot and is
+//! split into shares over GF(256), so any `threshold` of them rebuild it.
+//!
+//! Shares are written as `SFX1-` followed by base32 in groups of four. Only
+//! upper case letters, digits and dashes are used, which also keeps them in
+//! the compact alphanumeric mode of QR codes. Each share carries a checksum
+//! and the id of the split it belongs to, so typos and shares from another
+//! split are reported instead of yielding a wrong key.
+
+use std::fmt;
+use std::str::FromStr;
+
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::crypto::{EncryptionKey, KEY_SIZE};
+use crate::errors::{Error, Result};
+
+/// Prefix of encoded shares, including the share format version
+const SHARE_PREFIX: &str = "SFX1";
+
+/// Length of the id shared by all shares of one split
+const SET_ID_SIZE: usize = 4;
+
+/// Length of the per-share checksum
+const CHECKSUM_SIZE: usize = 4;
+
+/// Encoded share length: set id, threshold, index, value and checksum
+const SHARE_SIZE: usize = SET_ID_SIZE + 2 + KEY_SIZE + CHECKSUM_SIZE;
+
+const BASE32: base32::Alphabet = base32::Alphabet::RFC4648 { padding: false };
+
+/// One share of a split recovery key
+#[derive(Clone)]
+pub struct RecoveryShare {
+    set_id: [u8; SET_ID_SIZE],
+    threshold: u8,
+    index: u8,
+    value: Zeroizing<[u8; KEY_SIZE]>,
+}
+
+impl RecoveryShare {
+    /// Number of shares needed to rebuild the key
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// Position of this share, starting at 1
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Identifier of the split this share belongs to
+    pub fn set_id(&self) -> String {
+        hex::encode_upper(self.set_id)
+    }
+
+    /// Encode without separators, e.g. for a QR code
+    pub fn to_compact_string(&self) -> String {
+        self.to_string().replace('-', "")
+    }
+
+    fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let mut bytes = Zeroizing::new(Vec::with_capacity(SHARE_SIZE));
+        bytes.extend_from_slice(&self.set_id);
+        bytes.push(self.threshold);
+        bytes.push(self.index);
+        bytes.extend_from_slice(self.value.as_ref());
+        let checksum = Sha256::digest(&bytes[..]);
+        bytes.extend_from_slice(&checksum[..CHECKSUM_SIZE]);
+        bytes
+    }
+}
+
+impl fmt::Display for RecoveryShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let encoded = Zeroizing::new(base32::encode(BASE32, &self.to_bytes()));
+        write!(f, "{}", SHARE_PREFIX)?;
+        for group in encoded.as_bytes().chunks(4) {
+            // Base32 output is ASCII
+            write!(
+                f,
+                "-{}",
+                std::str::from_utf8(group).map_err(|_| fmt::Error)?
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RecoveryShare {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let normalized: Zeroizing<String> = Zeroizing::new(
+            s.chars()
+                .filter(|c| !c.is_whitespace() && *c != '-')
+                .collect::<String>()
+                .to_uppercase(),
+        );
+        let encoded = normalized.strip_prefix(SHARE_PREFIX).ok_or_else(|| {
+            Error::Recovery(format!(
+                "not a recovery share (expected {}-...)",
+                SHARE_PREFIX
+            ))
+        })?;
+
+        let bytes = Zeroizing::new(
+            base32::decode(BASE32, encoded)
+                .ok_or_else(|| Error::Recovery("share contains invalid characters".to_string()))?,
+        );
+        if bytes.len() != SHARE_SIZE {
+            return Err(Error::Recovery(
+                "share has the wrong length; check for missing characters".to_string(),
+            ));
+        }
+
+        let (body, checksum) = bytes.split_at(SHARE_SIZE - CHECKSUM_SIZE);
+        if Sha256::digest(body)[..CHECKSUM_SIZE] != *checksum {
+            return Err(Error::Recovery(
+                "share checksum does not match; check for typos".to_string(),
+            ));
+        }
+
+        let mut set_id = [0u8; SET_ID_SIZE];
+        set_id.copy_from_slice(&body[..SET_ID_SIZE]);
+        let (threshold, index) = (body[SET_ID_SIZE], body[SET_ID_SIZE + 1]);
+        if threshold == 0 || index == 0 {
+            return Err(Error::Recovery("share header is invalid".to_string()));
+        }
+
+        let mut value = Zeroizing::new([0u8; KEY_SIZE]);
+        value.copy_from_slice(&body[SET_ID_SIZE + 2..]);
+
+        Ok(Self {
+            set_id,
+            threshold,
+            index,
+            value,
+        })
+    }
+}
+
+/// Split `key` into `shares` shares, any `threshold` of which rebuild it
+pub fn split(key: &EncryptionKey, shares: u8, threshold: u8) -> Result<Vec<RecoveryShare>> {
+    if threshold < 2 || threshold > shares {
+        return Err(Error::Recovery(format!(
+            "threshold must be between 2 and the number of shares ({})",
+            shares
+        )));
+    }
+
+    let mut set_id = [0u8; SET_ID_SIZE];
+    OsRng.fill_bytes(&mut set_id);
+
+    // One random polynomial per key byte, with the key byte as constant term
+    let mut coefficients = Zeroizing::new(vec![[0u8; KEY_SIZE]; threshold as usize]);
+    coefficients[0].copy_from_slice(key.as_bytes());
+    for row in coefficients.iter_mut().skip(1) {
+        OsRng.fill_bytes(row);
+    }
+
+    Ok((1..=shares)
+        .map(|index| {
+            let mut value = Zeroizing::new([0u8; KEY_SIZE]);
+            for (byte, out) in value.iter_mut().enumerate() {
+                // Horner's rule from the highest coefficient down
+                *out = coefficients
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, row| gf_mul(acc, index) ^ row[byte]);
+            }
+            RecoveryShare {
+                set_id,
+                threshold,
+                index,
+                value,
+            }
+        })
+        .collect())
+}
+
+/// Rebuild the key from at least `threshold` shares of the same split
+pub fn combine(shares: &[RecoveryShare]) -> Result<EncryptionKey> {
+    let first = shares
+        .first()
+        .ok_or_else(|| Error::Recovery("no shares given".to_string()))?;
+
+    let mut selected: Vec<&RecoveryShare> = Vec::new();
+    for share in shares {
+        if share.set_id != first.set_id || share.threshold != first.threshold {
+            return Err(Error::Recovery(
+                "shares belong to different recovery splits".to_string(),
+            ));
+        }
+        if !selected.iter().any(|s| s.index == share.index) {
+            selected.push(share);
+        }
+    }
+
+    let threshold = first.threshold as usize;
+    if selected.len() < threshold {
+        return Err(Error::Recovery(format!(
+            "{} of {} required shares given",
+            selected.len(),
+            threshold
+        )));
+    }
+    selected.truncate(threshold);
+
+    // Lagrange interpolation at x = 0
+    let mut key = Zeroizing::new([0u8; KEY_SIZE]);
+    for (i, share) in selected.iter().enumerate() {
+        let mut basis = 1u8;
+        for (j, other) in selected.iter().enumerate() {
+            if i != j {
+                basis = gf_mul(
+                    basis,
+                    gf_mul(other.index, gf_inv(other.index ^ share.index)),
+                );
+            }
+        }
+        for (out, y) in key.iter_mut().zip(share.value.iter()) {
+            *out ^= gf_mul(*y, basis);
+        }
+    }
+
+    EncryptionKey::from_bytes(key.as_ref())
+}
+
+/// Multiplication in GF(2^8) with the AES polynomial, without lookup tables
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// Multiplicative inverse in GF(2^8), as a^254
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1u8;
+    let mut base = a;
+    let mut exponent = 254u8;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exponent >>= 1;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::generate_key;
+
+    #[test]
+    fn test_field_arithmetic() {
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+    }
+
+    #[test]
+    fn test_any_threshold_subset_rebuilds_key() {
+        let key = generate_key();
+        let shares = split(&key, 5, 3).unwrap();
+        assert_eq!(shares.len(), 5);
+
+        for subset in [[0, 1, 2], [4, 2, 0], [1, 3, 4]] {
+            let picked: Vec<_> = subset.iter().map(|&i| shares[i].clone()).collect();
+            assert_eq!(combine(&picked).unwrap().as_bytes(), key.as_bytes());
+        }
+
+        let too_few = vec![shares[0].clone(), shares[1].clone(), shares[1].clone()];
+        assert!(matches!(combine(&too_few), Err(Error::Recovery(_))));
+        assert!(split(&key, 3, 4).is_err());
+        assert!(split(&key, 3, 1).is_err());
+    }
+
+    #[test]
+    fn test_share_encoding() {
+        let key = generate_key();
+        let shares = split(&key, 3, 2).unwrap();
+
+        let text = shares[0].to_string();
+        assert!(text.starts_with("SFX1-"));
+        assert!(text
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '-'));
+
+        let parsed: Vec<RecoveryShare> = vec![
+            text.to_lowercase().parse().unwrap(),
+            shares[2].to_compact_string().parse().unwrap(),
+        ];
+        assert_eq!(parsed[0].index(), 1);
+        assert_eq!(combine(&parsed).unwrap().as_bytes(), key.as_bytes());
+
+        let mut typo = text.into_bytes();
+        typo[10] = if typo[10] == b'A' { b'Q' } else { b'A' };
+        let typo = String::from_utf8(typo).unwrap();
+        assert!(matches!(
+            typo.parse::<RecoveryShare>(),
+            Err(Error::Recovery(_))
+        ));
+    }
+
+    #[test]
+    fn test_shares_from_different_splits_are_rejected() {
+        let key = generate_key();
+        let first = split(&key, 3, 2).unwrap();
+        let second = split(&key, 3, 2).unwrap();
+
+        assert!(matches!(
+            combine(&[first[0].clone(), second[1].clone()]),
+            Err(Error::Recovery(_))
+        ));
+    }
+}
@@ -0,0 +1,217 @@
+//! Past and external states of a vault, for comparison and point-in-time restore
+//!
+//! A snapshot is named the way users type it: `current`, the path of a
+//! backup or another `.sf` file, a timestamp, or a git revision of the
+//! vault file. Snapshots are decrypted with the current data key; files of
+//! other vaults, and revisions from before the vault was re-keyed, need
+//! their own password.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, Utc};
+
+use crate::crypto::EncryptionKey;
+use crate::errors::{Error, Result};
+use crate::keyslot::Credentials;
+use crate::models::Vault;
+use crate::storage::{EncryptedVault, VaultStorage};
+
+/// A state of the vault
+#[derive(Debug, Clone, PartialEq)]
+pub enum VaultSnapshot {
+    /// The vault file as it is now
+    Current,
+    /// A vault file elsewhere, such as a backup
+    File(PathBuf),
+    /// A git revision of the vault file, e.g. a commit hash
+    Revision(String),
+    /// The last git revision of the vault file at or before a time
+    Time(DateTime<Utc>),
+}
+
+impl VaultSnapshot {
+    /// Interpret `spec` as `current`, an existing file, a timestamp or a git revision
+    ///
+    /// Timestamps are RFC 3339, or `YYYY-MM-DD[ HH:MM[:SS]]` in local time.
+    pub fn parse(spec: &str) -> Self {
+        if spec.eq_ignore_ascii_case("current") {
+            return Self::Current;
+        }
+        if Path::new(spec).is_file() {
+            return Self::File(PathBuf::from(spec));
+        }
+        match parse_time(spec) {
+            Some(time) => Self::Time(time),
+            None => Self::Revision(spec.to_string()),
+        }
+    }
+
+    /// Encrypted container of this snapshot of the vault in `storage`
+    pub fn container(&self, storage: &VaultStorage) -> Result<EncryptedVault> {
+        match self {
+            Self::Current => storage.load_container(),
+            Self::File(path) => EncryptedVault::from_slice(&fs::read(path)?),
+            #[cfg(feature = "git")]
+            Self::Revision(revision) => {
+                EncryptedVault::from_slice(&git(storage)?.vault_file_at(revision)?)
+            }
+            #[cfg(feature = "git")]
+            Self::Time(time) => {
+                let sync = git(storage)?;
+                let revision = sync
+                    .vault_revisions()?
+                    .into_iter()
+                    .find(|r| r.time <= *time)
+                    .ok_or_else(|| {
+                        Error::Other(format!("The vault has no revision before {}", time))
+                    })?;
+                EncryptedVault::from_slice(&sync.vault_file_at(&revision.commit)?)
+            }
+            #[cfg(not(feature = "git"))]
+            Self::Revision(_) | Self::Time(_) => Err(Error::Other(
+                "Git revisions require the git feature".to_string(),
+            )),
+        }
+    }
+
+    /// Decrypt this snapshot with the current data key
+    pub fn open(&self, storage: &VaultStorage, key: &EncryptionKey) -> Result<Vault> {
+        let container = self.container(storage)?;
+        if container.is_legacy() {
+            return Err(Error::KeyMismatch);
+        }
+        container.open(key)
+    }
+
+    /// Decrypt this snapshot with the password it was protected by
+    pub fn open_with_password<'a>(
+        &self,
+        storage: &VaultStorage,
+        credentials: impl Into<Credentials<'a>>,
+    ) -> Result<Vault> {
+        self.container(storage)?.open_with_password(credentials)
+    }
+}
+
+impl fmt::Display for VaultSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Current => write!(f, "current vault"),
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::Revision(revision) => write!(f, "revision {}", revision),
+            Self::Time(time) => write!(f, "vault as of {}", time.format("%Y-%m-%d %H:%M UTC")),
+        }
+    }
+}
+
+/// Replace the items and folders of the vault in `storage` with those of `snapshot`
+///
+/// The vault is backed up first and its keyslots are kept, so passwords
+/// removed since the snapshot do not unlock it again. Returns the backup
+/// path and the restored vault.
+pub fn restore(
+    storage: &VaultStorage,
+    key: &EncryptionKey,
+    snapshot: Vault,
+) -> Result<(PathBuf, Vault)> {
+    let backup = storage.backup_before("restore")?;
+    let (vault, ()) = storage.update_with_key(key, |vault| {
+        vault.folders = snapshot.folders;
+        vault.items = snapshot.items;
+        vault.sync_time = Utc::now();
+        Ok::<_, Error>(())
+    })?;
+    Ok((backup, vault))
+}
+
+fn parse_time(spec: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(spec) {
+        return Some(time.with_timezone(&Utc));
+    }
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(spec, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(spec, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(23, 59, 59))
+        })?;
+    naive
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// Git repository of the vault, without creating one
+#[cfg(feature = "git")]
+fn git(storage: &VaultStorage) -> Result<crate::git_sync::GitSync> {
+    let dir = storage.path().parent().unwrap_or_else(|| Path::new("."));
+    if !dir.join(".git").exists() {
+        return Err(Error::Other(
+            "The vault is not under git version control".to_string(),
+        ));
+    }
+    crate::git_sync::GitSync::init(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Item;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_snapshot() {
+        assert_eq!(VaultSnapshot::parse("current"), VaultSnapshot::Current);
+        assert_eq!(
+            VaultSnapshot::parse("a1b2c3d"),
+            VaultSnapshot::Revision("a1b2c3d".to_string())
+        );
+        assert_eq!(
+            VaultSnapshot::parse("2024-05-01T12:00:00Z"),
+            VaultSnapshot::Time("2024-05-01T12:00:00Z".parse().unwrap())
+        );
+        assert!(matches!(
+            VaultSnapshot::parse("2024-05-01"),
+            VaultSnapshot::Time(_)
+        ));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path().to_str().unwrap();
+        assert_eq!(
+            VaultSnapshot::parse(path),
+            VaultSnapshot::File(file.path().to_path_buf())
+        );
+    }
+
+    #[test]
+    fn test_restore_from_backup_keeps_keyslots() {
+        let temp_dir = tempdir().unwrap();
+        let storage = VaultStorage::with_path(temp_dir.path().join("vault.sf"));
+        let mut vault = Vault::new();
+        vault.add_item(Item::new_login("old"));
+        storage.save(&vault, "old password").unwrap();
+        let (mut vault, key) = storage.unlock("old password").unwrap();
+        let backup = VaultSnapshot::File(storage.backup().unwrap());
+
+        vault.items.clear();
+        vault.add_item(Item::new_login("new"));
+        storage.save_with_key(&vault, &key).unwrap();
+        storage
+            .change_password("old password", "new password", None, false)
+            .unwrap();
+
+        let snapshot = backup.open(&storage, &key).unwrap();
+        assert_eq!(snapshot.items[0].name, "old");
+        let (saved, restored) = restore(&storage, &key, snapshot).unwrap();
+        assert!(saved.exists());
+        assert_eq!(restored.items[0].name, "old");
+
+        // The old password stays revoked
+        assert!(storage.load("old password").is_err());
+        assert_eq!(storage.load("new password").unwrap().items[0].name, "old");
+    }
+}
@@ -0,0 +1,357 @@
+//! Minimal ssh-agent client used by SSH key keyslots
+//!
+//! Speaks the agent protocol over the socket in `SSH_AUTH_SOCK`: listing
+//! identities and signing data. Only Ed25519 keys are used, as their
+//! signatures are deterministic: signing the same challenge always yields
+//! the same bytes, which can then serve as a keyslot secret.
+
+#[cfg(unix)]
+use std::io::{Read, Write};
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD_NO_PAD as BASE64_NO_PAD, Engine};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::errors::{Error, Result};
+
+/// Environment variable holding the agent socket path
+pub const AUTH_SOCK_ENV: &str = "SSH_AUTH_SOCK";
+
+/// Key type of Ed25519 keys
+pub const ED25519_KEY_TYPE: &str = "ssh-ed25519";
+
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH2_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH2_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH2_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH2_AGENT_SIGN_RESPONSE: u8 = 14;
+
+/// Largest agent reply accepted
+const MAX_MESSAGE_SIZE: usize = 256 * 1024;
+
+/// A public key held by the agent
+#[derive(Debug, Clone)]
+pub struct SshIdentity {
+    /// Public key in SSH wire format
+    pub key_blob: Vec<u8>,
+    pub comment: String,
+}
+
+impl SshIdentity {
+    /// Key type, e.g. `ssh-ed25519`
+    pub fn key_type(&self) -> String {
+        Reader::new(&self.key_blob)
+            .string()
+            .map(|t| String::from_utf8_lossy(t).into_owned())
+            .unwrap_or_default()
+    }
+
+    /// OpenSSH SHA256 fingerprint, as printed by `ssh-add -l`
+    pub fn fingerprint(&self) -> String {
+        format!(
+            "SHA256:{}",
+            BASE64_NO_PAD.encode(Sha256::digest(&self.key_blob))
+        )
+    }
+
+    /// Whether `fingerprint` names this key; the `SHA256:` prefix is optional
+    pub fn matches(&self, fingerprint: &str) -> bool {
+        same_fingerprint(&self.fingerprint(), fingerprint)
+    }
+}
+
+/// Whether two SHA256 fingerprints are equal, ignoring an optional `SHA256:` prefix
+pub fn same_fingerprint(a: &str, b: &str) -> bool {
+    let strip = |f: &str| f.strip_prefix("SHA256:").unwrap_or(f).to_string();
+    strip(a) == strip(b)
+}
+
+/// Connection to a running ssh-agent
+pub struct SshAgent {
+    #[cfg(unix)]
+    stream: std::os::unix::net::UnixStream,
+}
+
+impl SshAgent {
+    /// Connect to the agent named by `SSH_AUTH_SOCK`
+    pub fn connect_env() -> Result<Self> {
+        let path = std::env::var_os(AUTH_SOCK_ENV).ok_or_else(|| {
+            Error::SshAgent(format!(
+                "{} is not set; is ssh-agent running?",
+                AUTH_SOCK_ENV
+            ))
+        })?;
+        Self::connect(Path::new(&path))
+    }
+
+    /// Connect to the agent listening on `path`
+    #[cfg(unix)]
+    pub fn connect(path: &Path) -> Result<Self> {
+        let stream = std::os::unix::net::UnixStream::connect(path)
+            .map_err(|e| Error::SshAgent(format!("cannot connect to {}: {}", path.display(), e)))?;
+        Ok(Self { stream })
+    }
+
+    #[cfg(not(unix))]
+    pub fn connect(_path: &Path) -> Result<Self> {
+        Err(Error::SshAgent(
+            "ssh-agent is only supported on Unix".to_string(),
+        ))
+    }
+
+    /// Keys currently held by the agent
+    pub fn identities(&mut self) -> Result<Vec<SshIdentity>> {
+        let reply = self.request(SSH2_AGENTC_REQUEST_IDENTITIES, &[])?;
+        let mut reader = Reader::new(&reply);
+        expect_type(reader.byte()?, SSH2_AGENT_IDENTITIES_ANSWER)?;
+
+        let count = reader.u32()?;
+        (0..count)
+            .map(|_| {
+                Ok(SshIdentity {
+                    key_blob: reader.string()?.to_vec(),
+                    comment: String::from_utf8_lossy(reader.string()?).into_owned(),
+                })
+            })
+            .collect()
+    }
+
+    /// Find the Ed25519 key with `fingerprint`
+    pub fn identity(&mut self, fingerprint: &str) -> Result<SshIdentity> {
+        let identity = self
+            .identities()?
+            .into_iter()
+            .find(|i| i.matches(fingerprint))
+            .ok_or_else(|| {
+                Error::SshAgent(format!("ssh-agent does not hold key {}", fingerprint))
+            })?;
+
+        if identity.key_type() != ED25519_KEY_TYPE {
+            return Err(Error::SshAgent(format!(
+                "{} is a {} key; only {} keys sign deterministically",
+                fingerprint,
+                identity.key_type(),
+                ED25519_KEY_TYPE
+            )));
+        }
+        Ok(identity)
+    }
+
+    /// Raw signature of `data` by `identity`
+    pub fn sign(&mut self, identity: &SshIdentity, data: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        let mut payload = Vec::new();
+        put_string(&mut payload, &identity.key_blob);
+        put_string(&mut payload, data);
+        payload.extend_from_slice(&0u32.to_be_bytes());
+
+        let reply = Zeroizing::new(self.request(SSH2_AGENTC_SIGN_REQUEST, &payload)?);
+        let mut reader = Reader::new(&reply);
+        expect_type(reader.byte()?, SSH2_AGENT_SIGN_RESPONSE)?;
+
+        // The signature blob is the algorithm name followed by the raw signature
+        let mut signature = Reader::new(reader.string()?);
+        signature.string()?;
+        Ok(Zeroizing::new(signature.string()?.to_vec()))
+    }
+
+    #[cfg(unix)]
+    fn request(&mut self, message_type: u8, payload: &[u8]) -> Result<Vec<u8>> {
+        let mut message = Vec::with_capacity(payload.len() + 5);
+        message.extend_from_slice(&(payload.len() as u32 + 1).to_be_bytes());
+        message.push(message_type);
+        message.extend_from_slice(payload);
+        self.stream.write_all(&message)?;
+
+        let mut length = [0u8; 4];
+        self.stream.read_exact(&mut length)?;
+        let length = u32::from_be_bytes(length) as usize;
+        if length == 0 || length > MAX_MESSAGE_SIZE {
+            return Err(Error::SshAgent(format!("invalid reply length {}", length)));
+        }
+
+        let mut reply = vec![0u8; length];
+        self.stream.read_exact(&mut reply)?;
+        Ok(reply)
+    }
+
+    #[cfg(not(unix))]
+    fn request(&mut self, _message_type: u8, _payload: &[u8]) -> Result<Vec<u8>> {
+        Err(Error::SshAgent(
+            "ssh-agent is only supported on Unix".to_string(),
+        ))
+    }
+}
+
+fn expect_type(found: u8, expected: u8) -> Result<()> {
+    match found {
+        t if t == expected => Ok(()),
+        SSH_AGENT_FAILURE => Err(Error::SshAgent("agent refused the request".to_string())),
+        t => Err(Error::SshAgent(format!(
+            "unexpected agent reply type {}",
+            t
+        ))),
+    }
+}
+
+fn put_string(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+}
+
+/// Reader of SSH wire format values
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(Error::SshAgent("truncated agent reply".to_string()));
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+}
+
+/// A throwaway ssh-agent holding one fresh Ed25519 key, for tests
+#[cfg(all(test, unix))]
+pub(crate) mod test_agent {
+    use std::path::PathBuf;
+    use std::process::{Child, Command, Stdio};
+    use std::time::{Duration, Instant};
+
+    pub struct TestAgent {
+        child: Child,
+        pub socket: PathBuf,
+        pub fingerprint: String,
+        _dir: tempfile::TempDir,
+    }
+
+    impl TestAgent {
+        /// Spawn an agent, or `None` when OpenSSH is not installed
+        pub fn spawn() -> Option<Self> {
+            let dir = tempfile::tempdir().unwrap();
+            let socket = dir.path().join("agent.sock");
+            let key = dir.path().join("id_ed25519");
+
+            let keygen = Command::new("ssh-keygen")
+                .args([
+                    "-q",
+                    "-t",
+                    "ed25519",
+                    "-N",
+                    "",
+                    "-C",
+                    "securefox-test",
+                    "-f",
+                ])
+                .arg(&key)
+                .stdout(Stdio::null())
+                .status()
+                .ok()?;
+            assert!(keygen.success());
+
+            let child = Command::new("ssh-agent")
+                .arg("-D")
+                .arg("-a")
+                .arg(&socket)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut agent = Self {
+                child,
+                socket,
+                fingerprint: String::new(),
+                _dir: dir,
+            };
+
+            let started = Instant::now();
+            while !agent.socket.exists() {
+                assert!(started.elapsed() < Duration::from_secs(10));
+                std::thread::sleep(Duration::from_millis(20));
+            }
+
+            let added = Command::new("ssh-add")
+                .arg(&key)
+                .env(super::AUTH_SOCK_ENV, &agent.socket)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .unwrap();
+            assert!(added.success());
+
+            let output = Command::new("ssh-keygen")
+                .args(["-l", "-E", "sha256", "-f"])
+                .arg(&key)
+                .output()
+                .unwrap();
+            agent.fingerprint = String::from_utf8(output.stdout)
+                .unwrap()
+                .split_whitespace()
+                .nth(1)
+                .unwrap()
+                .to_string();
+
+            Some(agent)
+        }
+    }
+
+    impl Drop for TestAgent {
+        fn drop(&mut self) {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::test_agent::TestAgent;
+    use super::*;
+
+    #[test]
+    fn test_agent_identities_and_deterministic_signatures() {
+        let Some(test_agent) = TestAgent::spawn() else {
+            return;
+        };
+        let mut agent = SshAgent::connect(&test_agent.socket).unwrap();
+
+        let identities = agent.identities().unwrap();
+        assert_eq!(identities.len(), 1);
+        assert_eq!(identities[0].comment, "securefox-test");
+        assert_eq!(identities[0].fingerprint(), test_agent.fingerprint);
+
+        let identity = agent.identity(&test_agent.fingerprint).unwrap();
+        let first = agent.sign(&identity, b"challenge").unwrap();
+        let second = agent.sign(&identity, b"challenge").unwrap();
+        assert_eq!(first.len(), 64);
+        assert_eq!(first, second);
+        assert_ne!(first, agent.sign(&identity, b"other").unwrap());
+
+        assert!(matches!(
+            agent.identity("SHA256:unknown"),
+            Err(Error::SshAgent(_))
+        ));
+    }
+}
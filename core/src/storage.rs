@@ -1,50 +1,404 @@
 //! Vault storage management
 
-use std::fs;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
+use chrono::{Duration, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use zeroize::Zeroizing;
 
+use crate::attachments::{AttachmentStore, ATTACHMENTS_DIR};
+use crate::backup::{BackupStore, BACKUPS_DIR};
+use crate::config::ConfigManager;
 use crate::crypto::{
-    decrypt_with_password, encrypt_with_password, encrypt_with_password_and_kdf, EncryptedData,
-    KdfParams,
+    decrypt_with_aad, decrypt_with_legacy_argon2, decrypt_with_password, encrypt_with_aad,
+    generate_key, key_check, legacy_argon2_memory_kb, matches_key_check, EncryptedData,
+    EncryptionKey, KdfAlgorithm, KdfParams,
 };
 use crate::errors::{Error, Result};
-use crate::models::Vault;
+use crate::keyslot::{Credentials, Keyslot, KeyslotKind};
+use crate::migration::{self, ContainerFormat, CONTAINER_VERSION};
+use crate::models::{BackupPolicy, PinPolicy};
+use crate::models::{Item, Vault};
+use crate::pin::PinStore;
+use crate::ssh_agent::{same_fingerprint, SshAgent};
 
 /// Default vault file name
-const VAULT_FILE_NAME: &str = "vault.sf";
-
-/// Default config directory name
-const CONFIG_DIR_NAME: &str = ".securefox";
+pub(crate) const VAULT_FILE_NAME: &str = "vault.sf";
 
 /// Encrypted vault container
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EncryptedVault {
     pub version: String,
+    /// Identity of the vault, bound to the payload and keyslots since format 3.0.0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault_id: Option<String>,
+    /// Wrapped copies of the data key; empty for legacy password-encrypted vaults
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keyslots: Vec<Keyslot>,
+    /// Key check value of the data key, used to tell a wrong key from corruption
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_check: Option<String>,
     pub encrypted_data: EncryptedData,
 }
 
+impl EncryptedVault {
+    /// Encrypt a vault with the data key and attach the given keyslots
+    pub fn seal(
+        vault: &Vault,
+        key: &EncryptionKey,
+        vault_id: &str,
+        keyslots: Vec<Keyslot>,
+    ) -> Result<Self> {
+        let json = Zeroizing::new(serde_json::to_vec(vault)?);
+        let mut container = Self {
+            version: CONTAINER_VERSION.to_string(),
+            vault_id: Some(vault_id.to_string()),
+            keyslots,
+            key_check: Some(key_check(key)),
+            encrypted_data: EncryptedData {
+                kdf_params: None,
+                nonce: String::new(),
+                ciphertext: String::new(),
+            },
+        };
+        container.encrypted_data = encrypt_with_aad(&json, key, &container.associated_data())?;
+        Ok(container)
+    }
+
+    /// Parse a container, e.g. a past revision or a backup of the vault file
+    pub fn from_slice(contents: &[u8]) -> Result<Self> {
+        let container: Self = serde_json::from_slice(contents)?;
+        container.format()?;
+        Ok(container)
+    }
+
+    /// Generate a new vault identity
+    pub fn new_vault_id() -> String {
+        Uuid::new_v4().to_string()
+    }
+
+    /// Vault identity, or a fresh one for containers written before format 3.0.0
+    pub fn vault_id_or_new(&self) -> String {
+        self.vault_id.clone().unwrap_or_else(Self::new_vault_id)
+    }
+
+    /// Container layout, refusing versions written by a newer SecureFox
+    pub fn format(&self) -> Result<ContainerFormat> {
+        ContainerFormat::detect(&self.version)
+    }
+
+    /// Whether the payload authenticates the container header
+    fn is_bound(&self) -> bool {
+        matches!(self.format(), Ok(ContainerFormat::Bound))
+    }
+
+    /// Associated data binding the format version and vault identity to the payload
+    ///
+    /// Empty for containers written before format 3.0.0. Downgrading the version
+    /// field of a bound container therefore also fails authentication.
+    fn associated_data(&self) -> Vec<u8> {
+        if !self.is_bound() {
+            return Vec::new();
+        }
+        format!(
+            "securefox vault|{}|{}",
+            self.version,
+            self.vault_id.as_deref().unwrap_or_default()
+        )
+        .into_bytes()
+    }
+
+    /// Whether the container was written in an older keyslot format
+    pub fn needs_upgrade(&self) -> bool {
+        !self.is_legacy() && self.version != CONTAINER_VERSION
+    }
+
+    /// Re-encrypt the payload in the current format, keeping the data key and keyslots
+    ///
+    /// Keyslots written before format 3.0.0 stay unbound until they are re-wrapped.
+    pub fn upgrade(&mut self, key: &EncryptionKey) -> Result<()> {
+        let vault = self.open(key)?;
+        let vault_id = self.vault_id_or_new();
+        let keyslots = std::mem::take(&mut self.keyslots);
+        *self = Self::seal(&vault, key, &vault_id, keyslots)?;
+        Ok(())
+    }
+
+    /// Whether the payload is encrypted directly with the master password
+    pub fn is_legacy(&self) -> bool {
+        self.keyslots.is_empty()
+    }
+
+    /// Recover the data key from the first password keyslot that opens
+    pub fn unlock_with_password<'a>(
+        &self,
+        credentials: impl Into<Credentials<'a>>,
+    ) -> Result<EncryptionKey> {
+        self.unlock_password_slot(credentials).map(|(_, key)| key)
+    }
+
+    /// Find the password keyslot opened by `credentials`, returning its index and the data key
+    ///
+    /// Password-only credentials try password slots, credentials with a keyfile
+    /// try password+keyfile slots.
+    pub fn unlock_password_slot<'a>(
+        &self,
+        credentials: impl Into<Credentials<'a>>,
+    ) -> Result<(usize, EncryptionKey)> {
+        let credentials = credentials.into();
+        let kind = credentials.kind();
+
+        let mut found_slot = false;
+        let mut keyfile_error = None;
+        for (index, slot) in self.keyslots.iter().enumerate() {
+            if slot.kind != kind {
+                continue;
+            }
+            found_slot = true;
+            if slot.vault_id.is_some() && slot.vault_id != self.vault_id {
+                return Err(Error::Decryption(format!(
+                    "Keyslot {} belongs to another vault",
+                    slot.id
+                )));
+            }
+            match slot.unlock(credentials) {
+                Ok(key) => return Ok((index, key)),
+                Err(Error::InvalidPassword) => continue,
+                Err(e @ Error::Keyfile(_)) => keyfile_error = Some(e),
+                Err(e) => return Err(e),
+            }
+        }
+
+        if found_slot {
+            return Err(keyfile_error.unwrap_or(Error::InvalidPassword));
+        }
+
+        let has_kind = |kind| self.keyslots.iter().any(|s| s.kind == kind);
+        match kind {
+            KeyslotKind::Password if has_kind(KeyslotKind::PasswordKeyfile) => {
+                Err(Error::KeyfileRequired)
+            }
+            KeyslotKind::PasswordKeyfile if has_kind(KeyslotKind::Password) => Err(Error::Keyfile(
+                "this vault is not protected by a keyfile".to_string(),
+            )),
+            _ => Err(Error::Decryption(
+                "Vault has no password keyslot".to_string(),
+            )),
+        }
+    }
+
+    /// Find the recovery keyslot opened by `recovery_key`, returning its index and the data key
+    pub fn unlock_recovery_slot(
+        &self,
+        recovery_key: &EncryptionKey,
+    ) -> Result<(usize, EncryptionKey)> {
+        let mut found_slot = false;
+        for (index, slot) in self.keyslots.iter().enumerate() {
+            if slot.kind != KeyslotKind::Recovery {
+                continue;
+            }
+            found_slot = true;
+            match slot.unwrap_key(recovery_key.as_bytes()) {
+                Ok(key) => return Ok((index, key)),
+                Err(Error::InvalidPassword) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        Err(Error::Recovery(if found_slot {
+            "the shares do not match this vault's recovery key".to_string()
+        } else {
+            "this vault has no recovery keyslot".to_string()
+        }))
+    }
+
+    /// Open an SSH keyslot with a key held by `agent`, returning its index and the data key
+    ///
+    /// With a `fingerprint` only that key is tried, otherwise any slot whose
+    /// key the agent holds.
+    pub fn unlock_ssh_slot(
+        &self,
+        agent: &mut SshAgent,
+        fingerprint: Option<&str>,
+    ) -> Result<(usize, EncryptionKey)> {
+        let identities = agent.identities()?;
+        let mut found_slot = false;
+
+        for (index, slot) in self.keyslots.iter().enumerate() {
+            let Some(slot_key) = slot.ssh_key.as_deref() else {
+                continue;
+            };
+            if slot.kind != KeyslotKind::SshAgent {
+                continue;
+            }
+            found_slot = true;
+            if fingerprint.is_some_and(|f| !same_fingerprint(f, slot_key)) {
+                continue;
+            }
+
+            if let Some(identity) = identities.iter().find(|i| i.matches(slot_key)) {
+                match slot.unlock_with_agent(agent, identity) {
+                    Ok(key) => return Ok((index, key)),
+                    Err(Error::InvalidPassword) => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        Err(Error::SshAgent(match (found_slot, fingerprint) {
+            (false, _) => "this vault has no SSH key keyslot".to_string(),
+            (true, Some(fingerprint)) => {
+                format!(
+                    "no keyslot of this vault opens with {} in ssh-agent",
+                    fingerprint
+                )
+            }
+            (true, None) => "ssh-agent holds none of this vault's SSH keys".to_string(),
+        }))
+    }
+
+    /// Whether `key` is the data key of this vault, without decrypting the payload
+    pub fn matches_key(&self, key: &EncryptionKey) -> bool {
+        match &self.key_check {
+            Some(check) => matches_key_check(key, check),
+            None => decrypt_with_aad(&self.encrypted_data, key, &self.associated_data()).is_ok(),
+        }
+    }
+
+    /// Decrypt the payload with the data key
+    pub fn open(&self, key: &EncryptionKey) -> Result<Vault> {
+        self.open_and_migrate(key).map(|(vault, _)| vault)
+    }
+
+    /// Decrypt the payload, reporting whether it was migrated from an older version
+    pub fn open_and_migrate(&self, key: &EncryptionKey) -> Result<(Vault, bool)> {
+        if let Some(check) = &self.key_check {
+            if !matches_key_check(key, check) {
+                return Err(Error::KeyMismatch);
+            }
+        }
+
+        let decrypted = Zeroizing::new(decrypt_with_aad(
+            &self.encrypted_data,
+            key,
+            &self.associated_data(),
+        )?);
+        migration::read_payload(&decrypted)
+    }
+
+    /// Decrypt the payload with the master password, handling legacy containers
+    pub fn open_with_password<'a>(&self, credentials: impl Into<Credentials<'a>>) -> Result<Vault> {
+        let credentials = credentials.into();
+        if self.is_legacy() {
+            if credentials.keyfile.is_some() {
+                return Err(Error::Keyfile(
+                    "this vault is not protected by a keyfile".to_string(),
+                ));
+            }
+
+            // Argon2id containers from before keyslots used a 1024x memory cost;
+            // it can take many GiB, so it is only tried on request
+            let legacy_argon2_kb = self
+                .encrypted_data
+                .kdf_params
+                .as_ref()
+                .filter(|params| matches!(params.algorithm, KdfAlgorithm::Argon2id))
+                .map(legacy_argon2_memory_kb)
+                .transpose()?;
+            let decrypted = match legacy_argon2_kb {
+                Some(_) if credentials.legacy_argon2 => {
+                    decrypt_with_legacy_argon2(&self.encrypted_data, credentials.password)
+                }
+                _ => decrypt_with_password(&self.encrypted_data, credentials.password),
+            };
+
+            // Legacy containers have no check value; a failure most likely means a typo
+            let decrypted = Zeroizing::new(decrypted.map_err(|_| match legacy_argon2_kb {
+                Some(kb) if !credentials.legacy_argon2 => {
+                    Error::LegacyArgon2Password(kb.div_ceil(1024 * 1024))
+                }
+                _ => Error::InvalidPassword,
+            })?);
+            return migration::read_payload(&decrypted).map(|(vault, _)| vault);
+        }
+
+        let key = self.unlock_with_password(credentials)?;
+        self.open(&key)
+    }
+
+    /// Add a keyslot wrapping the data key
+    pub fn add_keyslot(&mut self, slot: Keyslot) {
+        self.keyslots.push(slot);
+    }
+
+    /// Remove a keyslot by id, refusing to remove the last one
+    pub fn remove_keyslot(&mut self, id: &str) -> Result<Keyslot> {
+        let pos = self
+            .keyslots
+            .iter()
+            .position(|s| s.id == id)
+            .ok_or_else(|| Error::Other(format!("Keyslot not found: {}", id)))?;
+
+        if self.keyslots.len() == 1 {
+            return Err(Error::Other(
+                "Cannot remove the last keyslot of a vault".to_string(),
+            ));
+        }
+
+        Ok(self.keyslots.remove(pos))
+    }
+}
+
+/// Fingerprint of the vault file contents, used to detect concurrent writes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision([u8; 32]);
+
+impl Revision {
+    fn of(contents: &[u8]) -> Self {
+        Self(Sha256::digest(contents).into())
+    }
+}
+
+/// Exclusive advisory lock on a vault, released when dropped
+///
+/// The lock lives on a sibling `.lock` file because writes replace the vault
+/// file itself. It only excludes other SecureFox processes that also lock.
+pub struct VaultLock {
+    _file: File,
+}
+
 /// Vault storage manager
+///
+/// Each instance remembers the revision of the vault file it last read or
+/// wrote. Saving a vault read through this instance fails with
+/// `Error::StaleWrite` if another writer changed the file in between.
 pub struct VaultStorage {
     vault_path: PathBuf,
+    revision: Mutex<Option<Revision>>,
 }
 
 impl VaultStorage {
-    /// Create a new vault storage with default path
+    /// Create a vault storage for the current vault profile
     pub fn new() -> Result<Self> {
-        let home = dirs::home_dir()
-            .ok_or_else(|| Error::Other("Could not determine home directory".to_string()))?;
-        let config_dir = home.join(CONFIG_DIR_NAME);
-        Ok(Self {
-            vault_path: config_dir.join(VAULT_FILE_NAME),
-        })
+        Self::for_profile(None)
+    }
+
+    /// Create a vault storage for the vault registered as `profile`, or the current one
+    pub fn for_profile(profile: Option<&str>) -> Result<Self> {
+        let vault_dir = ConfigManager::new()?.vault_dir(profile)?;
+        Ok(Self::with_path(vault_dir.join(VAULT_FILE_NAME)))
     }
 
     /// Create a vault storage with custom path
     pub fn with_path<P: AsRef<Path>>(path: P) -> Self {
         Self {
             vault_path: path.as_ref().to_path_buf(),
+            revision: Mutex::new(None),
         }
     }
 
@@ -66,30 +420,168 @@ impl VaultStorage {
         Ok(())
     }
 
-    /// Save a vault with encryption using default KDF (PBKDF2)
-    pub fn save(&self, vault: &Vault, password: &str) -> Result<()> {
-        self.save_internal(vault, password, true)
+    /// Path next to the vault file with `suffix` appended to its name
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut name = self
+            .vault_path
+            .file_name()
+            .map(|n| n.to_os_string())
+            .unwrap_or_else(|| VAULT_FILE_NAME.into());
+        name.push(suffix);
+        self.vault_path.with_file_name(name)
     }
 
-    /// Internal save method with optional sync
-    fn save_internal(&self, vault: &Vault, password: &str, trigger_sync: bool) -> Result<()> {
+    /// Take the exclusive vault lock, blocking until other writers release it
+    ///
+    /// Hold the returned guard across a load-modify-save cycle. The methods of
+    /// this type take the lock themselves, so do not call them while holding it.
+    pub fn lock(&self) -> Result<VaultLock> {
         self.ensure_directory()?;
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.sibling(".lock"))?;
+        file.lock()?;
+        Ok(VaultLock { _file: file })
+    }
 
-        // Serialize vault to JSON
-        let json = serde_json::to_vec(vault)?;
+    /// Revision of the vault file this instance last read or wrote
+    pub fn revision(&self) -> Option<Revision> {
+        self.revision
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
 
-        // Encrypt the JSON with default KDF
-        let encrypted_data = encrypt_with_password(&json, password)?;
+    fn remember(&self, revision: Revision) {
+        *self.revision.lock().unwrap_or_else(|e| e.into_inner()) = Some(revision);
+    }
 
-        // Create encrypted vault container
-        let encrypted_vault = EncryptedVault {
-            version: "1.0.0".to_string(),
-            encrypted_data,
-        };
+    /// Fail if the file changed since this instance last read or wrote it
+    fn ensure_current(&self, on_disk: &Revision) -> Result<()> {
+        match self.revision() {
+            Some(seen) if &seen != on_disk => Err(Error::StaleWrite),
+            _ => Ok(()),
+        }
+    }
+
+    /// Read and parse the container along with its revision
+    fn read(&self) -> Result<(EncryptedVault, Revision)> {
+        if !self.exists() {
+            return Err(Error::VaultNotFound);
+        }
+
+        let contents = fs::read(&self.vault_path)?;
+        let container = EncryptedVault::from_slice(&contents)?;
+        Ok((container, Revision::of(&contents)))
+    }
+
+    /// Atomically replace the vault file and record the new revision
+    ///
+    /// `read` is the revision the change was based on. The remembered revision
+    /// only moves forward if it matched, so a stale in-memory vault stays stale.
+    fn commit(&self, container: &EncryptedVault, read: Option<&Revision>) -> Result<()> {
+        let written = self.write_atomic(container)?;
+
+        let mut seen = self.revision.lock().unwrap_or_else(|e| e.into_inner());
+        if seen.is_none() || seen.as_ref() == read {
+            *seen = Some(written);
+        }
+        Ok(())
+    }
+
+    /// Write to a temporary file, fsync it and rename it over the vault file
+    fn write_atomic(&self, container: &EncryptedVault) -> Result<Revision> {
+        self.ensure_directory()?;
+        let contents = serde_json::to_string_pretty(container)?;
+        let temp_path = self.sibling(".tmp");
+
+        let mut options = OpenOptions::new();
+        options.create(true).truncate(true).write(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
 
-        // Save to file
-        let contents = serde_json::to_string_pretty(&encrypted_vault)?;
-        fs::write(&self.vault_path, contents)?;
+        let mut file = options.open(&temp_path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&temp_path, &self.vault_path)?;
+
+        // Persist the rename itself
+        #[cfg(unix)]
+        if let Some(parent) = self.vault_path.parent() {
+            let dir = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            File::open(dir)?.sync_all()?;
+        }
+
+        Ok(Revision::of(contents.as_bytes()))
+    }
+
+    /// Save a vault, reusing the existing keyslots when the password opens one
+    ///
+    /// New vaults get a fresh data key and a PBKDF2 password keyslot; legacy
+    /// vaults are upgraded to keyslots keeping their KDF settings.
+    pub fn save<'a>(&self, vault: &Vault, credentials: impl Into<Credentials<'a>>) -> Result<()> {
+        self.save_internal(vault, credentials.into(), true)
+    }
+
+    /// Internal save method with optional sync
+    fn save_internal(
+        &self,
+        vault: &Vault,
+        credentials: Credentials<'_>,
+        trigger_sync: bool,
+    ) -> Result<()> {
+        {
+            let _lock = self.lock()?;
+            let existing = if self.exists() {
+                let (container, revision) = self.read()?;
+                self.ensure_current(&revision)?;
+                Some((container, revision))
+            } else {
+                None
+            };
+
+            let (key, vault_id, keyslots, read) = match existing {
+                Some((container, revision)) if !container.is_legacy() => {
+                    let key = container.unlock_with_password(credentials)?;
+                    (
+                        key,
+                        container.vault_id_or_new(),
+                        container.keyslots,
+                        Some(revision),
+                    )
+                }
+                Some((container, revision)) => {
+                    let vault_id = EncryptedVault::new_vault_id();
+                    let (_, key, slot) = Self::upgrade_legacy(&container, credentials, &vault_id)?;
+                    (key, vault_id, vec![slot], Some(revision))
+                }
+                None => {
+                    let key = generate_key();
+                    let vault_id = EncryptedVault::new_vault_id();
+                    let slot = Keyslot::from_credentials(
+                        credentials,
+                        KdfParams::default(),
+                        &key,
+                        &vault_id,
+                    )?;
+                    (key, vault_id, vec![slot], None)
+                }
+            };
+
+            let container = EncryptedVault::seal(vault, &key, &vault_id, keyslots)?;
+            self.commit(&container, read.as_ref())?;
+        }
 
         // Trigger git sync if enabled and configured for PushOnChange
         #[cfg(feature = "git")]
@@ -100,30 +592,147 @@ impl VaultStorage {
         Ok(())
     }
 
-    /// Save a vault with encryption using specified KDF
-    pub fn save_with_kdf(
+    /// Save a vault with a cached data key, keeping the keyslots on disk
+    ///
+    /// No key derivation runs, so this is cheap even with heavy KDF settings.
+    pub fn save_with_key(&self, vault: &Vault, key: &EncryptionKey) -> Result<()> {
+        {
+            let _lock = self.lock()?;
+            let (container, revision) = self.read()?;
+            self.ensure_current(&revision)?;
+            let container = Self::reseal_with_key(container, vault, key)?;
+            self.commit(&container, Some(&revision))?;
+        }
+
+        #[cfg(feature = "git")]
+        self.try_auto_sync(vault)?;
+
+        Ok(())
+    }
+
+    /// Load, modify and save the vault under the vault lock
+    ///
+    /// The vault is read fresh from disk, so changes made by other writers are
+    /// kept. Nothing is written if `update` fails.
+    pub fn update_with_key<T, E>(
         &self,
+        key: &EncryptionKey,
+        update: impl FnOnce(&mut Vault) -> std::result::Result<T, E>,
+    ) -> std::result::Result<(Vault, T), E>
+    where
+        E: From<Error>,
+    {
+        let (vault, output) = {
+            let _lock = self.lock()?;
+            let (container, revision) = self.read()?;
+            let mut vault = container.open(key)?;
+            let output = update(&mut vault)?;
+
+            let container = Self::reseal_with_key(container, &vault, key)?;
+            self.commit(&container, Some(&revision))?;
+            (vault, output)
+        };
+
+        #[cfg(feature = "git")]
+        self.try_auto_sync(&vault)?;
+
+        Ok((vault, output))
+    }
+
+    /// Permanently delete items trashed more than `retention_days` ago
+    ///
+    /// `vault` is replaced by the saved vault and the purged items are
+    /// returned. Nothing is written when no item has expired, and a retention
+    /// of 0 keeps trashed items forever.
+    pub fn purge_expired_trash(
+        &self,
+        vault: &mut Vault,
+        key: &EncryptionKey,
+        retention_days: u32,
+    ) -> Result<Vec<Item>> {
+        if retention_days == 0 {
+            return Ok(Vec::new());
+        }
+        let cutoff = Utc::now() - Duration::days(i64::from(retention_days));
+        if !vault
+            .trashed_items()
+            .any(|i| i.deleted_date.is_some_and(|d| d < cutoff))
+        {
+            return Ok(Vec::new());
+        }
+
+        let (purged_vault, purged) =
+            self.update_with_key(key, |v| Ok::<_, Error>(v.purge_trash(cutoff)))?;
+        *vault = purged_vault;
+        for item in &purged {
+            self.attachments().remove_all(&item.attachments)?;
+        }
+        Ok(purged)
+    }
+
+    /// Re-encrypt `vault` into an existing container, keeping its keyslots
+    fn reseal_with_key(
+        container: EncryptedVault,
         vault: &Vault,
-        password: &str,
-        kdf_params: KdfParams,
-    ) -> Result<()> {
-        self.ensure_directory()?;
+        key: &EncryptionKey,
+    ) -> Result<EncryptedVault> {
+        if container.is_legacy() {
+            return Err(Error::Other(
+                "Vault uses the legacy format; unlock it with the password first".to_string(),
+            ));
+        }
+
+        // Make sure the payload is not re-encrypted under a key no keyslot wraps
+        if !container.matches_key(key) {
+            return Err(Error::KeyMismatch);
+        }
 
-        // Serialize vault to JSON
-        let json = serde_json::to_vec(vault)?;
+        let vault_id = container.vault_id_or_new();
+        EncryptedVault::seal(vault, key, &vault_id, container.keyslots)
+    }
 
-        // Encrypt the JSON with specified KDF
-        let encrypted_data = encrypt_with_password_and_kdf(&json, password, kdf_params)?;
+    /// Decrypt a legacy container and wrap a fresh data key with its KDF settings
+    fn upgrade_legacy(
+        container: &EncryptedVault,
+        credentials: Credentials<'_>,
+        vault_id: &str,
+    ) -> Result<(Vault, EncryptionKey, Keyslot)> {
+        let vault = container.open_with_password(credentials)?;
+        let kdf_params = container
+            .encrypted_data
+            .kdf_params
+            .as_ref()
+            .map(KdfParams::with_new_salt)
+            .unwrap_or_default();
 
-        // Create encrypted vault container
-        let encrypted_vault = EncryptedVault {
-            version: "1.0.0".to_string(),
-            encrypted_data,
-        };
+        let key = generate_key();
+        let slot = Keyslot::from_credentials(credentials, kdf_params, &key, vault_id)?;
+        Ok((vault, key, slot))
+    }
 
-        // Save to file
-        let contents = serde_json::to_string_pretty(&encrypted_vault)?;
-        fs::write(&self.vault_path, contents)?;
+    /// Save a vault under a fresh data key with a single password keyslot
+    pub fn save_with_kdf<'a>(
+        &self,
+        vault: &Vault,
+        credentials: impl Into<Credentials<'a>>,
+        kdf_params: KdfParams,
+    ) -> Result<()> {
+        {
+            let _lock = self.lock()?;
+            let read = if self.exists() {
+                let (_, revision) = self.read()?;
+                self.ensure_current(&revision)?;
+                Some(revision)
+            } else {
+                None
+            };
+
+            let key = generate_key();
+            let vault_id = EncryptedVault::new_vault_id();
+            let slot = Keyslot::from_credentials(credentials.into(), kdf_params, &key, &vault_id)?;
+            let container = EncryptedVault::seal(vault, &key, &vault_id, vec![slot])?;
+            self.commit(&container, read.as_ref())?;
+        }
 
         // Trigger git sync if enabled and configured for PushOnChange
         #[cfg(feature = "git")]
@@ -132,24 +741,293 @@ impl VaultStorage {
         Ok(())
     }
 
+    /// Read the encrypted container without decrypting it
+    ///
+    /// Containers written by a newer SecureFox are refused so that saving
+    /// cannot silently drop data this version does not understand.
+    pub fn load_container(&self) -> Result<EncryptedVault> {
+        let (container, revision) = self.read()?;
+        self.remember(revision);
+        Ok(container)
+    }
+
+    /// Atomically write an encrypted container to disk under the vault lock
+    ///
+    /// Like saves, fails with `Error::StaleWrite` if the file changed since
+    /// this instance last read or wrote it. The file is not parsed, so a
+    /// damaged one can still be replaced.
+    pub fn write_container(&self, container: &EncryptedVault) -> Result<()> {
+        let _lock = self.lock()?;
+        let read = if self.exists() {
+            let revision = Revision::of(&fs::read(&self.vault_path)?);
+            self.ensure_current(&revision)?;
+            Some(revision)
+        } else {
+            None
+        };
+        self.commit(container, read.as_ref())
+    }
+
+    /// Add a keyslot to an existing vault without re-encrypting its payload
+    pub fn add_keyslot<'a>(
+        &self,
+        credentials: impl Into<Credentials<'a>>,
+        make_slot: impl FnOnce(&EncryptionKey, &str) -> Result<Keyslot>,
+    ) -> Result<Keyslot> {
+        let _lock = self.lock()?;
+        let (mut container, revision) = self.read()?;
+        if container.is_legacy() {
+            return Err(Error::Other(
+                "Vault uses the legacy format; save it once before adding keyslots".to_string(),
+            ));
+        }
+
+        let key = container.unlock_with_password(credentials)?;
+        if container.needs_upgrade() {
+            self.backup_before("migration")?;
+            container.upgrade(&key)?;
+        }
+
+        let vault_id = container.vault_id_or_new();
+        let slot = make_slot(&key, &vault_id)?;
+        container.add_keyslot(slot.clone());
+        self.commit(&container, Some(&revision))?;
+
+        Ok(slot)
+    }
+
+    /// Change the master password and/or KDF parameters, returning the backup path
+    ///
+    /// The keyslot opened by `current` is re-wrapped and the payload is left
+    /// untouched. With `rekey`, or for legacy vaults, the payload is re-encrypted
+    /// under a fresh data key and every other keyslot is dropped. Without new
+    /// `kdf_params` the previous algorithm and cost settings are kept.
+    pub fn change_password<'a, 'b>(
+        &self,
+        current: impl Into<Credentials<'a>>,
+        new: impl Into<Credentials<'b>>,
+        kdf_params: Option<KdfParams>,
+        rekey: bool,
+    ) -> Result<PathBuf> {
+        let (current, new) = (current.into(), new.into());
+        let _lock = self.lock()?;
+        let (mut container, revision) = self.read()?;
+
+        let container = if container.is_legacy() || rekey {
+            let (vault, previous_kdf) = if container.is_legacy() {
+                (
+                    container.open_with_password(current)?,
+                    container.encrypted_data.kdf_params.clone(),
+                )
+            } else {
+                let (index, key) = container.unlock_password_slot(current)?;
+                (
+                    container.open(&key)?,
+                    container.keyslots[index].kdf_params().cloned(),
+                )
+            };
+
+            let key = generate_key();
+            let vault_id = container.vault_id_or_new();
+            let kdf_params = kdf_params
+                .or_else(|| previous_kdf.map(|p| p.with_new_salt()))
+                .unwrap_or_default();
+            let slot = Keyslot::from_credentials(new, kdf_params, &key, &vault_id)?;
+            EncryptedVault::seal(&vault, &key, &vault_id, vec![slot])?
+        } else {
+            let (index, key) = container.unlock_password_slot(current)?;
+            if container.needs_upgrade() {
+                container.upgrade(&key)?;
+            }
+
+            let previous = &container.keyslots[index];
+            let vault_id = container.vault_id_or_new();
+            let kdf_params = kdf_params
+                .or_else(|| previous.kdf_params().map(KdfParams::with_new_salt))
+                .unwrap_or_default();
+
+            let mut slot = Keyslot::from_credentials(new, kdf_params, &key, &vault_id)?;
+            slot.label = previous.label.clone();
+            container.keyslots[index] = slot;
+            container
+        };
+
+        let backup_path = self.backup_before("passwd")?;
+        self.commit(&container, Some(&revision))?;
+        Ok(backup_path)
+    }
+
+    /// Re-wrap the password keyslot with Argon2id if its KDF settings are weak
+    ///
+    /// Returns whether an upgrade happened. A backup is taken before rewriting.
+    pub fn upgrade_weak_kdf<'a>(&self, credentials: impl Into<Credentials<'a>>) -> Result<bool> {
+        let credentials = credentials.into();
+        let _lock = self.lock()?;
+        let (mut container, revision) = self.read()?;
+
+        let has_weak_slot = container
+            .keyslots
+            .iter()
+            .filter(|s| s.kind == credentials.kind())
+            .any(|s| s.kdf_params().map(KdfParams::is_weak).unwrap_or(false));
+        if !has_weak_slot {
+            return Ok(false);
+        }
+
+        let (index, key) = container.unlock_password_slot(credentials)?;
+        let previous = &container.keyslots[index];
+        if !previous
+            .kdf_params()
+            .map(KdfParams::is_weak)
+            .unwrap_or(false)
+        {
+            return Ok(false);
+        }
+
+        let label = previous.label.clone();
+        if container.needs_upgrade() {
+            container.upgrade(&key)?;
+        }
+
+        self.backup_before("kdf-upgrade")?;
+        let vault_id = container.vault_id_or_new();
+        let mut slot =
+            Keyslot::from_credentials(credentials, KdfParams::argon2(), &key, &vault_id)?;
+        slot.label = label;
+        container.keyslots[index] = slot;
+        self.commit(&container, Some(&revision))?;
+
+        Ok(true)
+    }
+
+    /// Load and decrypt the vault with an SSH key held in ssh-agent
+    pub fn unlock_with_ssh_agent(
+        &self,
+        agent: &mut SshAgent,
+        fingerprint: Option<&str>,
+    ) -> Result<(Vault, EncryptionKey)> {
+        let container = self.load_container()?;
+        let (_, key) = container.unlock_ssh_slot(agent, fingerprint)?;
+        let vault = container.open(&key)?;
+        Ok((vault, key))
+    }
+
+    /// Replace the recovery keyslot with one opened by `recovery_key`
+    ///
+    /// Shares of an earlier recovery key stop working.
+    pub fn set_recovery_key<'a>(
+        &self,
+        credentials: impl Into<Credentials<'a>>,
+        recovery_key: &EncryptionKey,
+        label: Option<String>,
+    ) -> Result<Keyslot> {
+        let _lock = self.lock()?;
+        let (mut container, revision) = self.read()?;
+        if container.is_legacy() {
+            return Err(Error::Other(
+                "Vault uses the legacy format; save it once before adding keyslots".to_string(),
+            ));
+        }
+
+        let key = container.unlock_with_password(credentials)?;
+        if container.needs_upgrade() {
+            self.backup_before("migration")?;
+            container.upgrade(&key)?;
+        }
+
+        let vault_id = container.vault_id_or_new();
+        let mut slot = Keyslot::recovery(recovery_key, &key, &vault_id)?;
+        slot.label = label;
+        container
+            .keyslots
+            .retain(|s| s.kind != KeyslotKind::Recovery);
+        container.add_keyslot(slot.clone());
+        self.commit(&container, Some(&revision))?;
+
+        Ok(slot)
+    }
+
+    /// Load and decrypt the vault with a recovery key
+    pub fn unlock_with_recovery_key(
+        &self,
+        recovery_key: &EncryptionKey,
+    ) -> Result<(Vault, EncryptionKey)> {
+        let container = self.load_container()?;
+        let (_, key) = container.unlock_recovery_slot(recovery_key)?;
+        let vault = container.open(&key)?;
+        Ok((vault, key))
+    }
+
+    /// Replace all password keyslots after a recovery, returning the backup path
+    ///
+    /// The recovery keyslot and the payload are kept, so the same shares keep working.
+    /// Without `kdf_params` the new slot reuses the KDF settings of the old one.
+    pub fn reset_password<'a>(
+        &self,
+        recovery_key: &EncryptionKey,
+        new: impl Into<Credentials<'a>>,
+        kdf_params: Option<KdfParams>,
+    ) -> Result<PathBuf> {
+        let _lock = self.lock()?;
+        let (mut container, revision) = self.read()?;
+
+        let (_, key) = container.unlock_recovery_slot(recovery_key)?;
+        if container.needs_upgrade() {
+            container.upgrade(&key)?;
+        }
+
+        let is_password_slot =
+            |s: &Keyslot| matches!(s.kind, KeyslotKind::Password | KeyslotKind::PasswordKeyfile);
+        let vault_id = container.vault_id_or_new();
+        let kdf_params = kdf_params
+            .or_else(|| {
+                container
+                    .keyslots
+                    .iter()
+                    .find(|s| is_password_slot(s))
+                    .and_then(|s| s.kdf_params().map(KdfParams::with_new_salt))
+            })
+            .unwrap_or_default();
+        let slot = Keyslot::from_credentials(new.into(), kdf_params, &key, &vault_id)?;
+        container.keyslots.retain(|s| !is_password_slot(s));
+        container.add_keyslot(slot);
+
+        let backup_path = self.backup_before("reset")?;
+        self.commit(&container, Some(&revision))?;
+        Ok(backup_path)
+    }
+
+    /// Remove a keyslot from an existing vault, authorised by the master password
+    pub fn remove_keyslot<'a>(
+        &self,
+        credentials: impl Into<Credentials<'a>>,
+        id: &str,
+    ) -> Result<Keyslot> {
+        let _lock = self.lock()?;
+        let (mut container, revision) = self.read()?;
+        container.unlock_with_password(credentials)?;
+
+        let removed = container.remove_keyslot(id)?;
+        self.commit(&container, Some(&revision))?;
+
+        Ok(removed)
+    }
+
     /// Try to auto-sync if configured
     #[cfg(feature = "git")]
     fn try_auto_sync(&self, _vault: &Vault) -> Result<()> {
-        use crate::config::ConfigManager;
         use crate::git_sync::GitSync;
 
-        // Load sync config from standalone config file
-        if let Ok(config_manager) = ConfigManager::new() {
-            if let Ok(config_file) = config_manager.load() {
-                if let Some(sync_config) = config_file.sync_config {
-                    if sync_config.enabled && sync_config.mode.is_push_on_change() {
-                        // Get vault directory (parent of vault file)
-                        if let Some(vault_dir) = self.vault_path.parent() {
-                            // Try to sync, but don't fail the save operation if sync fails
-                            if let Ok(git_sync) = GitSync::init(vault_dir) {
-                                let _ = git_sync.auto_commit_push("Auto-sync: vault updated");
-                            }
-                        }
+        // Load sync config of this vault from standalone config file
+        let Some(vault_dir) = self.vault_path.parent() else {
+            return Ok(());
+        };
+        if let Ok(config_file) = ConfigManager::new().and_then(|m| m.load()) {
+            if let Some(sync_config) = config_file.sync_config_for(vault_dir) {
+                if sync_config.enabled && sync_config.mode.is_push_on_change() {
+                    // Try to sync, but don't fail the save operation if sync fails
+                    if let Ok(git_sync) = GitSync::init(vault_dir) {
+                        let _ = git_sync.auto_commit_push("Auto-sync: vault updated");
                     }
                 }
             }
@@ -159,92 +1037,179 @@ impl VaultStorage {
     }
 
     /// Load and decrypt a vault
-    pub fn load(&self, password: &str) -> Result<Vault> {
-        if !self.exists() {
-            return Err(Error::VaultNotFound);
+    pub fn load<'a>(&self, credentials: impl Into<Credentials<'a>>) -> Result<Vault> {
+        self.load_container()?.open_with_password(credentials)
+    }
+
+    /// Load a vault and return its data key for later key-based saves
+    ///
+    /// Older containers and payloads are migrated to the current format in
+    /// place after taking a backup; legacy vaults gain a data key and keyslot.
+    pub fn unlock<'a>(
+        &self,
+        credentials: impl Into<Credentials<'a>>,
+    ) -> Result<(Vault, EncryptionKey)> {
+        let credentials = credentials.into();
+        let (container, revision) = self.read()?;
+
+        if container.is_legacy() {
+            let vault_id = EncryptedVault::new_vault_id();
+            let (vault, key, slot) = Self::upgrade_legacy(&container, credentials, &vault_id)?;
+            let upgraded = EncryptedVault::seal(&vault, &key, &vault_id, vec![slot])?;
+            self.write_upgrade(&upgraded, revision)?;
+            return Ok((vault, key));
         }
 
-        // Read the encrypted vault file
-        let contents = fs::read_to_string(&self.vault_path)?;
-        let encrypted_vault: EncryptedVault = serde_json::from_str(&contents)?;
+        let key = container.unlock_with_password(credentials)?;
+        let (vault, migrated) = container.open_and_migrate(&key)?;
+        if migrated || container.needs_upgrade() {
+            let vault_id = container.vault_id_or_new();
+            let upgraded = EncryptedVault::seal(&vault, &key, &vault_id, container.keyslots)?;
+            self.write_upgrade(&upgraded, revision)?;
+        } else {
+            self.remember(revision);
+        }
 
-        // Decrypt the data
-        let decrypted = decrypt_with_password(&encrypted_vault.encrypted_data, password)?;
+        Ok((vault, key))
+    }
 
-        // Deserialize the vault
-        let vault: Vault = serde_json::from_slice(&decrypted)?;
+    /// Replace a container read at `revision` with its migrated form
+    ///
+    /// Skipped if another writer changed the file meanwhile; the next unlock
+    /// will migrate again.
+    fn write_upgrade(&self, upgraded: &EncryptedVault, revision: Revision) -> Result<()> {
+        let _lock = self.lock()?;
+        let (_, current) = self.read()?;
+        if current != revision {
+            self.remember(revision);
+            return Ok(());
+        }
 
-        Ok(vault)
+        self.backup_before("migration")?;
+        self.remember(revision.clone());
+        self.commit(upgraded, Some(&revision))
     }
 
-    /// Create a backup of the vault
-    pub fn backup(&self) -> Result<PathBuf> {
-        if !self.exists() {
-            return Err(Error::VaultNotFound);
+    /// Attachment blobs of this vault
+    pub fn attachments(&self) -> AttachmentStore {
+        AttachmentStore::with_dir(
+            self.vault_path
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+                .join(ATTACHMENTS_DIR),
+        )
+    }
+
+    /// Local PIN unlock file of this vault
+    pub fn pin_store(&self) -> PinStore {
+        PinStore::with_path(self.sibling(".pin"))
+    }
+
+    /// Enable PIN unlock with the data key of the unlocked vault
+    pub fn enable_pin(&self, pin: &str, key: &EncryptionKey, policy: &PinPolicy) -> Result<()> {
+        let vault_id = self.bound_vault_id(key)?;
+        self.pin_store().enable(pin, key, &vault_id, policy)
+    }
+
+    /// Re-arm PIN unlock after a full unlock, returning whether PIN unlock is enabled
+    pub fn rearm_pin(&self, key: &EncryptionKey, policy: &PinPolicy) -> Result<bool> {
+        let store = self.pin_store();
+        if !store.exists() {
+            return Ok(false);
         }
+        let vault_id = self.bound_vault_id(key)?;
+        store.rearm(key, &vault_id, policy)
+    }
 
-        let backup_name = format!(
-            "{}.{}.backup",
-            VAULT_FILE_NAME,
-            chrono::Utc::now().timestamp()
-        );
+    /// Load and decrypt the vault with the quick unlock PIN
+    pub fn unlock_with_pin(&self, pin: &str, policy: &PinPolicy) -> Result<(Vault, EncryptionKey)> {
+        let store = self.pin_store();
+        let key = store.unlock(pin, policy)?;
+        match self.load_with_key(&key) {
+            Ok(vault) => Ok((vault, key)),
+            Err(Error::KeyMismatch) => {
+                // The vault was re-keyed elsewhere since the PIN was set
+                store.disable()?;
+                Err(Error::PinUnavailable(
+                    "the vault key changed; unlock with the master password".to_string(),
+                ))
+            }
+            Err(e) => Err(e),
+        }
+    }
 
-        let backup_path = self
-            .vault_path
-            .parent()
-            .unwrap_or_else(|| Path::new("."))
-            .join("backups")
-            .join(backup_name);
+    /// Vault id of a format 3 vault opened by `key`
+    fn bound_vault_id(&self, key: &EncryptionKey) -> Result<String> {
+        let container = self.load_container()?;
+        if !container.matches_key(key) {
+            return Err(Error::KeyMismatch);
+        }
+        container.vault_id.ok_or_else(|| {
+            Error::Other(
+                "Vault uses an older format; save it once before enabling a PIN".to_string(),
+            )
+        })
+    }
 
-        // Ensure backup directory exists
-        if let Some(parent) = backup_path.parent() {
-            fs::create_dir_all(parent)?;
+    /// Load a vault with a cached data key
+    pub fn load_with_key(&self, key: &EncryptionKey) -> Result<Vault> {
+        let container = self.load_container()?;
+        if container.is_legacy() {
+            return Err(Error::KeyMismatch);
         }
+        container.open(key)
+    }
 
-        // Copy vault to backup
-        fs::copy(&self.vault_path, &backup_path)?;
+    /// Backups of this vault
+    pub fn backups(&self) -> BackupStore {
+        BackupStore::with_dir(
+            self.vault_path
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+                .join(BACKUPS_DIR),
+        )
+    }
 
-        Ok(backup_path)
+    /// Create a backup of the vault
+    pub fn backup(&self) -> Result<PathBuf> {
+        self.take_backup(None)
     }
 
-    /// Rotate backups, keeping only the most recent N backups
-    pub fn rotate_backups(&self, keep_count: usize) -> Result<()> {
-        let backup_dir = self
-            .vault_path
-            .parent()
-            .unwrap_or_else(|| Path::new("."))
-            .join("backups");
+    /// Back up the vault before `operation` rewrites or replaces it
+    ///
+    /// Old backups are pruned by the configured [`BackupPolicy`].
+    pub fn backup_before(&self, operation: &str) -> Result<PathBuf> {
+        self.take_backup(Some(operation))
+    }
 
-        if !backup_dir.exists() {
-            return Ok(());
+    fn take_backup(&self, reason: Option<&str>) -> Result<PathBuf> {
+        if !self.exists() {
+            return Err(Error::VaultNotFound);
         }
 
-        // List all backup files
-        let mut backups: Vec<_> = fs::read_dir(&backup_dir)?
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| {
-                entry
-                    .file_name()
-                    .to_string_lossy()
-                    .starts_with(VAULT_FILE_NAME)
-                    && entry.file_name().to_string_lossy().ends_with(".backup")
-            })
-            .collect();
-
-        // Sort by modification time (newest first)
-        backups.sort_by_key(|entry| {
-            entry
-                .metadata()
-                .and_then(|m| m.modified())
-                .ok()
-                .map(std::cmp::Reverse)
-        });
+        let store = self.backups();
+        let backup = store.create(&self.vault_path, reason)?;
 
-        // Remove old backups
-        for entry in backups.iter().skip(keep_count) {
-            fs::remove_file(entry.path())?;
+        // Pruning is housekeeping; a failure must not block the operation
+        let policy = ConfigManager::new()
+            .and_then(|manager| manager.load())
+            .ok()
+            .and_then(|config| config.backup_policy)
+            .unwrap_or_default();
+        if let Err(e) = store.prune(&policy) {
+            tracing::warn!("Failed to prune old backups: {}", e);
         }
 
+        Ok(backup.path)
+    }
+
+    /// Rotate backups, keeping only the most recent N backups
+    pub fn rotate_backups(&self, keep_count: usize) -> Result<()> {
+        self.backups().prune(&BackupPolicy {
+            keep_last: keep_count,
+            keep_daily: 0,
+            keep_weekly: 0,
+        })?;
         Ok(())
     }
 
@@ -266,7 +1231,7 @@ impl Default for VaultStorage {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::Item;
+    use crate::keyfile::Keyfile;
     use tempfile::tempdir;
 
     #[test]
@@ -298,7 +1263,24 @@ mod tests {
         storage.save(&vault, "correct_password").unwrap();
 
         let result = storage.load("wrong_password");
-        assert!(result.is_err());
+        assert!(matches!(result, Err(Error::InvalidPassword)));
+    }
+
+    #[test]
+    fn test_corruption_is_not_a_wrong_password() {
+        let temp_dir = tempdir().unwrap();
+        let vault_path = temp_dir.path().join("test.vault");
+        let storage = VaultStorage::with_path(&vault_path);
+
+        storage.save(&Vault::new(), "password").unwrap();
+        let mut container = storage.load_container().unwrap();
+        container.encrypted_data.ciphertext = container.encrypted_data.nonce.clone();
+        storage.write_container(&container).unwrap();
+
+        assert!(matches!(
+            storage.load("password"),
+            Err(Error::Decryption(_))
+        ));
     }
 
     #[test]
@@ -314,4 +1296,589 @@ mod tests {
         assert!(backup_path.exists());
         assert!(backup_path.to_string_lossy().contains(".backup"));
     }
+
+    #[test]
+    fn test_change_password_keeps_payload_and_kdf() {
+        let temp_dir = tempdir().unwrap();
+        let vault_path = temp_dir.path().join("test.vault");
+        let storage = VaultStorage::with_path(&vault_path);
+
+        let mut vault = Vault::new();
+        vault.add_item(Item::new_login("github"));
+        storage.save(&vault, "old").unwrap();
+        let before = storage.load_container().unwrap();
+
+        let backup = storage.change_password("old", "new", None, false).unwrap();
+        assert!(backup.exists());
+
+        let after = storage.load_container().unwrap();
+        assert_eq!(
+            before.encrypted_data.ciphertext,
+            after.encrypted_data.ciphertext
+        );
+        assert_eq!(
+            after.keyslots[0].kdf_params().unwrap().iterations,
+            before.keyslots[0].kdf_params().unwrap().iterations
+        );
+        assert!(storage.load("old").is_err());
+        assert_eq!(storage.load("new").unwrap().items.len(), 1);
+    }
+
+    #[test]
+    fn test_keyfile_is_required() {
+        let temp_dir = tempdir().unwrap();
+        let storage = VaultStorage::with_path(temp_dir.path().join("test.vault"));
+        let keyfile = Keyfile::generate();
+
+        let mut vault = Vault::new();
+        vault.add_item(Item::new_login("github"));
+        storage
+            .save(&vault, Credentials::new("password", Some(&keyfile)))
+            .unwrap();
+
+        assert!(matches!(
+            storage.load("password"),
+            Err(Error::KeyfileRequired)
+        ));
+        assert!(matches!(
+            storage.load(Credentials::new("password", Some(&Keyfile::generate()))),
+            Err(Error::Keyfile(_))
+        ));
+        assert!(matches!(
+            storage.load(Credentials::new("wrong", Some(&keyfile))),
+            Err(Error::InvalidPassword)
+        ));
+
+        let (vault, _) = storage
+            .unlock(Credentials::new("password", Some(&keyfile)))
+            .unwrap();
+        assert_eq!(vault.items.len(), 1);
+    }
+
+    #[test]
+    fn test_keyfile_rotation_and_removal() {
+        let temp_dir = tempdir().unwrap();
+        let storage = VaultStorage::with_path(temp_dir.path().join("test.vault"));
+        let (old, new) = (Keyfile::generate(), Keyfile::generate());
+
+        storage.save(&Vault::new(), "password").unwrap();
+        assert!(matches!(
+            storage.load(Credentials::new("password", Some(&old))),
+            Err(Error::Keyfile(_))
+        ));
+
+        storage
+            .change_password(
+                "password",
+                Credentials::new("password", Some(&old)),
+                None,
+                false,
+            )
+            .unwrap();
+        storage
+            .change_password(
+                Credentials::new("password", Some(&old)),
+                Credentials::new("password", Some(&new)),
+                None,
+                false,
+            )
+            .unwrap();
+        assert!(storage
+            .load(Credentials::new("password", Some(&old)))
+            .is_err());
+        assert!(storage
+            .load(Credentials::new("password", Some(&new)))
+            .is_ok());
+
+        storage
+            .change_password(
+                Credentials::new("password", Some(&new)),
+                "password",
+                None,
+                false,
+            )
+            .unwrap();
+        assert!(storage.load("password").is_ok());
+    }
+
+    #[test]
+    fn test_recovery_key_resets_password() {
+        let temp_dir = tempdir().unwrap();
+        let storage = VaultStorage::with_path(temp_dir.path().join("test.vault"));
+
+        let mut vault = Vault::new();
+        vault.add_item(Item::new_login("github"));
+        storage.save(&vault, "forgotten").unwrap();
+
+        let recovery_key = generate_key();
+        assert!(matches!(
+            storage.unlock_with_recovery_key(&recovery_key),
+            Err(Error::Recovery(_))
+        ));
+        storage
+            .set_recovery_key("forgotten", &recovery_key, None)
+            .unwrap();
+
+        let shares = crate::recovery::split(&recovery_key, 5, 3).unwrap();
+        let rebuilt = crate::recovery::combine(&shares[2..]).unwrap();
+        let (vault, _) = storage.unlock_with_recovery_key(&rebuilt).unwrap();
+        assert_eq!(vault.items.len(), 1);
+
+        storage.reset_password(&rebuilt, "new", None).unwrap();
+        assert!(storage.load("forgotten").is_err());
+        assert_eq!(storage.load("new").unwrap().items.len(), 1);
+        assert!(storage.unlock_with_recovery_key(&recovery_key).is_ok());
+
+        // A new split replaces the old recovery key
+        storage
+            .set_recovery_key("new", &generate_key(), None)
+            .unwrap();
+        assert!(storage.unlock_with_recovery_key(&recovery_key).is_err());
+    }
+
+    #[test]
+    fn test_reset_password_keeps_kdf() {
+        let temp_dir = tempdir().unwrap();
+        let storage = VaultStorage::with_path(temp_dir.path().join("test.vault"));
+
+        let mut params = KdfParams::argon2();
+        params.memory_kb = Some(8);
+        storage
+            .save_with_kdf(&Vault::new(), "forgotten", params.clone())
+            .unwrap();
+        let recovery_key = generate_key();
+        storage
+            .set_recovery_key("forgotten", &recovery_key, None)
+            .unwrap();
+
+        storage.reset_password(&recovery_key, "new", None).unwrap();
+        let container = storage.load_container().unwrap();
+        let slot = container
+            .keyslots
+            .iter()
+            .find(|s| s.kind == KeyslotKind::Password)
+            .unwrap();
+        let reset = slot.kdf_params().unwrap();
+        assert!(matches!(reset.algorithm, KdfAlgorithm::Argon2id));
+        assert_eq!(reset.memory_kb, Some(8));
+        assert_ne!(reset.salt, params.salt);
+        assert!(storage.load("new").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ssh_agent_keyslot_unlocks() {
+        use crate::ssh_agent::test_agent::TestAgent;
+
+        let Some(test_agent) = TestAgent::spawn() else {
+            return;
+        };
+        let mut agent = SshAgent::connect(&test_agent.socket).unwrap();
+
+        let temp_dir = tempdir().unwrap();
+        let storage = VaultStorage::with_path(temp_dir.path().join("test.vault"));
+        let mut vault = Vault::new();
+        vault.add_item(Item::new_login("github"));
+        storage.save(&vault, "password").unwrap();
+
+        assert!(matches!(
+            storage.unlock_with_ssh_agent(&mut agent, None),
+            Err(Error::SshAgent(_))
+        ));
+
+        let identity = agent.identity(&test_agent.fingerprint).unwrap();
+        let slot = storage
+            .add_keyslot("password", |key, vault_id| {
+                Keyslot::ssh_agent(&mut agent, &identity, key, vault_id)
+            })
+            .unwrap();
+        assert_eq!(
+            slot.ssh_key.as_deref(),
+            Some(test_agent.fingerprint.as_str())
+        );
+
+        let (vault, _) = storage
+            .unlock_with_ssh_agent(&mut agent, Some(&test_agent.fingerprint))
+            .unwrap();
+        assert_eq!(vault.items.len(), 1);
+        assert!(storage.unlock_with_ssh_agent(&mut agent, None).is_ok());
+        assert!(storage
+            .unlock_with_ssh_agent(&mut agent, Some("SHA256:unknown"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_change_password_rekey_drops_other_slots() {
+        let temp_dir = tempdir().unwrap();
+        let vault_path = temp_dir.path().join("test.vault");
+        let storage = VaultStorage::with_path(&vault_path);
+
+        storage.save(&Vault::new(), "old").unwrap();
+        storage
+            .add_keyslot("old", |key, vault_id| {
+                Keyslot::password("other", KdfParams::default(), key, vault_id)
+            })
+            .unwrap();
+        let before = storage.load_container().unwrap();
+
+        storage.change_password("old", "new", None, true).unwrap();
+
+        let after = storage.load_container().unwrap();
+        assert_eq!(after.keyslots.len(), 1);
+        assert_ne!(
+            before.encrypted_data.ciphertext,
+            after.encrypted_data.ciphertext
+        );
+        assert!(storage.load("other").is_err());
+        assert!(storage.load("new").is_ok());
+    }
+
+    #[test]
+    fn test_upgrade_weak_kdf() {
+        let temp_dir = tempdir().unwrap();
+        let vault_path = temp_dir.path().join("test.vault");
+        let storage = VaultStorage::with_path(&vault_path);
+
+        // New vaults are created with settings that are not weak
+        storage.save(&Vault::new(), "password").unwrap();
+        assert!(!storage.upgrade_weak_kdf("password").unwrap());
+
+        let mut weak = KdfParams::pbkdf2();
+        weak.iterations = 100_000;
+        storage
+            .save_with_kdf(&Vault::new(), "password", weak)
+            .unwrap();
+        assert!(storage.upgrade_weak_kdf("password").unwrap());
+
+        let container = storage.load_container().unwrap();
+        let params = container.keyslots[0].kdf_params().unwrap();
+        assert!(matches!(params.algorithm, KdfAlgorithm::Argon2id));
+        assert!(!storage.upgrade_weak_kdf("password").unwrap());
+        assert!(storage.load("password").is_ok());
+    }
+
+    #[test]
+    fn test_save_with_key_keeps_kdf() {
+        let temp_dir = tempdir().unwrap();
+        let vault_path = temp_dir.path().join("test.vault");
+        let storage = VaultStorage::with_path(&vault_path);
+
+        storage
+            .save_with_kdf(&Vault::new(), "password", KdfParams::argon2())
+            .unwrap();
+
+        let (mut vault, key) = storage.unlock("password").unwrap();
+        vault.add_item(Item::new_login("edited"));
+        storage.save_with_key(&vault, &key).unwrap();
+        storage.save(&vault, "password").unwrap();
+
+        let container = storage.load_container().unwrap();
+        assert!(matches!(
+            container.keyslots[0].kdf_params().unwrap().algorithm,
+            KdfAlgorithm::Argon2id
+        ));
+        assert_eq!(storage.load_with_key(&key).unwrap().items.len(), 1);
+
+        let other_key = crate::crypto::generate_key();
+        assert!(matches!(
+            storage.save_with_key(&vault, &other_key),
+            Err(Error::KeyMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_legacy_vault_loads_and_upgrades() {
+        use crate::crypto::encrypt_with_password;
+
+        let temp_dir = tempdir().unwrap();
+        let vault_path = temp_dir.path().join("test.vault");
+        let storage = VaultStorage::with_path(&vault_path);
+
+        let mut vault = Vault::new();
+        vault.add_item(Item::new_login("legacy"));
+        let json = serde_json::to_vec(&vault).unwrap();
+        let legacy = EncryptedVault {
+            version: "1.0.0".to_string(),
+            vault_id: None,
+            keyslots: Vec::new(),
+            key_check: None,
+            encrypted_data: encrypt_with_password(&json, "password").unwrap(),
+        };
+        storage.write_container(&legacy).unwrap();
+
+        let loaded = storage.load("password").unwrap();
+        assert_eq!(loaded.items[0].name, "legacy");
+
+        let (_, key) = storage.unlock("password").unwrap();
+        let container = storage.load_container().unwrap();
+        assert_eq!(container.version, CONTAINER_VERSION);
+        assert!(container.vault_id.is_some());
+        assert_eq!(container.keyslots.len(), 1);
+        assert_eq!(container.keyslots[0].vault_id, container.vault_id);
+        assert_eq!(
+            container.keyslots[0].kdf_params().unwrap().iterations,
+            legacy.encrypted_data.kdf_params.unwrap().iterations
+        );
+        assert_eq!(storage.load_with_key(&key).unwrap().items.len(), 1);
+    }
+
+    #[test]
+    fn test_legacy_argon2_vault_needs_opt_in() {
+        use crate::crypto::{derive_key, encrypt};
+
+        let temp_dir = tempdir().unwrap();
+        let storage = VaultStorage::with_path(temp_dir.path().join("test.vault"));
+
+        // Stands in for a legacy Argon2id container: derived with 1024x the cost
+        let mut params = KdfParams::argon2();
+        params.memory_kb = Some(8);
+        params.iterations = 1;
+        let mut legacy_params = params.clone();
+        legacy_params.memory_kb = Some(8 * 1024);
+        let key = derive_key("password", &legacy_params).unwrap();
+
+        let mut vault = Vault::new();
+        vault.add_item(Item::new_login("legacy"));
+        let json = serde_json::to_vec(&vault).unwrap();
+        let mut encrypted_data = encrypt(&json, &key).unwrap();
+        encrypted_data.kdf_params = Some(params);
+        let legacy = EncryptedVault {
+            version: "1.0.0".to_string(),
+            vault_id: None,
+            keyslots: Vec::new(),
+            key_check: None,
+            encrypted_data,
+        };
+        storage.write_container(&legacy).unwrap();
+
+        // Without the opt-in the legacy cost is never tried, so even the
+        // right password fails, with a hint instead of a plain rejection
+        for password in ["wrong", "password"] {
+            assert!(matches!(
+                storage.unlock(password),
+                Err(Error::LegacyArgon2Password(1))
+            ));
+        }
+        assert!(matches!(
+            storage.unlock(Credentials::new("wrong", None).with_legacy_argon2(true)),
+            Err(Error::InvalidPassword)
+        ));
+
+        let (_, key) = storage
+            .unlock(Credentials::new("password", None).with_legacy_argon2(true))
+            .unwrap();
+        let container = storage.load_container().unwrap();
+        assert!(!container.is_legacy());
+        assert_eq!(
+            container.keyslots[0].kdf_params().unwrap().memory_kb,
+            Some(8)
+        );
+        assert_eq!(storage.load_with_key(&key).unwrap().items.len(), 1);
+        assert_eq!(storage.unlock("password").unwrap().0.items.len(), 1);
+    }
+
+    #[test]
+    fn test_keyslots_share_payload() {
+        let temp_dir = tempdir().unwrap();
+        let vault_path = temp_dir.path().join("test.vault");
+        let storage = VaultStorage::with_path(&vault_path);
+
+        storage.save(&Vault::new(), "first").unwrap();
+        let before = storage.load_container().unwrap();
+
+        let slot = storage
+            .add_keyslot("first", |key, vault_id| {
+                Keyslot::password("second", KdfParams::default(), key, vault_id)
+            })
+            .unwrap();
+
+        let after = storage.load_container().unwrap();
+        assert_eq!(after.keyslots.len(), 2);
+        assert_eq!(
+            before.encrypted_data.ciphertext,
+            after.encrypted_data.ciphertext
+        );
+        assert!(storage.load("second").is_ok());
+
+        let first_id = before.keyslots[0].id.clone();
+        storage.remove_keyslot("second", &first_id).unwrap();
+        assert!(storage.load("first").is_err());
+        assert!(storage.remove_keyslot("second", &slot.id).is_err());
+    }
+
+    #[test]
+    fn test_header_is_bound_to_payload() {
+        let temp_dir = tempdir().unwrap();
+        let vault_path = temp_dir.path().join("test.vault");
+        let storage = VaultStorage::with_path(&vault_path);
+
+        storage.save(&Vault::new(), "password").unwrap();
+        let (_, key) = storage.unlock("password").unwrap();
+        let original = storage.load_container().unwrap();
+
+        // Claiming an older, unauthenticated format must not bypass the binding
+        let mut downgraded = storage.load_container().unwrap();
+        downgraded.version = "2.0.0".to_string();
+        assert!(matches!(downgraded.open(&key), Err(Error::Decryption(_))));
+
+        // Swapping the vault identity breaks both the payload and the keyslot
+        let mut renamed = storage.load_container().unwrap();
+        renamed.vault_id = Some(EncryptedVault::new_vault_id());
+        assert!(matches!(renamed.open(&key), Err(Error::Decryption(_))));
+        assert!(matches!(
+            renamed.unlock_with_password("password"),
+            Err(Error::Decryption(_))
+        ));
+
+        assert!(original.open(&key).is_ok());
+    }
+
+    #[test]
+    fn test_newer_container_is_refused() {
+        let temp_dir = tempdir().unwrap();
+        let vault_path = temp_dir.path().join("test.vault");
+        let storage = VaultStorage::with_path(&vault_path);
+
+        storage.save(&Vault::new(), "password").unwrap();
+        let (vault, key) = storage.unlock("password").unwrap();
+
+        let mut container = storage.load_container().unwrap();
+        container.version = "4.0.0".to_string();
+        storage.write_container(&container).unwrap();
+
+        assert!(matches!(
+            storage.load("password"),
+            Err(Error::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            storage.save_with_key(&vault, &key),
+            Err(Error::UnsupportedVersion(_))
+        ));
+        assert!(matches!(
+            storage.save(&vault, "password"),
+            Err(Error::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_stale_write_is_rejected() {
+        let temp_dir = tempdir().unwrap();
+        let vault_path = temp_dir.path().join("test.vault");
+        VaultStorage::with_path(&vault_path)
+            .save(&Vault::new(), "password")
+            .unwrap();
+
+        let first = VaultStorage::with_path(&vault_path);
+        let second = VaultStorage::with_path(&vault_path);
+        let (mut first_vault, key) = first.unlock("password").unwrap();
+        let (mut second_vault, _) = second.unlock("password").unwrap();
+
+        second_vault.add_item(Item::new_login("from second"));
+        second.save_with_key(&second_vault, &key).unwrap();
+
+        first_vault.add_item(Item::new_login("from first"));
+        assert!(matches!(
+            first.save_with_key(&first_vault, &key),
+            Err(Error::StaleWrite)
+        ));
+        assert_eq!(second.load_with_key(&key).unwrap().items.len(), 1);
+
+        // Raw container writes are checked the same way
+        let container = second.load_container().unwrap();
+        assert!(matches!(
+            first.write_container(&container),
+            Err(Error::StaleWrite)
+        ));
+        second.write_container(&container).unwrap();
+
+        // Saving again after a reload succeeds
+        let mut reloaded = first.load_with_key(&key).unwrap();
+        reloaded.add_item(Item::new_login("from first"));
+        first.save_with_key(&reloaded, &key).unwrap();
+        assert_eq!(second.load_with_key(&key).unwrap().items.len(), 2);
+    }
+
+    #[test]
+    fn test_update_with_key_keeps_concurrent_changes() {
+        let temp_dir = tempdir().unwrap();
+        let vault_path = temp_dir.path().join("test.vault");
+        let storage = VaultStorage::with_path(&vault_path);
+        storage.save(&Vault::new(), "password").unwrap();
+        let (_, key) = storage.unlock("password").unwrap();
+
+        let other = VaultStorage::with_path(&vault_path);
+        let mut vault = other.load_with_key(&key).unwrap();
+        vault.add_item(Item::new_login("other"));
+        other.save_with_key(&vault, &key).unwrap();
+
+        let (updated, ()) = storage
+            .update_with_key(&key, |vault| {
+                vault.add_item(Item::new_login("mine"));
+                Ok::<_, Error>(())
+            })
+            .unwrap();
+        assert_eq!(updated.items.len(), 2);
+
+        let failed = storage.update_with_key(&key, |vault| {
+            vault.items.clear();
+            Err::<(), _>(Error::Other("rejected".to_string()))
+        });
+        assert!(failed.is_err());
+        assert_eq!(storage.load_with_key(&key).unwrap().items.len(), 2);
+    }
+
+    #[test]
+    fn test_lock_is_exclusive_and_writes_are_atomic() {
+        let temp_dir = tempdir().unwrap();
+        let vault_path = temp_dir.path().join("test.vault");
+        let storage = VaultStorage::with_path(&vault_path);
+        storage.save(&Vault::new(), "password").unwrap();
+
+        let guard = storage.lock().unwrap();
+        let contender = File::open(storage.sibling(".lock")).unwrap();
+        assert!(contender.try_lock().is_err());
+        drop(guard);
+        assert!(contender.try_lock().is_ok());
+        contender.unlock().unwrap();
+
+        storage.save(&Vault::new(), "password").unwrap();
+        assert!(!storage.sibling(".tmp").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&vault_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_purge_expired_trash_removes_items_and_blobs() {
+        let temp_dir = tempdir().unwrap();
+        let storage = VaultStorage::with_path(temp_dir.path().join("vault.sf"));
+        storage.save(&Vault::new(), "password").unwrap();
+        let (mut vault, key) = storage.unlock("password").unwrap();
+
+        let mut old = Item::new_login("old");
+        let blob = storage.attachments().add("a.txt", b"contents").unwrap();
+        old.attachments.push(blob.clone());
+        old.deleted_date = Some(Utc::now() - Duration::days(31));
+        let mut recent = Item::new_login("recent");
+        recent.deleted_date = Some(Utc::now() - Duration::days(1));
+        vault.add_item(old);
+        vault.add_item(recent);
+        storage.save_with_key(&vault, &key).unwrap();
+
+        // A retention of 0 keeps everything
+        assert!(storage
+            .purge_expired_trash(&mut vault, &key, 0)
+            .unwrap()
+            .is_empty());
+
+        let purged = storage.purge_expired_trash(&mut vault, &key, 30).unwrap();
+        assert_eq!(purged.len(), 1);
+        assert_eq!(purged[0].name, "old");
+        assert!(!storage.attachments().contains(&blob));
+        assert_eq!(vault.items.len(), 1);
+        assert_eq!(storage.load_with_key(&key).unwrap().items[0].name, "recent");
+    }
 }
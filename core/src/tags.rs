@@ -0,0 +1,139 @@
+//! Item tags
+//!
+//! Tags are free-form labels such as `prod` or `rotate-q4`. They are kept
+//! per item; the set of tags in a vault is whatever its items carry, so
+//! renaming or merging tags rewrites the items.
+
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+
+use crate::errors::{Error, Result};
+use crate::models::{Item, Vault};
+
+/// Trim a tag, rejecting empty ones and ones with commas, which separate tags
+/// on the command line
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let tag = tag.trim();
+    if tag.is_empty() || tag.contains(',') {
+        return Err(Error::InvalidTag(tag.to_string()));
+    }
+    Ok(tag.to_string())
+}
+
+impl Item {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag.trim())
+    }
+
+    /// Add tags, returning whether any was new
+    pub fn add_tags<S: AsRef<str>>(&mut self, tags: &[S]) -> Result<bool> {
+        let mut added = false;
+        for tag in tags {
+            added |= self.tags.insert(normalize_tag(tag.as_ref())?);
+        }
+        Ok(added)
+    }
+}
+
+impl Vault {
+    /// Tags of the items outside the trash, with the number of items having each
+    pub fn tags(&self) -> BTreeMap<String, usize> {
+        let mut tags = BTreeMap::new();
+        for tag in self.active_items().flat_map(|i| &i.tags) {
+            *tags.entry(tag.clone()).or_insert(0) += 1;
+        }
+        tags
+    }
+
+    /// Rename a tag on every item; items that already have `new` keep one copy
+    ///
+    /// Returns the number of items changed.
+    pub fn rename_tag(&mut self, old: &str, new: &str) -> Result<usize> {
+        self.merge_tags(&[old], new)
+    }
+
+    /// Replace each of `tags` with `into` on every item, trashed ones included
+    ///
+    /// Returns the number of items changed.
+    pub fn merge_tags<S: AsRef<str>>(&mut self, tags: &[S], into: &str) -> Result<usize> {
+        let into = normalize_tag(into)?;
+        let tags = tags
+            .iter()
+            .map(|t| normalize_tag(t.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(self.retag(|item| {
+            let mut changed = false;
+            for tag in tags.iter().filter(|t| **t != into) {
+                changed |= item.tags.remove(tag);
+            }
+            if changed {
+                item.tags.insert(into.clone());
+            }
+            changed
+        }))
+    }
+
+    /// Remove a tag from every item, returning the number of items changed
+    pub fn remove_tag(&mut self, tag: &str) -> Result<usize> {
+        let tag = normalize_tag(tag)?;
+        Ok(self.retag(|item| item.tags.remove(&tag)))
+    }
+
+    fn retag(&mut self, mut update: impl FnMut(&mut Item) -> bool) -> usize {
+        let now = Utc::now();
+        let mut changed = 0;
+        for item in &mut self.items {
+            if update(item) {
+                item.revision_date = now;
+                changed += 1;
+            }
+        }
+        if changed > 0 {
+            self.sync_time = now;
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tagged(name: &str, tags: &[&str]) -> Item {
+        let mut item = Item::new_login(name);
+        item.add_tags(tags).unwrap();
+        item
+    }
+
+    #[test]
+    fn test_rename_merge_and_remove_tags() {
+        let mut vault = Vault::new();
+        vault.add_item(tagged("db", &["prod", " oncall "]));
+        vault.add_item(tagged("web", &["production", "oncall"]));
+        vault.add_item(tagged("ci", &["staging"]));
+        assert!(Item::new_login("x").add_tags(&["a,b"]).is_err());
+        assert!(vault.items[0].has_tag("oncall"));
+
+        let tags = vault.tags();
+        assert_eq!(tags["oncall"], 2);
+        assert_eq!(tags.len(), 4);
+
+        // Merging keeps a single copy on items that had both
+        assert_eq!(
+            vault.merge_tags(&["production", "prod"], "prod").unwrap(),
+            1
+        );
+        assert_eq!(vault.tags()["prod"], 2);
+        assert!(!vault.tags().contains_key("production"));
+
+        assert_eq!(vault.rename_tag("oncall", "shared-with-oncall").unwrap(), 2);
+        assert!(vault.items[1].has_tag("shared-with-oncall"));
+        assert!(vault.rename_tag("staging", " ").is_err());
+
+        assert_eq!(vault.remove_tag("staging").unwrap(), 1);
+        assert!(vault.items[2].tags.is_empty());
+        assert_eq!(vault.remove_tag("missing").unwrap(), 0);
+    }
+}
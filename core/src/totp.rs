@@ -4,6 +4,7 @@ use base32::Alphabet;
 use totp_rs::{Algorithm, Secret, TOTP};
 
 use crate::errors::{Error, Result};
+use crate::logging::redact;
 
 /// TOTP configuration
 pub struct TotpConfig {
@@ -19,7 +20,8 @@ impl TotpConfig {
     /// Create from otpauth:// URI
     pub fn from_uri(uri: &str) -> Result<Self> {
         let totp = TOTP::from_url(uri).map_err(|e| {
-            eprintln!("[TOTP] from_url failed for '{}': {:?}", uri, e);
+            // The error may quote the secret
+            tracing::debug!("otpauth URI rejected: {}", redact(&e.to_string()));
             Error::InvalidTotp
         })?;
 
@@ -77,17 +79,13 @@ impl TotpConfig {
         let secret = Secret::Encoded(self.secret.clone())
             .to_bytes()
             .map_err(|e| {
-                eprintln!(
-                    "[TOTP] Secret::to_bytes() failed for '{}': {:?}",
-                    self.secret, e
+                tracing::debug!(
+                    "TOTP secret is not valid base32: {}",
+                    redact(&e.to_string())
                 );
                 Error::InvalidTotp
             })?;
-
-        eprintln!(
-            "[TOTP] Secret decoded successfully, creating TOTP with {} bytes",
-            secret.len()
-        );
+        tracing::trace!(secret_len = secret.len(), "TOTP secret decoded");
 
         // Use new_unchecked to allow shorter secrets (like 10 bytes)
         // Many TOTP secrets in the wild are shorter than the 16-byte minimum of TOTP::new()